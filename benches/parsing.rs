@@ -0,0 +1,110 @@
+//! Benchmarks of the parsing and body reading hot paths.
+//!
+//! They need the private `__bench` feature, which exposes the internal functions they call:
+//! `cargo bench --features __bench`. A plain `cargo bench` skips them.
+
+use std::hint::black_box;
+
+use attohttpc::bench;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const BODY_LEN: usize = 10 * 1024 * 1024;
+
+fn response_head(headers: usize) -> Vec<u8> {
+    let mut head = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 0\r\n".to_vec();
+    for i in 0..headers {
+        head.extend(format!("X-Header-{i}: some header value {i}\r\n").as_bytes());
+    }
+    head.extend(b"\r\n");
+    head
+}
+
+fn chunked_body(chunk_len: usize) -> Vec<u8> {
+    let chunk = vec![b'x'; chunk_len];
+    let mut body = Vec::with_capacity(BODY_LEN + BODY_LEN / chunk_len * 10);
+    for _ in 0..BODY_LEN / chunk_len {
+        body.extend(format!("{chunk_len:x}\r\n").as_bytes());
+        body.extend(&chunk);
+        body.extend(b"\r\n");
+    }
+    body.extend(b"0\r\n\r\n");
+    body
+}
+
+fn bench_parse_response_head(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_response_head");
+    for (name, headers) in [("typical", 8), ("100_headers", 100)] {
+        let head = response_head(headers);
+        group.throughput(Throughput::Bytes(head.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &head, |b, head| {
+            b.iter(|| bench::parse_response_head(black_box(head), 128).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_chunked_reader(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunked_reader");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(BODY_LEN as u64));
+    for (name, chunk_len) in [("1KiB", 1024), ("64KiB", 64 * 1024)] {
+        let body = chunked_body(chunk_len);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &body, |b, body| {
+            b.iter(|| bench::read_chunked(black_box(body)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "__compress")]
+fn bench_gzip_decode(c: &mut Criterion) {
+    let body: Vec<u8> = (0..BODY_LEN).map(|i| (i % 64) as u8 + b' ').collect();
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+        bench::gzip(&body).len()
+    )
+    .into_bytes();
+    response.extend(bench::gzip(&body));
+
+    let request = attohttpc::get("http://localhost").prepare();
+    let mut group = c.benchmark_group("compressed_reader");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(BODY_LEN as u64));
+    group.bench_function("gzip", |b| {
+        b.iter_batched(
+            || response.clone(),
+            |response| bench::read_response(&request, response).unwrap(),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "__compress"))]
+fn bench_gzip_decode(_: &mut Criterion) {}
+
+fn bench_write_request(c: &mut Criterion) {
+    let mut request = attohttpc::post("http://localhost:8080/api/items?page=2")
+        .header("Authorization", "Bearer 0123456789abcdef")
+        .header("X-Request-Id", "c0ffee")
+        .text("{\"name\":\"item\"}")
+        .prepare();
+    let mut buf = Vec::new();
+
+    c.bench_function("write_request", |b| {
+        b.iter(|| {
+            buf.clear();
+            bench::write_request(&mut request, &mut buf).unwrap();
+            black_box(buf.len())
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_parse_response_head,
+    bench_chunked_reader,
+    bench_gzip_decode,
+    bench_write_request
+);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! Download a file to `path`, resuming from `path.part` if a previous download was interrupted, and
+//! verifying the SHA-256 digest of the whole file when one is given.
+//!
+//! Usage: download <url> <path> [sha256]
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::time::Duration;
+
+use attohttpc::header::RANGE;
+use attohttpc::{ErrorKind, Result, StatusCode};
+use sha2::{Digest, Sha256};
+
+fn main() -> Result {
+    env_logger::init();
+
+    let mut args = env::args().skip(1);
+    let url = args.next().expect("missing url");
+    let path = args.next().expect("missing path");
+    let expected = args.next();
+
+    let part = format!("{path}.part");
+    let mut file = OpenOptions::new().create(true).append(true).open(&part)?;
+    let offset = file.metadata()?.len();
+
+    let mut req = attohttpc::get(url).timeout(Duration::from_secs(60));
+    if offset > 0 {
+        println!("Resuming from byte {offset}");
+        req = req.header(RANGE, format!("bytes={offset}-"));
+    }
+    let resp = req.send()?;
+    println!("Status: {}", resp.status());
+
+    match resp.status() {
+        // The server sent the rest of the file.
+        StatusCode::PARTIAL_CONTENT => {
+            let n = resp.write_to(&mut file)?;
+            println!("Wrote {n} bytes");
+        }
+        // The whole file was already downloaded.
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {}
+        // The server ignored the range and sent the whole file.
+        _ => {
+            let resp = resp.error_for_status()?;
+            file.set_len(0)?;
+            let n = resp.write_to(&mut file)?;
+            println!("Wrote {n} bytes");
+        }
+    }
+    file.sync_all()?;
+
+    if let Some(expected) = expected {
+        let mut file = File::open(&part)?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher)?;
+        let actual: String = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+        if !actual.eq_ignore_ascii_case(&expected) {
+            fs::remove_file(&part)?;
+            return Err(ErrorKind::ChecksumMismatch { expected, actual }.into());
+        }
+        println!("SHA-256: {actual}");
+    }
+
+    fs::rename(&part, &path)?;
+    println!("Saved to {path}");
+
+    Ok(())
+}
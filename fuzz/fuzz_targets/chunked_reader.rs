@@ -0,0 +1,8 @@
+#![no_main]
+
+use attohttpc::bench;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = bench::read_chunked(data);
+});
@@ -0,0 +1,37 @@
+#![no_main]
+
+use attohttpc::body::{Body, BodyKind};
+use attohttpc::{MultipartBuilder, MultipartFile};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Fields are separated by null bytes: boundary, text name, text value, file name, file
+    // name, MIME type, then the file content.
+    let mut fields = data.splitn(7, |&b| b == 0).map(String::from_utf8_lossy);
+    let mut next = || fields.next().unwrap_or_default();
+    let (boundary, name, value, file_name, filename, mime, content) =
+        (next(), next(), next(), next(), next(), next(), next());
+
+    let mut file = MultipartFile::new(&file_name, content.as_bytes()).with_filename(&filename);
+    if let Ok(typed) = file.clone().with_type(&*mime) {
+        file = typed;
+    }
+    let mut builder = MultipartBuilder::new().with_text(&name, &value).with_file(file);
+    if !boundary.is_empty() {
+        builder = match builder.with_boundary(&*boundary) {
+            Ok(builder) => builder,
+            Err(_) => return,
+        };
+    }
+    let mut form = builder.build().unwrap();
+
+    // The announced length must match the written body exactly.
+    let len = match form.kind().unwrap() {
+        BodyKind::KnownLength(len) => len,
+        kind => panic!("unexpected body kind {:?}", kind),
+    };
+    let mut body = Vec::new();
+    form.write(&mut body).unwrap();
+    assert_eq!(body.len() as u64, len);
+    form.content_type().unwrap();
+});
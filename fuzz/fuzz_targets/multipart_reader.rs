@@ -0,0 +1,12 @@
+#![no_main]
+
+use attohttpc::bench;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first line is the boundary, the rest is the body.
+    let split = data.iter().position(|&b| b == b'\n').unwrap_or(data.len());
+    let boundary = String::from_utf8_lossy(&data[..split]);
+    let body = data.get(split + 1..).unwrap_or(&[]);
+    let _ = bench::read_multipart(body, &boundary);
+});
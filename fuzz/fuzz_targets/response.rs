@@ -0,0 +1,10 @@
+#![no_main]
+
+use attohttpc::bench;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Parses the head, then reads the body with the framing and encoding it announces.
+    let request = attohttpc::get("http://localhost/").prepare();
+    let _ = bench::read_response(&request, data.to_vec());
+});
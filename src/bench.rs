@@ -0,0 +1,84 @@
+//! Entry points into the internals of the crate used by the benchmarks and the fuzz targets. This
+//! is not part of the public API.
+
+use std::io::{self, BufReader};
+
+use http::{HeaderMap, StatusCode};
+
+use crate::body::Body;
+use crate::error::Result;
+use crate::parsing::chunked_reader::ChunkedReader;
+use crate::parsing::parse_response;
+use crate::parsing::response::{parse_response_head_with, HeadScratch};
+use crate::raw::ResponseLimits;
+use crate::streams::BaseStream;
+use crate::PreparedRequest;
+
+/// Parse the status line and headers of the given response.
+pub fn parse_response_head(response: &[u8], max_headers: usize) -> Result<(StatusCode, HeaderMap)> {
+    let limits = ResponseLimits {
+        max_headers,
+        ..ResponseLimits::default()
+    };
+    let head = crate::parsing::response::parse_response_head(&mut BufReader::new(response), &limits)?;
+    Ok((head.status, head.headers))
+}
+
+/// Parse the status line and headers of each of the given responses, reusing the buffers between
+/// them like the hops of a request do. Returns the number of headers parsed.
+pub fn parse_response_heads(responses: &[&[u8]], max_headers: usize) -> Result<usize> {
+    let limits = ResponseLimits {
+        max_headers,
+        ..ResponseLimits::default()
+    };
+    let mut scratch = HeadScratch::default();
+    let mut count = 0;
+    for response in responses {
+        let head = parse_response_head_with(&mut BufReader::new(*response), &mut scratch, &limits)?;
+        count += head.headers.len();
+        scratch.recycle(head.headers);
+    }
+    Ok(count)
+}
+
+/// Decode the given chunked body, returning its length.
+pub fn read_chunked(body: &[u8]) -> io::Result<u64> {
+    io::copy(&mut ChunkedReader::new(BufReader::new(body)), &mut io::sink())
+}
+
+/// Read every part of the given multipart body, returning the total length of their bodies.
+#[cfg(feature = "multipart-form")]
+pub fn read_multipart(body: &[u8], boundary: &str) -> io::Result<u64> {
+    let mut reader = crate::parsing::multipart_reader::MultipartReader::new(body, boundary);
+    let mut len = 0;
+    while reader.next_part()?.is_some() {
+        len += io::copy(&mut reader, &mut io::sink())?;
+    }
+    Ok(len)
+}
+
+/// Parse the given response and read its body, returning the length of the decoded body.
+pub fn read_response<B>(request: &PreparedRequest<B>, response: Vec<u8>) -> Result<u64> {
+    parse_response(BaseStream::mock(response), request, request.url())?.write_to(io::sink())
+}
+
+/// Write the given request, as it is sent when no proxy is used.
+pub fn write_request<B, W>(request: &mut PreparedRequest<B>, writer: W) -> Result
+where
+    B: Body,
+    W: io::Write,
+{
+    let url = request.url().clone();
+    request.write_request(writer, &url, None)
+}
+
+/// Compress the given data using gzip.
+#[cfg(feature = "__compress")]
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
@@ -0,0 +1,236 @@
+//! In-memory cache of the responses received by a `Session`, revalidated with the server.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use http::header::{
+    HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    TRANSFER_ENCODING, VARY,
+};
+use url::{Position, Url};
+
+use crate::parsing::Response;
+
+/// Limits of the response cache of a `Session`, see [`Session::cache`](crate::Session::cache).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Maximum number of responses kept. The least recently used ones are dropped first.
+    pub max_entries: usize,
+    /// Maximum size of the body of a response kept. Larger responses are not cached.
+    pub max_body_bytes: usize,
+}
+
+/// A response kept in the cache, along with its validators.
+#[derive(Debug)]
+pub(crate) struct CacheEntry {
+    pub reason: Option<String>,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+}
+
+impl CacheEntry {
+    /// Add the conditional headers asking the server if the response is still valid.
+    pub fn add_validators(&self, headers: &mut HeaderMap) {
+        if let Some(etag) = &self.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+}
+
+/// Map which drops its least recently used entries.
+#[derive(Debug, Default)]
+struct LruMap {
+    /// Entries along with the time they were last used.
+    entries: HashMap<String, (u64, Arc<CacheEntry>)>,
+    clock: u64,
+}
+
+impl LruMap {
+    fn get(&mut self, key: &str) -> Option<Arc<CacheEntry>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|(used, entry)| {
+            *used = clock;
+            entry.clone()
+        })
+    }
+
+    fn insert(&mut self, key: String, entry: Arc<CacheEntry>, max_entries: usize) {
+        self.clock += 1;
+        self.entries.insert(key, (self.clock, entry));
+        while self.entries.len() > max_entries {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+/// Cache of the `200 OK` responses to GET requests which have an `ETag` or a `Last-Modified`
+/// header, shared by the requests of a `Session`. Responses with a `Vary` header and responses to
+/// requests with an `Authorization` header are not cached.
+///
+/// Clones of a `ResponseCache` share the same entries.
+#[derive(Clone)]
+pub(crate) struct ResponseCache {
+    config: CacheConfig,
+    entries: Arc<Mutex<LruMap>>,
+}
+
+/// The fragment is not sent to the server, so it does not make the URL different.
+fn cache_key(url: &Url) -> &str {
+    &url[..Position::AfterQuery]
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> ResponseCache {
+        ResponseCache {
+            config,
+            entries: Arc::new(Mutex::new(LruMap::default())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruMap> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get the cached response of the URL, if there is one.
+    pub fn get(&self, url: &Url) -> Option<Arc<CacheEntry>> {
+        self.lock().get(cache_key(url))
+    }
+
+    /// Get the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Drop every cached response.
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// Keep the body of the successful response to a GET request as it is read, if the response
+    /// can be revalidated. The response is cached once its body is read to the end.
+    pub fn capture(&self, url: &Url, response: &mut Response) {
+        let no_store = response
+            .cache_control()
+            .is_some_and(|cache_control| cache_control.no_store);
+        let etag = response.headers().get(ETAG).cloned();
+        let last_modified = response.headers().get(LAST_MODIFIED).cloned();
+        // The key does not include the request headers, so responses which vary with them are
+        // not cached.
+        let vary = response.headers().contains_key(VARY);
+        if (etag.is_none() && last_modified.is_none()) || no_store || vary {
+            self.lock().entries.remove(cache_key(url));
+            return;
+        }
+
+        let mut headers = response.headers().clone();
+        headers.remove(TRANSFER_ENCODING);
+        // The body is kept as it is returned by the response, after decompression.
+        if response.is_decoding() {
+            headers.remove(CONTENT_ENCODING);
+        }
+        let entry = CacheEntry {
+            reason: response.reason_phrase().map(str::to_owned),
+            headers,
+            body: Vec::new(),
+            etag,
+            last_modified,
+        };
+        response.set_cache_capture(CacheCapture {
+            cache: self.clone(),
+            key: cache_key(url).to_owned(),
+            entry: Some(entry),
+        });
+    }
+}
+
+impl fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("config", &self.config)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+/// Keeps the body of a response as it is read, the response is cached when the end of the body
+/// is reached. Nothing is cached if the body is too large or not read to the end.
+#[derive(Debug)]
+pub(crate) struct CacheCapture {
+    cache: ResponseCache,
+    key: String,
+    entry: Option<CacheEntry>,
+}
+
+impl CacheCapture {
+    pub fn on_read(&mut self, data: &[u8]) {
+        let entry = match &mut self.entry {
+            Some(entry) => entry,
+            None => return,
+        };
+        if data.is_empty() {
+            let mut entry = self.entry.take().expect("entry is present");
+            entry
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from(entry.body.len()));
+            let max_entries = self.cache.config.max_entries;
+            self.cache
+                .lock()
+                .insert(std::mem::take(&mut self.key), Arc::new(entry), max_entries);
+        } else if entry.body.len() + data.len() > self.cache.config.max_body_bytes {
+            debug!("response body is too large to be cached");
+            self.entry = None;
+        } else {
+            entry.body.extend_from_slice(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> Arc<CacheEntry> {
+        Arc::new(CacheEntry {
+            reason: None,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            etag: None,
+            last_modified: None,
+        })
+    }
+
+    #[test]
+    fn test_lru_map() {
+        let mut map = LruMap::default();
+        map.insert("a".into(), entry(), 2);
+        map.insert("b".into(), entry(), 2);
+        assert!(map.get("a").is_some());
+        map.insert("c".into(), entry(), 2);
+        assert!(map.get("a").is_some());
+        assert!(map.get("b").is_none());
+        assert!(map.get("c").is_some());
+
+        map.insert("d".into(), entry(), 0);
+        assert!(map.entries.is_empty());
+    }
+
+    #[test]
+    fn test_cache_key_ignores_fragment() {
+        let url = Url::parse("http://example.com/a?b#c").unwrap();
+        assert_eq!(cache_key(&url), "http://example.com/a?b");
+    }
+}
@@ -12,4 +12,96 @@ pub use encoding_rs::{
     ISO_8859_16, ISO_8859_2, ISO_8859_3, ISO_8859_4, ISO_8859_5, ISO_8859_6, ISO_8859_7, ISO_8859_8, ISO_8859_8_I,
     KOI8_R, KOI8_U, MACINTOSH, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252,
     WINDOWS_1253, WINDOWS_1254, WINDOWS_1255, WINDOWS_1256, WINDOWS_1257, WINDOWS_1258, WINDOWS_874, X_MAC_CYRILLIC,
+    X_USER_DEFINED,
 };
+
+static ALL: [Charset; 39] = [
+    BIG5,
+    EUC_JP,
+    EUC_KR,
+    GB18030,
+    GBK,
+    IBM866,
+    ISO_2022_JP,
+    ISO_8859_10,
+    ISO_8859_13,
+    ISO_8859_14,
+    ISO_8859_15,
+    ISO_8859_16,
+    ISO_8859_2,
+    ISO_8859_3,
+    ISO_8859_4,
+    ISO_8859_5,
+    ISO_8859_6,
+    ISO_8859_7,
+    ISO_8859_8,
+    ISO_8859_8_I,
+    KOI8_R,
+    KOI8_U,
+    MACINTOSH,
+    SHIFT_JIS,
+    UTF_16BE,
+    UTF_16LE,
+    UTF_8,
+    WINDOWS_1250,
+    WINDOWS_1251,
+    WINDOWS_1252,
+    WINDOWS_1253,
+    WINDOWS_1254,
+    WINDOWS_1255,
+    WINDOWS_1256,
+    WINDOWS_1257,
+    WINDOWS_1258,
+    WINDOWS_874,
+    X_MAC_CYRILLIC,
+    X_USER_DEFINED,
+];
+
+/// Find the charset of a label such as `"shift_jis"` or `"latin1"`.
+///
+/// Labels are matched like browsers do, as specified by the WHATWG Encoding Standard: case is
+/// ignored, surrounding whitespace is trimmed and aliases are resolved. Labels of the replacement
+/// encoding, which cannot decode anything, give `None` like unknown labels.
+pub fn from_label(label: &str) -> Option<Charset> {
+    Encoding::for_label_no_replacement(label.as_bytes())
+}
+
+/// Get all the supported charsets, for instance to list them in the help of a command line tool.
+/// The canonical name of a charset is given by its `name` method.
+pub fn all() -> &'static [Charset] {
+    &ALL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_label() {
+        assert_eq!(from_label("shift_jis"), Some(SHIFT_JIS));
+        assert_eq!(from_label("  Shift_JIS\t"), Some(SHIFT_JIS));
+        assert_eq!(from_label("\nUTF-8 "), Some(UTF_8));
+        assert_eq!(from_label("Latin1"), Some(WINDOWS_1252));
+        assert_eq!(from_label("ISO-8859-2"), Some(ISO_8859_2));
+        assert_eq!(from_label("x-gbk"), Some(GBK));
+        assert_eq!(from_label("EUC-JP"), Some(EUC_JP));
+    }
+
+    #[test]
+    fn test_from_label_unknown() {
+        assert_eq!(from_label(""), None);
+        assert_eq!(from_label("utf 8"), None);
+        assert_eq!(from_label("klingon"), None);
+        assert_eq!(from_label("iso-2022-kr"), None);
+    }
+
+    #[test]
+    fn test_all() {
+        let all = all();
+        assert!(all.contains(&UTF_8));
+        assert!(all.contains(&SHIFT_JIS));
+        for charset in all {
+            assert_eq!(from_label(charset.name()), Some(*charset));
+        }
+    }
+}
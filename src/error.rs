@@ -2,6 +2,7 @@ use std::convert::Infallible;
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
 use std::io;
+use std::net::SocketAddr;
 use std::result;
 
 /// Errors than can occur while parsing the response from the server.
@@ -17,12 +18,19 @@ pub enum InvalidResponseKind {
     StatusCode,
     /// Error parsing header
     Header,
+    /// Header with an invalid name or value, rejected when `strict_response_headers` is enabled
+    InvalidHeader {
+        /// Name of the header, which might be the invalid part.
+        name: String,
+    },
     /// Error decoding chunk size
     ChunkSize,
     /// Error decoding chunk
     Chunk,
     /// Invalid Content-Length header
     ContentLength,
+    /// The decoded body is too large compared to the compressed body, see `max_decompression_ratio`
+    DecompressionBomb,
 }
 
 impl Display for InvalidResponseKind {
@@ -35,9 +43,11 @@ impl Display for InvalidResponseKind {
             StatusLine => write!(f, "invalid status line"),
             StatusCode => write!(f, "invalid status code"),
             Header => write!(f, "invalid header"),
+            InvalidHeader { name } => write!(f, "invalid header {name:?}"),
             ChunkSize => write!(f, "invalid chunk size"),
             Chunk => write!(f, "invalid chunk"),
             ContentLength => write!(f, "invalid content length"),
+            DecompressionBomb => write!(f, "decompressed body exceeds the maximum ratio to its compressed size"),
         }
     }
 }
@@ -51,28 +61,65 @@ pub enum ErrorKind {
     ConnectError {
         /// Status code from the proxy.
         status_code: http::StatusCode,
+        /// Headers from the proxy, like the `Proxy-Authenticate` challenges of a 407 response.
+        headers: http::HeaderMap,
         /// Up to 10 KiB of body data from the proxy which might help diagnose the error.
         body: Vec<u8>,
     },
     /// Error generated by the `http` crate.
     Http(http::Error),
     /// IO Error
+    ///
+    /// Failing to connect to a host with a single address is reported as an I/O error, but a host
+    /// with several addresses gives `ConnectAllFailed` when none of them can be reached.
     Io(io::Error),
+    /// None of the addresses of the host could be reached.
+    ConnectAllFailed(Vec<(SocketAddr, io::Error)>),
     /// Invalid base URL given to the Request.
     InvalidBaseUrl,
     /// An URL with an invalid host was found while processing the request.
     InvalidUrlHost,
     /// The URL scheme is unknown and the port is missing.
     InvalidUrlPort,
+    /// The scheme of the URL given to the request is not supported, only `http` and `https` are.
+    UnsupportedScheme(String),
     /// Server sent an invalid response.
     InvalidResponse(InvalidResponseKind),
     /// Too many redirections
     TooManyRedirections,
+    /// A redirection leads to a URL which was already visited.
+    RedirectionLoop {
+        /// URL which was visited twice.
+        url: String,
+    },
     /// Status code indicates failure
-    StatusCode(http::StatusCode),
+    StatusCode {
+        /// Status code of the response.
+        status: http::StatusCode,
+        /// Headers of the response, which can contain details like `Retry-After`.
+        headers: http::HeaderMap,
+    },
     /// JSON decoding/encoding error.
     #[cfg(feature = "json")]
     Json(serde_json::Error),
+    /// A response body could not be parsed as JSON.
+    #[cfg(feature = "json")]
+    JsonDecode {
+        /// Error returned by the parser.
+        source: serde_json::Error,
+        /// Part of the body around the position of the error, decoded lossily. It is left out of the
+        /// `Display` output since the body can contain sensitive data.
+        snippet: String,
+        /// Value of the `Content-Type` header of the response, if any.
+        content_type: Option<String>,
+    },
+    /// Data other than whitespace follows the JSON value of a response body, see
+    /// `ResponseReader::json_strict_eof`.
+    #[cfg(feature = "json")]
+    TrailingData {
+        /// Number of bytes after the JSON value, starting at the first one which is not whitespace.
+        bytes: usize,
+    },
     /// Form-URL encoding error.
     #[cfg(feature = "form")]
     UrlEncoded(serde_urlencoded::ser::Error),
@@ -83,12 +130,65 @@ pub enum ErrorKind {
     #[cfg(all(feature = "__rustls", not(feature = "tls-native")))]
     Tls(rustls::Error),
     /// Invalid DNS name used for TLS certificate verification
-    #[cfg(feature = "__rustls")]
     InvalidDNSName(String),
     /// Invalid mime type in a Multipart form
     InvalidMimeType(String),
+    /// Invalid boundary for a Multipart form
+    InvalidMultipartBoundary(String),
+    /// Response body is not valid text in the expected charset.
+    InvalidTextEncoding {
+        /// Number of bytes of the body which were valid before the first invalid sequence.
+        valid_up_to: usize,
+    },
+    /// Digest of a downloaded file does not match the expected digest.
+    #[cfg(feature = "checksum")]
+    ChecksumMismatch {
+        /// Expected SHA-256 digest, in hexadecimal.
+        expected: String,
+        /// Actual SHA-256 digest of the downloaded data, in hexadecimal.
+        actual: String,
+    },
+    /// The `Content-Type` of the response is not one of the types given to `expect_content_type`.
+    UnexpectedContentType {
+        /// Expected content types.
+        expected: Vec<String>,
+        /// Value of the `Content-Type` header of the response, if any.
+        actual: Option<String>,
+    },
+    /// The response body is larger than the maximum size given to `get_to_writer`.
+    ResponseTooLarge {
+        /// Maximum size of the body, in bytes.
+        max_size: u64,
+    },
+    /// Content encoding requested in `accept_encoding` which cannot be decoded.
+    UnsupportedContentEncoding(String),
+    /// The server encoded the response body although `no_compression` was set, and it cannot be
+    /// decoded because the compress features are disabled.
+    UnexpectedContentEncoding(String),
     /// TLS was not enabled by features.
-    TlsDisabled,
+    TlsDisabled {
+        /// URL which requires TLS, if the error was detected before connecting.
+        url: Option<String>,
+    },
+    /// A certificate of a PEM bundle could not be parsed.
+    InvalidPemCertificate {
+        /// Index of the certificate in the bundle.
+        index: usize,
+        /// Reason why the certificate could not be parsed.
+        reason: String,
+    },
+    /// A part of the request contains a character which cannot be sent, like CR, LF or NUL.
+    InvalidRequestComponent(String),
+    /// The list of addresses given to `RequestBuilder::preresolved` is empty.
+    EmptyAddressList,
+    /// The server did not answer a request sent with `PreparedRequest::send_upgrade` with
+    /// `101 Switching Protocols`.
+    UpgradeRefused {
+        /// Status code of the response.
+        status: http::StatusCode,
+        /// Headers of the response.
+        headers: http::HeaderMap,
+    },
     /// Empty cert store
     #[cfg(all(feature = "__rustls", not(feature = "tls-native")))]
     ServerCertVerifier(rustls::client::VerifierBuilderError),
@@ -108,6 +208,83 @@ impl Error {
     pub fn into_kind(self) -> ErrorKind {
         *self.0
     }
+
+    /// Get the status code of the response which caused this error, if any.
+    pub fn status(&self) -> Option<http::StatusCode> {
+        match *self.0 {
+            ErrorKind::StatusCode { status, .. } => Some(status),
+            ErrorKind::ConnectError { status_code, .. } => Some(status_code),
+            ErrorKind::UpgradeRefused { status, .. } => Some(status),
+            _ => None,
+        }
+    }
+
+    /// Check if sending the request again might succeed.
+    ///
+    /// This is a heuristic which considers connection failures, timeouts, connections closed by
+    /// the server, and the status codes 408, 429 and 5xx as retriable. Errors which would happen
+    /// again, like invalid URLs or TLS errors, are not retriable. Note that retrying a request
+    /// which is not idempotent might apply it twice.
+    pub fn is_retriable(&self) -> bool {
+        match *self.0 {
+            ErrorKind::Io(ref e) => is_retriable_io(e),
+            ErrorKind::ConnectAllFailed(ref errors) => errors.iter().any(|(_, e)| is_retriable_io(e)),
+            ErrorKind::StatusCode { status, .. }
+            | ErrorKind::ConnectError {
+                status_code: status, ..
+            } => {
+                status.is_server_error()
+                    || status == http::StatusCode::REQUEST_TIMEOUT
+                    || status == http::StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the part of the response body around the position of the error, if this is a
+    /// `JsonDecode` error.
+    ///
+    /// The snippet is not part of the `Display` output of the error, so that the content of the
+    /// body does not end up in logs by accident.
+    #[cfg(feature = "json")]
+    pub fn body_snippet(&self) -> Option<&str> {
+        match *self.0 {
+            ErrorKind::JsonDecode { ref snippet, .. } => Some(snippet),
+            _ => None,
+        }
+    }
+
+    /// Get the headers of the response which caused this error, if any.
+    pub fn response_headers(&self) -> Option<&http::HeaderMap> {
+        match *self.0 {
+            ErrorKind::StatusCode { ref headers, .. }
+            | ErrorKind::ConnectError { ref headers, .. }
+            | ErrorKind::UpgradeRefused { ref headers, .. } => Some(headers),
+            _ => None,
+        }
+    }
+}
+
+fn is_retriable_io(err: &io::Error) -> bool {
+    use io::ErrorKind::*;
+
+    // Errors of the body reader are attohttpc errors wrapped in an io::Error.
+    if let Some(err) = err.get_ref().and_then(|e| e.downcast_ref::<Error>()) {
+        return err.is_retriable();
+    }
+
+    matches!(
+        err.kind(),
+        TimedOut
+            | WouldBlock
+            | Interrupted
+            | ConnectionRefused
+            | ConnectionReset
+            | ConnectionAborted
+            | NotConnected
+            | BrokenPipe
+            | UnexpectedEof
+    )
 }
 
 impl Display for Error {
@@ -117,32 +294,87 @@ impl Display for Error {
         match *self.0 {
             ConnectNotSupported => write!(w, "CONNECT is not supported"),
             ConnectError { status_code, .. } => write!(w, "Proxy CONNECT error: {status_code}"),
-            Http(ref e) => write!(w, "Http Error: {e}"),
-            Io(ref e) => write!(w, "Io Error: {e}"),
+            Http(_) => write!(w, "Http Error"),
+            Io(_) => write!(w, "Io Error"),
+            ConnectAllFailed(ref errors) => {
+                write!(w, "Could not connect to any address")?;
+                for (i, (addr, e)) in errors.iter().enumerate() {
+                    write!(w, "{} {addr}: {e}", if i == 0 { ":" } else { ";" })?;
+                }
+                Ok(())
+            }
             InvalidBaseUrl => write!(w, "Invalid base URL"),
             InvalidUrlHost => write!(w, "URL is missing a host"),
             InvalidUrlPort => write!(w, "URL is missing a port"),
+            UnsupportedScheme(ref scheme) => write!(w, "Unsupported URL scheme {scheme:?}"),
             InvalidResponse(ref k) => write!(w, "InvalidResponse: {k}"),
             TooManyRedirections => write!(w, "Too many redirections"),
-            StatusCode(ref sc) => write!(w, "Status code {sc} indicates failure"),
+            RedirectionLoop { ref url } => write!(w, "Redirection loop detected at {url}"),
+            StatusCode { status, .. } => write!(w, "Status code {status} indicates failure"),
+            #[cfg(feature = "json")]
+            Json(_) => write!(w, "Json Error"),
             #[cfg(feature = "json")]
-            Json(ref e) => write!(w, "Json Error: {e}"),
+            JsonDecode { ref content_type, .. } => write!(
+                w,
+                "Json Error (content type {})",
+                content_type.as_deref().unwrap_or("missing")
+            ),
+            #[cfg(feature = "json")]
+            TrailingData { bytes } => write!(
+                w,
+                "Trailing data after the JSON value: {bytes} bytes, a body with several values like \
+                 newline-delimited JSON must be parsed one line at a time"
+            ),
             #[cfg(feature = "form")]
-            UrlEncoded(ref e) => write!(w, "URL Encoding Error: {e}"),
+            UrlEncoded(_) => write!(w, "URL Encoding Error"),
             #[cfg(any(feature = "tls-native", feature = "__rustls"))]
-            Tls(ref e) => write!(w, "Tls Error: {e}"),
-            #[cfg(feature = "__rustls")]
+            Tls(_) => write!(w, "Tls Error"),
             InvalidDNSName(ref e) => write!(w, "Invalid DNS name: {e}"),
             InvalidMimeType(ref e) => write!(w, "Invalid mime type: {e}"),
-            TlsDisabled => write!(w, "TLS is disabled, activate one of the tls- features"),
+            InvalidMultipartBoundary(ref e) => write!(w, "Invalid multipart boundary: {e}"),
+            InvalidTextEncoding { valid_up_to } => {
+                write!(w, "Invalid text encoding: invalid sequence after {valid_up_to} bytes")
+            }
+            #[cfg(feature = "checksum")]
+            ChecksumMismatch {
+                ref expected,
+                ref actual,
+            } => write!(w, "Checksum mismatch: expected {expected}, got {actual}"),
+            UnexpectedContentType {
+                ref expected,
+                ref actual,
+            } => write!(
+                w,
+                "Unexpected content type: expected {}, got {}",
+                expected.join(" or "),
+                actual.as_deref().unwrap_or("none")
+            ),
+            ResponseTooLarge { max_size } => {
+                write!(w, "Response body is larger than the maximum size of {max_size} bytes")
+            }
+            UnsupportedContentEncoding(ref e) => write!(w, "Unsupported content encoding: {e}"),
+            UnexpectedContentEncoding(ref e) => write!(w, "Unexpected content encoding: {e}"),
+            TlsDisabled { url: None } => write!(w, "TLS is disabled, activate one of the tls- features"),
+            TlsDisabled { url: Some(ref url) } => write!(
+                w,
+                "TLS is required by {url} but is disabled, activate one of the tls- features"
+            ),
+            InvalidPemCertificate { index, ref reason } => {
+                write!(w, "Invalid PEM certificate at index {index}: {reason}")
+            }
+            InvalidRequestComponent(ref e) => write!(w, "Invalid character in request component: {e}"),
+            EmptyAddressList => write!(w, "The list of pre-resolved addresses is empty"),
+            UpgradeRefused { status, .. } => write!(w, "The server refused to upgrade the connection: {status}"),
             #[cfg(all(feature = "__rustls", not(feature = "tls-native")))]
-            ServerCertVerifier(ref e) => write!(w, "Invalid certificate: {e}"),
+            ServerCertVerifier(_) => write!(w, "Invalid certificate"),
         }
     }
 }
 
+/// The errors wrapped by an `Error` are not part of its `Display` output, they are returned by
+/// `source` instead, so that printing the chain of errors does not repeat them.
 impl StdError for Error {
-    fn cause(&self) -> Option<&dyn StdError> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         use ErrorKind::*;
 
         match *self.0 {
@@ -150,8 +382,14 @@ impl StdError for Error {
             Http(ref e) => Some(e),
             #[cfg(feature = "json")]
             Json(ref e) => Some(e),
+            #[cfg(feature = "json")]
+            JsonDecode { ref source, .. } => Some(source),
+            #[cfg(feature = "form")]
+            UrlEncoded(ref e) => Some(e),
             #[cfg(any(feature = "tls-native", feature = "__rustls"))]
             Tls(ref e) => Some(e),
+            #[cfg(all(feature = "__rustls", not(feature = "tls-native")))]
+            ServerCertVerifier(ref e) => Some(e),
             _ => None,
         }
     }
@@ -230,15 +468,88 @@ impl From<rustls::client::VerifierBuilderError> for Error {
 
 impl From<Error> for io::Error {
     fn from(err: Error) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, err)
+        io::Error::other(err)
     }
 }
 
 impl From<InvalidResponseKind> for io::Error {
     fn from(kind: InvalidResponseKind) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, Error(Box::new(ErrorKind::InvalidResponse(kind))))
+        io::Error::other(Error(Box::new(ErrorKind::InvalidResponse(kind))))
     }
 }
 
 /// Wrapper for the `Result` type with an `Error`.
 pub type Result<T = ()> = result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as StdError;
+    use std::io;
+
+    use super::{Error, ErrorKind};
+
+    #[test]
+    fn test_source_io() {
+        let err = Error::from(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+        assert_eq!(err.to_string(), "Io Error");
+        let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(source.to_string(), "refused");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_source_json() {
+        let err = Error::from(serde_json::from_str::<u32>("nope").unwrap_err());
+        let source = err.source().unwrap().downcast_ref::<serde_json::Error>().unwrap();
+        assert!(source.is_syntax());
+        assert!(!err.to_string().contains(&source.to_string()));
+    }
+
+    #[test]
+    fn test_source_wrapped_in_io() {
+        // Errors of response readers are wrapped in io::Error, the chain still reaches the cause.
+        let inner = Error::from(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        let err = Error::from(io::Error::from(inner));
+        let mut chain = Vec::new();
+        let mut current: Option<&(dyn StdError + 'static)> = Some(&err);
+        while let Some(e) = current {
+            chain.push(e.to_string());
+            current = e.source();
+        }
+        assert_eq!(chain.last().unwrap(), "timed out");
+        assert!(err.is_retriable());
+    }
+
+    #[test]
+    fn test_is_retriable() {
+        let io_err = |kind| Error::from(io::Error::from(kind));
+        assert!(io_err(io::ErrorKind::TimedOut).is_retriable());
+        assert!(io_err(io::ErrorKind::ConnectionRefused).is_retriable());
+        assert!(io_err(io::ErrorKind::ConnectionReset).is_retriable());
+        assert!(!io_err(io::ErrorKind::PermissionDenied).is_retriable());
+
+        let status = |status: u16| -> Error {
+            ErrorKind::StatusCode {
+                status: http::StatusCode::from_u16(status).unwrap(),
+                headers: http::HeaderMap::new(),
+            }
+            .into()
+        };
+        assert!(status(503).is_retriable());
+        assert!(status(500).is_retriable());
+        assert!(status(429).is_retriable());
+        assert!(!status(404).is_retriable());
+        assert!(!status(401).is_retriable());
+
+        let all_failed: Error = ErrorKind::ConnectAllFailed(vec![
+            ("[::1]:80".parse().unwrap(), io::ErrorKind::AddrNotAvailable.into()),
+            ("127.0.0.1:80".parse().unwrap(), io::ErrorKind::ConnectionRefused.into()),
+        ])
+        .into();
+        assert!(all_failed.is_retriable());
+
+        assert!(!Error::from(ErrorKind::InvalidUrlHost).is_retriable());
+        assert!(!Error::from(ErrorKind::TooManyRedirections).is_retriable());
+    }
+}
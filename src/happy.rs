@@ -1,39 +1,215 @@
 use std::io;
 use std::iter::{self, FusedIterator};
-use std::net::{IpAddr, TcpStream, ToSocketAddrs};
-use std::sync::mpsc::channel;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Mutex, PoisonError};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use url::Host;
 
+use crate::{ErrorKind, Result};
+
 const RACE_DELAY: Duration = Duration::from_millis(200);
 
-/// This function implements a basic form of the happy eyeballs RFC to quickly connect
-/// to a domain which is available in both IPv4 and IPv6. Connection attempts are raced
-/// against each other and the first to connect successfully wins the race.
-pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option<Instant>) -> io::Result<TcpStream> {
-    let addrs: Vec<_> = match *host {
-        Host::Domain(domain) => (domain, port).to_socket_addrs()?.collect(),
-        Host::Ipv4(ip) => return TcpStream::connect_timeout(&(IpAddr::V4(ip), port).into(), timeout),
-        Host::Ipv6(ip) => return TcpStream::connect_timeout(&(IpAddr::V6(ip), port).into(), timeout),
+/// Maximum number of hosts for which the preferred address family is remembered.
+const FAMILY_CACHE_SIZE: usize = 128;
+/// Duration after which a remembered address family is forgotten.
+const FAMILY_CACHE_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+static PREFERRED_FAMILIES: Mutex<FamilyCache> = Mutex::new(FamilyCache::new(FAMILY_CACHE_SIZE, FAMILY_CACHE_EXPIRY));
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum AddressFamily {
+    V4,
+    V6,
+}
+
+impl AddressFamily {
+    fn of(addr: &SocketAddr) -> AddressFamily {
+        match addr {
+            SocketAddr::V4(_) => AddressFamily::V4,
+            SocketAddr::V6(_) => AddressFamily::V6,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FamilyEntry {
+    host: String,
+    family: AddressFamily,
+    last_success: Instant,
+}
+
+/// Remembers which address family last connected successfully to a host, so that it can be tried
+/// first on the next connection. The least recently successful host is evicted when full.
+#[derive(Debug)]
+struct FamilyCache {
+    entries: Vec<FamilyEntry>,
+    capacity: usize,
+    expiry: Duration,
+}
+
+impl FamilyCache {
+    const fn new(capacity: usize, expiry: Duration) -> FamilyCache {
+        FamilyCache {
+            entries: Vec::new(),
+            capacity,
+            expiry,
+        }
+    }
+
+    fn preferred(&mut self, host: &str, now: Instant) -> Option<AddressFamily> {
+        let expiry = self.expiry;
+        self.entries
+            .retain(|entry| now.saturating_duration_since(entry.last_success) < expiry);
+        self.entries
+            .iter()
+            .find(|entry| entry.host == host)
+            .map(|entry| entry.family)
+    }
+
+    fn record_success(&mut self, host: &str, family: AddressFamily, now: Instant) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.host == host) {
+            entry.family = family;
+            entry.last_success = now;
+            return;
+        }
+
+        if self.entries.len() >= self.capacity {
+            if let Some(oldest) = (0..self.entries.len()).min_by_key(|&i| self.entries[i].last_success) {
+                self.entries.swap_remove(oldest);
+            }
+        }
+        self.entries.push(FamilyEntry {
+            host: host.to_owned(),
+            family,
+            last_success: now,
+        });
+    }
+
+    fn record_failure(&mut self, host: &str) {
+        self.entries.retain(|entry| entry.host != host);
+    }
+}
+
+/// Sort the addresses so that attempts alternate between address families, starting with the
+/// preferred family, or IPv6 if there is no preference.
+fn sort_addrs(addrs: &[SocketAddr], preferred: Option<AddressFamily>) -> Vec<SocketAddr> {
+    let ipv4 = addrs.iter().copied().filter(|a| a.is_ipv4());
+    let ipv6 = addrs.iter().copied().filter(|a| a.is_ipv6());
+    match preferred {
+        Some(AddressFamily::V4) => intertwine(ipv4, ipv6).collect(),
+        Some(AddressFamily::V6) | None => intertwine(ipv6, ipv4).collect(),
+    }
+}
+
+/// The system resolver usually gives up on its own within this time.
+const SYSTEM_RESOLVER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Resolve the addresses on a helper thread, giving up once `timeout` elapses or the deadline is
+/// reached.
+///
+/// The system resolver cannot be interrupted, so after a timeout the helper thread is leaked until
+/// the resolution finishes in the background. The resolution runs on the calling thread instead
+/// when the time left is not shorter than `SYSTEM_RESOLVER_TIMEOUT`, which is the case with the
+/// default connect timeout and no deadline.
+fn resolve_with_timeout<F>(resolve: F, timeout: Duration, deadline: Option<Instant>) -> io::Result<Vec<SocketAddr>>
+where
+    F: FnOnce() -> io::Result<Vec<SocketAddr>> + Send + 'static,
+{
+    let timeout = match deadline {
+        Some(deadline) => timeout.min(deadline.saturating_duration_since(Instant::now())),
+        None => timeout,
     };
+    if timeout >= SYSTEM_RESOLVER_TIMEOUT {
+        return resolve();
+    }
 
-    if let [addr] = &addrs[..] {
-        debug!("DNS returned only one address, using fast path");
-        return TcpStream::connect_timeout(addr, timeout);
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        let _ = tx.send(resolve());
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(res) => res,
+        Err(RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, "DNS resolution timed out")),
+        Err(RecvTimeoutError::Disconnected) => Err(io::Error::other("DNS resolution failed")),
     }
+}
 
-    let ipv4 = addrs.iter().filter(|a| a.is_ipv4());
-    let ipv6 = addrs.iter().filter(|a| a.is_ipv6());
-    let sorted = intertwine(ipv6, ipv4);
+/// Resolve the addresses of the host. The DNS resolution is bounded by `timeout` and the
+/// deadline, like the connection attempts, when the time left is shorter than
+/// `SYSTEM_RESOLVER_TIMEOUT`. IP addresses are used as they are.
+pub fn resolve(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option<Instant>) -> Result<Vec<SocketAddr>> {
+    match *host {
+        Host::Domain(domain) => {
+            let owned = domain.to_owned();
+            let resolve = move || Ok((owned.as_str(), port).to_socket_addrs()?.collect());
+            Ok(resolve_with_timeout(resolve, timeout, deadline)?)
+        }
+        Host::Ipv4(ip) => Ok(vec![(IpAddr::V4(ip), port).into()]),
+        Host::Ipv6(ip) => Ok(vec![(IpAddr::V6(ip), port).into()]),
+    }
+}
+
+/// This function implements a basic form of the happy eyeballs RFC to quickly connect
+/// to a host which is available in both IPv4 and IPv6. Connection attempts to the addresses
+/// of the host are raced against each other and the first to connect successfully wins the race.
+///
+/// If `remember_family` is true, the address family of the last successful connection to the
+/// host is tried first.
+///
+/// When the host has several addresses and none of them can be reached, the error of each
+/// attempt is returned in `ErrorKind::ConnectAllFailed`. Other failures are I/O errors.
+pub fn connect(
+    host: &str,
+    addrs: &[SocketAddr],
+    timeout: Duration,
+    deadline: Option<Instant>,
+    remember_family: bool,
+) -> Result<TcpStream> {
+    match addrs {
+        [] => Err(io::Error::other("no DNS entries found").into()),
+        [addr] => {
+            debug!("host has only one address, using fast path");
+            Ok(connect_timeout(addr, timeout, deadline)?)
+        }
+        _ => connect_addrs(host, addrs, timeout, deadline, remember_family),
+    }
+}
+
+/// Connect to the address, giving up once `timeout` elapses or the deadline is reached.
+fn connect_timeout(addr: &SocketAddr, timeout: Duration, deadline: Option<Instant>) -> io::Result<TcpStream> {
+    match deadline.map(|deadline| deadline.checked_duration_since(Instant::now())) {
+        None => TcpStream::connect_timeout(addr, timeout),
+        Some(Some(timeout1)) => TcpStream::connect_timeout(addr, timeout.min(timeout1)),
+        Some(None) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Race connection attempts to the addresses of the host.
+fn connect_addrs(
+    domain: &str,
+    addrs: &[SocketAddr],
+    timeout: Duration,
+    deadline: Option<Instant>,
+    remember_family: bool,
+) -> Result<TcpStream> {
+    let preferred = if remember_family {
+        let mut families = PREFERRED_FAMILIES.lock().unwrap_or_else(PoisonError::into_inner);
+        families.preferred(domain, Instant::now())
+    } else {
+        None
+    };
+    let sorted = sort_addrs(addrs, preferred);
 
     let (tx, rx) = channel();
-    let mut first_err = None;
+    let mut errors = Vec::new();
 
     let start = Instant::now();
 
-    let mut handle_res = |addr, res| match res {
+    let mut handle_res = |addr: SocketAddr, res| match res {
         Ok(sock) => {
             debug!(
                 "successfully connected to {}, took {}ms",
@@ -41,14 +217,17 @@ pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option
                 start.elapsed().as_millis()
             );
 
+            if remember_family {
+                let mut families = PREFERRED_FAMILIES.lock().unwrap_or_else(PoisonError::into_inner);
+                families.record_success(domain, AddressFamily::of(&addr), Instant::now());
+            }
+
             Some(sock)
         }
         Err(err) => {
             debug!("failed to connect to {}: {}", addr, err);
 
-            if first_err.is_none() {
-                first_err = Some(err);
-            }
+            errors.push((addr, err));
 
             None
         }
@@ -56,17 +235,13 @@ pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option
 
     // This loop will race each connection attempt against others, returning early if a
     // connection attempt is successful.
-    for &addr in sorted {
+    for addr in sorted {
         let tx = tx.clone();
 
         thread::spawn(move || {
             debug!("trying to connect to {}", addr);
 
-            let res = match deadline.map(|deadline| deadline.checked_duration_since(Instant::now())) {
-                None => TcpStream::connect_timeout(&addr, timeout),
-                Some(Some(timeout1)) => TcpStream::connect_timeout(&addr, timeout.min(timeout1)),
-                Some(None) => Err(io::ErrorKind::TimedOut.into()),
-            };
+            let res = connect_timeout(&addr, timeout, deadline);
 
             let _ = tx.send((addr, res));
         });
@@ -96,7 +271,12 @@ pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option
         start.elapsed().as_millis()
     );
 
-    Err(first_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "no DNS entries found")))
+    if remember_family {
+        let mut families = PREFERRED_FAMILIES.lock().unwrap_or_else(PoisonError::into_inner);
+        families.record_failure(domain);
+    }
+
+    Err(ErrorKind::ConnectAllFailed(errors).into())
 }
 
 fn intertwine<T, A, B>(mut ita: A, mut itb: B) -> impl Iterator<Item = T>
@@ -140,3 +320,163 @@ fn test_intertwine_right() {
     let x: Vec<u32> = intertwine(vec![1, 2, 3].into_iter(), vec![4, 5, 6, 100, 101].into_iter()).collect();
     assert_eq!(&x[..], &[1, 4, 2, 5, 3, 6, 100, 101][..]);
 }
+
+#[test]
+fn test_resolve_with_timeout() {
+    let resolved = resolve_with_timeout(|| Ok(addrs(&["1.1.1.1:80"])), Duration::from_secs(1), None).unwrap();
+    assert_eq!(resolved, addrs(&["1.1.1.1:80"]));
+
+    // No helper thread is needed with the default connect timeout.
+    let caller = thread::current().id();
+    let resolve = move || {
+        assert_eq!(thread::current().id(), caller);
+        Ok(addrs(&["1.1.1.1:80"]))
+    };
+    assert!(resolve_with_timeout(resolve, SYSTEM_RESOLVER_TIMEOUT, None).is_ok());
+
+    let slow = || {
+        thread::sleep(Duration::from_secs(2));
+        Ok(addrs(&["1.1.1.1:80"]))
+    };
+
+    let start = Instant::now();
+    let err = resolve_with_timeout(slow, Duration::from_millis(100), None).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(100);
+    let err = resolve_with_timeout(slow, Duration::from_secs(30), Some(deadline)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_connect_addrs_all_failed() {
+    use std::net::TcpListener;
+
+    // Bind then release two ports, so that connecting to them is refused.
+    let closed: Vec<SocketAddr> = (0..2)
+        .map(|_| TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap())
+        .collect();
+
+    let err = connect_addrs("localhost", &closed, Duration::from_secs(5), None, false).unwrap_err();
+    match err.kind() {
+        ErrorKind::ConnectAllFailed(errors) => {
+            let mut failed: Vec<_> = errors.iter().map(|(addr, _)| *addr).collect();
+            failed.sort();
+            let mut expected = closed.clone();
+            expected.sort();
+            assert_eq!(failed, expected);
+        }
+        kind => panic!("unexpected error {:?}", kind),
+    }
+
+    let text = err.to_string();
+    for addr in &closed {
+        assert!(text.contains(&addr.to_string()), "{} missing from {}", addr, text);
+    }
+}
+
+#[test]
+fn test_connect_empty_list() {
+    let err = connect("localhost", &[], Duration::from_secs(5), None, false).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Io(_)), "{:?}", err);
+}
+
+#[test]
+fn test_connect_single_addr() {
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let stream = connect("localhost", &[addr], Duration::from_secs(5), None, false).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+
+    // Errors of the fast path are not wrapped in `ConnectAllFailed`.
+    drop(listener);
+    let err = connect("localhost", &[addr], Duration::from_secs(5), None, false).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Io(_)), "{:?}", err);
+
+    let deadline = Instant::now() - Duration::from_secs(1);
+    let err = connect("localhost", &[addr], Duration::from_secs(5), Some(deadline), false).unwrap_err();
+    match err.kind() {
+        ErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+        kind => panic!("unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn test_connect_multiple_addrs() {
+    use std::net::TcpListener;
+
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let open = listener.local_addr().unwrap();
+
+    let stream = connect("localhost", &[closed, open], Duration::from_secs(5), None, false).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), open);
+}
+
+#[cfg(test)]
+fn addrs(list: &[&str]) -> Vec<SocketAddr> {
+    list.iter().map(|addr| addr.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_sort_addrs_no_preference() {
+    let x = sort_addrs(&addrs(&["1.1.1.1:80", "1.0.0.1:80", "[::1]:80"]), None);
+    assert_eq!(x, addrs(&["[::1]:80", "1.1.1.1:80", "1.0.0.1:80"]));
+}
+
+#[test]
+fn test_sort_addrs_prefer_ipv4() {
+    let x = sort_addrs(&addrs(&["[::1]:80", "[::2]:80", "1.1.1.1:80"]), Some(AddressFamily::V4));
+    assert_eq!(x, addrs(&["1.1.1.1:80", "[::1]:80", "[::2]:80"]));
+}
+
+#[test]
+fn test_family_cache_remembers_success() {
+    let now = Instant::now();
+    let mut cache = FamilyCache::new(2, Duration::from_secs(60));
+    assert_eq!(cache.preferred("example.com", now), None);
+
+    cache.record_success("example.com", AddressFamily::V4, now);
+    assert_eq!(cache.preferred("example.com", now), Some(AddressFamily::V4));
+
+    cache.record_success("example.com", AddressFamily::V6, now);
+    assert_eq!(cache.preferred("example.com", now), Some(AddressFamily::V6));
+
+    cache.record_failure("example.com");
+    assert_eq!(cache.preferred("example.com", now), None);
+}
+
+#[test]
+fn test_family_cache_expiry() {
+    let now = Instant::now();
+    let mut cache = FamilyCache::new(2, Duration::from_secs(60));
+    cache.record_success("example.com", AddressFamily::V4, now);
+
+    assert_eq!(
+        cache.preferred("example.com", now + Duration::from_secs(59)),
+        Some(AddressFamily::V4)
+    );
+    assert_eq!(cache.preferred("example.com", now + Duration::from_secs(60)), None);
+}
+
+#[test]
+fn test_family_cache_bounded() {
+    let now = Instant::now();
+    let mut cache = FamilyCache::new(2, Duration::from_secs(60));
+    cache.record_success("a.com", AddressFamily::V4, now);
+    cache.record_success("b.com", AddressFamily::V4, now + Duration::from_secs(1));
+    cache.record_success("a.com", AddressFamily::V4, now + Duration::from_secs(2));
+    cache.record_success("c.com", AddressFamily::V4, now + Duration::from_secs(3));
+
+    let later = now + Duration::from_secs(4);
+    assert_eq!(cache.entries.len(), 2);
+    assert_eq!(cache.preferred("a.com", later), Some(AddressFamily::V4));
+    assert_eq!(cache.preferred("b.com", later), None);
+    assert_eq!(cache.preferred("c.com", later), Some(AddressFamily::V4));
+}
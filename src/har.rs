@@ -0,0 +1,387 @@
+//! Recording of the requests sent by a `Session` in the HAR format.
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+    PROXY_AUTHORIZATION, SET_COOKIE,
+};
+use http::StatusCode;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::error::Result;
+use crate::parsing::Response;
+
+/// Headers whose values are replaced by `[redacted]` unless `include_credentials` is set.
+const CREDENTIAL_HEADERS: [HeaderName; 4] = [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE];
+
+/// Durations of the steps of a request, measured by `PreparedRequest::send`.
+#[derive(Debug)]
+pub(crate) struct Timings {
+    pub connect: Duration,
+    pub ssl: Option<Duration>,
+    pub send: Duration,
+    pub wait: Duration,
+}
+
+#[derive(Debug, Default)]
+struct ResponseBody {
+    size: u64,
+    content: Vec<u8>,
+    truncated: bool,
+    receive: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    started: SystemTime,
+    method: String,
+    url: Url,
+    request_headers: HeaderMap,
+    status: StatusCode,
+    status_text: String,
+    response_headers: HeaderMap,
+    timings: Timings,
+    body_size: Option<u64>,
+    body: ResponseBody,
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    entries: Vec<Entry>,
+    max_body_capture: Option<usize>,
+    include_credentials: bool,
+}
+
+/// `HarRecorder` collects the requests sent by a `Session` and their responses, to export them in
+/// the [HAR 1.2](http://www.softwareishard.com/blog/har-12-spec/) format.
+///
+/// Every request sent, including the redirections followed, is recorded as an entry. The entry of
+/// a request is complete once its response body is read to the end or dropped. Clones of a
+/// `HarRecorder` share the same entries.
+///
+/// The values of the `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie` headers
+/// are recorded as `[redacted]`, since HAR files are often shared, unless `include_credentials` is
+/// set.
+///
+/// The timings are approximations: the DNS resolution is part of the connect time and the time
+/// spent waiting for a concurrency or rate limit is not recorded.
+///
+/// # Example
+/// ```no_run
+/// let recorder = attohttpc::HarRecorder::new().capture_bodies(64 * 1024);
+/// let mut session = attohttpc::Session::new();
+/// session.record_to(recorder.clone());
+///
+/// session.get("https://example.com").send()?.text()?;
+/// println!("{}", recorder.to_json());
+/// # Ok::<(), attohttpc::Error>(())
+/// ```
+///
+/// This type only exists when the `har` feature is enabled.
+#[derive(Clone, Default)]
+pub struct HarRecorder {
+    inner: Arc<Mutex<Recorded>>,
+}
+
+impl HarRecorder {
+    /// Create a new `HarRecorder` which does not capture the response bodies.
+    pub fn new() -> HarRecorder {
+        HarRecorder::default()
+    }
+
+    /// Capture up to `max_size` bytes of each response body, after decompression.
+    ///
+    /// Bodies which are not valid UTF-8 are not included in the HAR output.
+    pub fn capture_bodies(self, max_size: usize) -> HarRecorder {
+        self.lock().max_body_capture = Some(max_size);
+        self
+    }
+
+    /// Record the values of the `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie`
+    /// headers instead of redacting them.
+    ///
+    /// The default is false.
+    pub fn include_credentials(self, include: bool) -> HarRecorder {
+        self.lock().include_credentials = include;
+        self
+    }
+
+    /// Get the number of entries recorded.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Check if no entries were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the recorded entries as a HAR document.
+    pub fn to_json(&self) -> Value {
+        let recorded = self.lock();
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": "attohttpc",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": recorded.entries.iter().map(Entry::to_json).collect::<Vec<_>>(),
+            }
+        })
+    }
+
+    /// Write the recorded entries as a HAR document to any object that implements `Write`.
+    pub fn write_to<W>(&self, writer: W) -> Result
+    where
+        W: Write,
+    {
+        serde_json::to_writer_pretty(writer, &self.to_json())?;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Recorded> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn record(
+        &self,
+        started: SystemTime,
+        method: &http::Method,
+        url: &Url,
+        request_headers: &HeaderMap,
+        timings: Timings,
+        response: &mut Response,
+    ) {
+        let mut recorded = self.lock();
+        let index = recorded.entries.len();
+        let mut request_headers = request_headers.clone();
+        let mut response_headers = response.headers().clone();
+        if !recorded.include_credentials {
+            redact_credentials(&mut request_headers);
+            redact_credentials(&mut response_headers);
+        }
+        recorded.entries.push(Entry {
+            started,
+            method: method.to_string(),
+            url: url.clone(),
+            request_headers,
+            status: response.status(),
+            // The reason phrase received is recorded, the canonical one is only used when the
+            // server sent none.
+            status_text: response
+                .reason_phrase()
+                .or_else(|| response.status().canonical_reason())
+                .unwrap_or("")
+                .to_owned(),
+            response_headers,
+            timings,
+            body_size: response.size_hint().0,
+            body: ResponseBody::default(),
+        });
+        response.set_har_capture(BodyCapture {
+            recorder: self.clone(),
+            index,
+            start: Instant::now(),
+            max_content: recorded.max_body_capture.unwrap_or(0),
+            body: ResponseBody::default(),
+        });
+    }
+}
+
+impl fmt::Debug for HarRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HarRecorder").field("entries", &self.len()).finish()
+    }
+}
+
+/// Records the response body of an entry as it is read, the entry is updated when the end of the
+/// body is reached or when the `BodyCapture` is dropped.
+#[derive(Debug)]
+pub(crate) struct BodyCapture {
+    recorder: HarRecorder,
+    index: usize,
+    start: Instant,
+    max_content: usize,
+    body: ResponseBody,
+}
+
+impl BodyCapture {
+    pub fn on_read(&mut self, data: &[u8]) {
+        if data.is_empty() {
+            self.finish();
+            return;
+        }
+        self.body.size += data.len() as u64;
+        let capacity = self.max_content - self.body.content.len();
+        if data.len() > capacity {
+            self.body.truncated = true;
+        }
+        self.body.content.extend_from_slice(&data[..data.len().min(capacity)]);
+    }
+
+    fn finish(&mut self) {
+        if self.body.receive.is_some() {
+            return;
+        }
+        self.body.receive = Some(self.start.elapsed());
+        let mut recorded = self.recorder.lock();
+        if let Some(entry) = recorded.entries.get_mut(self.index) {
+            entry.body = ResponseBody {
+                size: self.body.size,
+                content: std::mem::take(&mut self.body.content),
+                truncated: self.body.truncated,
+                receive: self.body.receive,
+            };
+        }
+    }
+}
+
+impl Drop for BodyCapture {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn redact_credentials(headers: &mut HeaderMap) {
+    for name in CREDENTIAL_HEADERS {
+        if let http::header::Entry::Occupied(mut entry) = headers.entry(name) {
+            for value in entry.iter_mut() {
+                *value = HeaderValue::from_static("[redacted]");
+            }
+        }
+    }
+}
+
+fn headers_to_json(headers: &HeaderMap) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            json!({
+                "name": name.as_str(),
+                "value": String::from_utf8_lossy(value.as_bytes()),
+            })
+        })
+        .collect()
+}
+
+fn header_str(headers: &HeaderMap, name: http::header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Entry {
+    fn to_json(&self) -> Value {
+        let request_body_size = match header_str(&self.request_headers, CONTENT_LENGTH) {
+            Some(len) => len.parse::<i64>().unwrap_or(-1),
+            None if self.request_headers.contains_key(http::header::TRANSFER_ENCODING) => -1,
+            None => 0,
+        };
+
+        let mut content = json!({
+            "size": self.body.size,
+            "mimeType": header_str(&self.response_headers, CONTENT_TYPE).unwrap_or(""),
+        });
+        if let Ok(text) = std::str::from_utf8(&self.body.content) {
+            if !text.is_empty() {
+                content["text"] = text.into();
+            }
+        }
+        if self.body.truncated {
+            content["comment"] = format!("truncated to {} bytes", self.body.content.len()).into();
+        }
+
+        let receive = self.body.receive.map_or(0.0, millis);
+        let ssl = self.timings.ssl.map_or(-1.0, millis);
+        let time = millis(self.timings.connect + self.timings.send + self.timings.wait) + receive;
+
+        json!({
+            "startedDateTime": format_rfc3339(self.started),
+            "time": time,
+            "request": {
+                "method": self.method,
+                "url": self.url.as_str(),
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": headers_to_json(&self.request_headers),
+                "queryString": self.url.query_pairs().map(|(name, value)| json!({
+                    "name": name,
+                    "value": value,
+                })).collect::<Vec<_>>(),
+                "headersSize": -1,
+                "bodySize": request_body_size,
+            },
+            "response": {
+                "status": self.status.as_u16(),
+                "statusText": self.status_text,
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": headers_to_json(&self.response_headers),
+                "content": content,
+                "redirectURL": header_str(&self.response_headers, LOCATION).unwrap_or(""),
+                "headersSize": -1,
+                "bodySize": self.body_size.map_or(-1, |size| size as i64),
+            },
+            "cache": {},
+            "timings": {
+                "blocked": -1,
+                "dns": -1,
+                "connect": millis(self.timings.connect),
+                "ssl": ssl,
+                "send": millis(self.timings.send),
+                "wait": millis(self.timings.wait),
+                "receive": receive,
+            },
+        })
+    }
+}
+
+/// Format the time as an ISO 8601 date in UTC with millisecond precision.
+fn format_rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Convert the number of days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[test]
+fn test_format_rfc3339() {
+    assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    assert_eq!(
+        format_rfc3339(UNIX_EPOCH + Duration::from_millis(951_782_400_123)),
+        "2000-02-29T00:00:00.123Z"
+    );
+    assert_eq!(
+        format_rfc3339(UNIX_EPOCH + Duration::from_secs(1_792_152_059)),
+        "2026-10-16T12:00:59.000Z"
+    );
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use http::header::{HeaderMap, CONTENT_TYPE};
+use http::StatusCode;
+use url::Url;
+
+use crate::error::{ErrorKind, Result};
+use crate::{IntoUrl, RequestBuilder, Response, Session};
+
+/// Default maximum number of pages fetched by `paginate`.
+const DEFAULT_MAX_PAGES: usize = 100;
+
+/// Iterate over the pages of a paginated resource by following the `rel="next"` links of the
+/// `Link` headers.
+///
+/// Every request is created with the settings of the `Session` and can be customized using the
+/// `customize` function, to add authentication or query parameters for instance. Relative links
+/// are resolved against the URL of the page which contains them.
+///
+/// The iteration stops when a page has no next link, after an error is returned, after a page
+/// links to a page which was already fetched or after `max_pages` pages, 100 by default.
+///
+/// # Example
+/// ```no_run
+/// let session = attohttpc::Session::new();
+/// for page in attohttpc::paginate(&session, "https://api.github.com/repos/rust-lang/rust/issues", |req| req) {
+///     let page = page?.error_for_status()?;
+///     println!("{}", page.text()?);
+/// }
+/// # Ok::<(), attohttpc::Error>(())
+/// ```
+pub fn paginate<U, F>(session: &Session, first_url: U, customize: F) -> Pages<'_, F>
+where
+    U: IntoUrl,
+    F: FnMut(RequestBuilder) -> RequestBuilder,
+{
+    Pages {
+        session,
+        next: Some(first_url.into_url()),
+        visited: Vec::new(),
+        customize,
+        max_pages: DEFAULT_MAX_PAGES,
+    }
+}
+
+/// Iterator over the pages of a paginated resource, created by [`paginate`].
+pub struct Pages<'a, F> {
+    session: &'a Session,
+    next: Option<Result<Url>>,
+    visited: Vec<Url>,
+    customize: F,
+    max_pages: usize,
+}
+
+impl<F> Pages<'_, F> {
+    /// Set the maximum number of pages fetched.
+    ///
+    /// The default is 100.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+}
+
+impl<F> Iterator for Pages<'_, F>
+where
+    F: FnMut(RequestBuilder) -> RequestBuilder,
+{
+    type Item = Result<Response>;
+
+    fn next(&mut self) -> Option<Result<Response>> {
+        let url = match self.next.take()? {
+            Ok(url) => url,
+            Err(err) => return Some(Err(err)),
+        };
+
+        if self.visited.len() >= self.max_pages {
+            debug!("stopping pagination after {} pages", self.visited.len());
+            return None;
+        }
+        if self.visited.contains(&url) {
+            debug!("stopping pagination, {} was already fetched", url);
+            return None;
+        }
+
+        let resp = match (self.customize)(self.session.get(&url)).send() {
+            Ok(resp) => resp,
+            Err(err) => return Some(Err(err)),
+        };
+
+        self.next = resp
+            .links()
+            .into_iter()
+            .find(|link| link.has_rel("next"))
+            .and_then(|link| resp.url().join(&link.href).ok())
+            .map(Ok);
+        self.visited.push(url);
+
+        Some(Ok(resp))
+    }
+}
+
+impl<F> fmt::Debug for Pages<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pages")
+            .field("next", &self.next)
+            .field("visited", &self.visited)
+            .field("max_pages", &self.max_pages)
+            .finish()
+    }
+}
+
+/// Options of [`get_to_writer`].
+#[derive(Clone, Debug)]
+pub struct GetOptions {
+    /// Timeout of the whole request, including redirections and reading the body. The default is
+    /// no timeout.
+    pub timeout: Option<Duration>,
+    /// Maximum number of bytes of the body to write. The default is no limit.
+    pub max_size: Option<u64>,
+    /// Headers added to the request, replacing the default headers with the same name.
+    pub headers: HeaderMap,
+    /// Fail with `ErrorKind::StatusCode` without writing the body if the status code is not a
+    /// success code. When false, the body is written whatever the status code. The default is true.
+    pub error_for_status: bool,
+}
+
+impl Default for GetOptions {
+    fn default() -> GetOptions {
+        GetOptions {
+            timeout: None,
+            max_size: None,
+            headers: HeaderMap::new(),
+            error_for_status: true,
+        }
+    }
+}
+
+/// Summary of a response written by [`get_to_writer`].
+#[derive(Clone, Debug)]
+pub struct DownloadSummary {
+    /// Status code of the response.
+    pub status: StatusCode,
+    /// Number of bytes of the body written, after decompression.
+    pub bytes_written: u64,
+    /// URL of the response, after following redirections.
+    pub final_url: Url,
+    /// Value of the `Content-Type` header of the response, if any.
+    pub content_type: Option<String>,
+}
+
+/// Send a GET request to the URL and write the response body to the writer.
+///
+/// Redirections are followed like with [`get`](crate::get). A body larger than `max_size` fails
+/// with `ErrorKind::ResponseTooLarge`. When the size of the body is known in advance, nothing is
+/// written in that case, otherwise the first `max_size` bytes are written before the error is
+/// returned.
+///
+/// # Example
+/// ```no_run
+/// let mut file = std::fs::File::create("index.html")?;
+/// let options = attohttpc::GetOptions {
+///     timeout: Some(std::time::Duration::from_secs(30)),
+///     max_size: Some(10 * 1024 * 1024),
+///     ..Default::default()
+/// };
+/// let summary = attohttpc::get_to_writer("https://www.rust-lang.org", &mut file, options)?;
+/// println!("wrote {} bytes from {}", summary.bytes_written, summary.final_url);
+/// # Ok::<(), attohttpc::Error>(())
+/// ```
+pub fn get_to_writer<U, W>(url: U, writer: &mut W, options: GetOptions) -> Result<DownloadSummary>
+where
+    U: IntoUrl,
+    W: Write + ?Sized,
+{
+    let mut req = crate::get(url);
+    if let Some(timeout) = options.timeout {
+        req = req.timeout(timeout);
+    }
+    req.headers_mut().extend(options.headers);
+
+    let mut resp = req.send()?;
+    if options.error_for_status {
+        resp = resp.error_for_status()?;
+    }
+
+    if let (Some(max_size), (_, Some(size))) = (options.max_size, resp.size_hint()) {
+        if size > max_size {
+            return Err(ErrorKind::ResponseTooLarge { max_size }.into());
+        }
+    }
+
+    let final_url = resp.url().clone();
+    let (status, headers, reader) = resp.split();
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+
+    let bytes_written = match options.max_size {
+        Some(max_size) => {
+            let mut limited = reader.take(max_size);
+            let written = io::copy(&mut limited, writer)?;
+            // Any byte left means that the body is larger than the maximum size.
+            if limited.into_inner().read(&mut [0])? > 0 {
+                return Err(ErrorKind::ResponseTooLarge { max_size }.into());
+            }
+            written
+        }
+        None => reader.write_to(writer)?,
+    };
+
+    Ok(DownloadSummary {
+        status,
+        bytes_written,
+        final_url,
+        content_type,
+    })
+}
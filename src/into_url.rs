@@ -0,0 +1,70 @@
+use url::Url;
+
+use crate::error::{ErrorKind, Result};
+
+/// A value which can be used as the URL of a request.
+///
+/// It is implemented for every type which implements `AsRef<str>`, like strings and `Url`. A `Url`
+/// can be passed as is, it does not need to be formatted first.
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait IntoUrl: private::Sealed {
+    #[doc(hidden)]
+    fn into_url(self) -> Result<Url>;
+
+    #[doc(hidden)]
+    fn as_url_str(&self) -> &str;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+impl<T: AsRef<str>> IntoUrl for T {
+    fn into_url(self) -> Result<Url> {
+        Url::parse(self.as_ref()).map_err(|_| ErrorKind::InvalidBaseUrl.into())
+    }
+
+    fn as_url_str(&self) -> &str {
+        self.as_ref()
+    }
+}
+
+impl<T: AsRef<str>> private::Sealed for T {}
+
+#[test]
+fn test_into_url() {
+    use std::borrow::Cow;
+
+    let url = Url::parse("http://example.com/a?b=c").unwrap();
+    assert_eq!("http://example.com/a?b=c".into_url().unwrap(), url);
+    assert_eq!(String::from("http://example.com/a?b=c").into_url().unwrap(), url);
+    assert_eq!((&String::from("http://example.com/a?b=c")).into_url().unwrap(), url);
+    assert_eq!(Cow::Borrowed("http://example.com/a?b=c").into_url().unwrap(), url);
+    assert_eq!((&url).into_url().unwrap(), url);
+    assert_eq!(url.clone().into_url().unwrap(), url);
+    assert_eq!((&"http://example.com/a?b=c").into_url().unwrap(), url);
+    assert_eq!(Box::<str>::from("http://example.com/a?b=c").into_url().unwrap(), url);
+    assert_eq!(
+        std::sync::Arc::<str>::from("http://example.com/a?b=c")
+            .into_url()
+            .unwrap(),
+        url
+    );
+    assert_eq!((&Cow::Borrowed("http://example.com/a?b=c")).into_url().unwrap(), url);
+
+    struct Endpoint;
+
+    impl AsRef<str> for Endpoint {
+        fn as_ref(&self) -> &str {
+            "http://example.com/a?b=c"
+        }
+    }
+
+    assert_eq!(Endpoint.into_url().unwrap(), url);
+
+    assert!(matches!(
+        "not a url".into_url().unwrap_err().kind(),
+        ErrorKind::InvalidBaseUrl
+    ));
+}
@@ -39,12 +39,16 @@
 //!
 //! # Features
 //! * `basic-auth` support for basic auth
+//! * `bytes` support for reading response bodies to a `bytes::Bytes` buffer
 //! * `charsets` support for decoding more text encodings than just UTF-8
-//! * `compress` support for decompressing response bodies using `miniz_oxide` (**default**)
+//! * `checksum` support for verifying the SHA-256 digest of downloaded files
+//! * `cookies` support for parsing the cookies set by responses
+//! * `compress` support for decompressing response bodies and compressing request bodies using `miniz_oxide` (**default**)
 //! * `compress-zlib` support for decompressing response bodies using `zlib` instead of `miniz_oxide`
 //!   (see [flate2 backends](https://github.com/rust-lang/flate2-rs#backends))
 //! * `compress-zlib-ng` support for decompressing response bodies using `zlib-ng` instead of `miniz_oxide`
 //!   (see [flate2 backends](https://github.com/rust-lang/flate2-rs#backends))
+//! * `har` support for recording requests in the HAR format, implies `json`
 //! * `json` support for serialization and deserialization
 //! * `form` support for url encoded forms (does not include support for multipart)
 //! * `multipart-form` support for multipart forms (does not include support for url encoding)
@@ -63,6 +67,8 @@
 #[cfg(feature = "__rustls")]
 extern crate rustls_opt_dep as rustls;
 
+use std::sync::OnceLock;
+
 macro_rules! debug {
     ($($arg:tt)+) => { log::debug!(target: "attohttpc", $($arg)+) };
 }
@@ -71,95 +77,188 @@ macro_rules! warn {
     ($($arg:tt)+) => { log::warn!(target: "attohttpc", $($arg)+) };
 }
 
+#[cfg(feature = "__bench")]
+#[doc(hidden)]
+pub mod bench;
+mod cache;
 #[cfg(feature = "charsets")]
 pub mod charsets;
 mod error;
 mod happy;
-#[cfg(feature = "multipart")]
+#[cfg(feature = "har")]
+mod har;
+mod helpers;
+mod into_url;
+#[cfg(feature = "multipart-form")]
 mod multipart;
 mod parsing;
+pub mod raw;
 mod request;
 mod streams;
+#[cfg(feature = "testing")]
+#[doc(hidden)]
+pub mod testing;
 mod tls;
 
+pub use crate::cache::CacheConfig;
 pub use crate::error::{Error, ErrorKind, InvalidResponseKind, Result};
-#[cfg(feature = "multipart")]
+#[cfg(feature = "har")]
+pub use crate::har::HarRecorder;
+pub use crate::helpers::{get_to_writer, paginate, DownloadSummary, GetOptions, Pages};
+pub use crate::into_url::IntoUrl;
+#[cfg(feature = "multipart-form")]
 pub use crate::multipart::{Multipart, MultipartBuilder, MultipartFile};
-pub use crate::parsing::{Response, ResponseReader};
+pub use crate::parsing::{
+    AuthChallenge, CacheControl, ConnectionInfo, CorsInfo, Link, RedirectHop, Response, ResponseReader,
+    ServerTimingMetric, TeeReader, WwwAuthenticate,
+};
 pub use crate::request::proxy::{ProxySettings, ProxySettingsBuilder};
-pub use crate::request::{body, PreparedRequest, RequestBuilder, RequestInspector, Session};
+pub use crate::request::{
+    body, PreparedRequest, RequestBuilder, RequestInspector, RequestTemplate, Session, Signer, UpgradedStream,
+};
+pub use crate::tls::{parse_pem_certificates, TlsVersion};
 #[cfg(feature = "charsets")]
 pub use crate::{charsets::Charset, parsing::TextReader};
+#[cfg(feature = "cookies")]
+pub use cookie::Cookie;
 pub use http::Method;
 pub use http::StatusCode;
+pub use http::Uri;
 
 pub mod header {
     //! This module is a re-export of the `http` crate's `header` module.
     pub use http::header::*;
 }
 
+static DEFAULT_SESSION: OnceLock<Session> = OnceLock::new();
+
+/// Set the `Session` whose settings are used by the functions creating requests without a
+/// `Session`, like [`get`] and [`post`].
+///
+/// This allows setting a timeout or a `User-Agent` once for the whole process, including code
+/// which does not take a `Session`. The default `Session` can only be set once, it is returned
+/// back as an error if one was already set. Until it's set, the requests use the default
+/// settings, with the proxy settings read from the environment.
+///
+/// # Example
+/// ```
+/// let mut session = attohttpc::Session::new();
+/// session.header("User-Agent", "my-tool/1.0");
+/// session.timeout(std::time::Duration::from_secs(10));
+/// assert!(attohttpc::set_default_session(session).is_ok());
+/// ```
+#[allow(clippy::result_large_err)]
+pub fn set_default_session(session: Session) -> std::result::Result<(), Session> {
+    DEFAULT_SESSION.set(session)
+}
+
+/// Get the `Session` set with `set_default_session`, if any.
+pub fn default_session() -> Option<&'static Session> {
+    DEFAULT_SESSION.get()
+}
+
 /// Create a new `RequestBuilder` with the GET method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn get<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::GET, base_url)
+    match default_session() {
+        Some(session) => session.get(base_url),
+        None => RequestBuilder::new(Method::GET, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the POST method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn post<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::POST, base_url)
+    match default_session() {
+        Some(session) => session.post(base_url),
+        None => RequestBuilder::new(Method::POST, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the PUT method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn put<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::PUT, base_url)
+    match default_session() {
+        Some(session) => session.put(base_url),
+        None => RequestBuilder::new(Method::PUT, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the DELETE method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn delete<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::DELETE, base_url)
+    match default_session() {
+        Some(session) => session.delete(base_url),
+        None => RequestBuilder::new(Method::DELETE, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the HEAD method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn head<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::HEAD, base_url)
+    match default_session() {
+        Some(session) => session.head(base_url),
+        None => RequestBuilder::new(Method::HEAD, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the OPTIONS method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn options<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::OPTIONS, base_url)
+    match default_session() {
+        Some(session) => session.options(base_url),
+        None => RequestBuilder::new(Method::OPTIONS, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the PATCH method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn patch<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::PATCH, base_url)
+    match default_session() {
+        Some(session) => session.patch(base_url),
+        None => RequestBuilder::new(Method::PATCH, base_url),
+    }
 }
 
 /// Create a new `RequestBuilder` with the TRACE method.
+///
+/// The settings of the default `Session` are used if one was set with `set_default_session`.
 pub fn trace<U>(base_url: U) -> RequestBuilder
 where
-    U: AsRef<str>,
+    U: IntoUrl,
 {
-    RequestBuilder::new(Method::TRACE, base_url)
+    match default_session() {
+        Some(session) => session.trace(base_url),
+        None => RequestBuilder::new(Method::TRACE, base_url),
+    }
 }
 
 mod skip_debug {
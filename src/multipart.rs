@@ -1,16 +1,25 @@
 use super::body::{Body, BodyKind};
 use super::{Error, ErrorKind, Result};
+#[cfg(feature = "charsets")]
+use crate::charsets::Charset;
 use mime::Mime;
-use multipart::client as mp;
-use std::fmt;
-use std::io::{copy, prelude::*, Cursor, Error as IoError, Result as IoResult};
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, prelude::*, Result as IoResult};
+use std::path::Path;
+use std::sync::Arc;
+
+const MAX_BOUNDARY_LEN: usize = 70;
 
 /// A file to be uploaded as part of a multipart form.
 #[derive(Debug, Clone)]
 pub struct MultipartFile<'key, 'data> {
-    name: &'key str,
-    file: &'data [u8],
-    filename: Option<&'key str>,
+    name: Cow<'key, str>,
+    file: PartData<'data>,
+    filename: Option<Cow<'key, str>>,
     mime: Option<Mime>,
 }
 
@@ -18,13 +27,33 @@ impl<'key, 'data> MultipartFile<'key, 'data> {
     /// Constructs a new `MultipartFile` from the name and contents.
     pub fn new(name: &'key str, file: &'data [u8]) -> Self {
         Self {
-            name,
-            file,
+            name: Cow::Borrowed(name),
+            file: PartData::Bytes(Cow::Borrowed(file)),
             filename: None,
             mime: None,
         }
     }
 
+    /// Constructs a new `MultipartFile` from the name and an open file.
+    ///
+    /// The file is not read into memory, it is read from the start every time the form is sent,
+    /// for instance when a redirect is followed. Its length is taken from its metadata, so the file
+    /// must not change size until the request is sent. The file is shared by the clones of the
+    /// form. On unix and Windows, each read gives its own position, so the clones can be sent
+    /// concurrently. The offset of the file may still be moved.
+    ///
+    /// # Errors
+    /// Returns an error if the metadata of the file cannot be read.
+    pub fn from_file(name: &'key str, file: fs::File) -> Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(Self {
+            name: Cow::Borrowed(name),
+            file: PartData::File(Arc::new(file), len),
+            filename: None,
+            mime: None,
+        })
+    }
+
     /// Sets the MIME type of the file.
     ///
     /// # Errors
@@ -44,17 +73,27 @@ impl<'key, 'data> MultipartFile<'key, 'data> {
     /// Sets the filename of the file.
     pub fn with_filename(self, filename: &'key str) -> Self {
         Self {
-            filename: Some(filename),
+            filename: Some(Cow::Borrowed(filename)),
             ..self
         }
     }
 }
 
+#[derive(Debug, Clone)]
+struct TextField<'key, 'data> {
+    name: &'key str,
+    text: &'data str,
+    mime: Option<Mime>,
+}
+
 /// A builder for creating a `Multipart` body.
 #[derive(Debug, Clone, Default)]
 pub struct MultipartBuilder<'key, 'data> {
-    text: Vec<(&'key str, &'data str)>,
+    text: Vec<TextField<'key, 'data>>,
     files: Vec<MultipartFile<'key, 'data>>,
+    boundary: Option<String>,
+    #[cfg(feature = "charsets")]
+    text_charset: Option<Charset>,
 }
 
 impl<'key, 'data> MultipartBuilder<'key, 'data> {
@@ -64,53 +103,687 @@ impl<'key, 'data> MultipartBuilder<'key, 'data> {
     }
 
     /// Adds a text field to the form.
+    ///
+    /// The field has no `Content-Type` unless a charset is set with `default_text_charset`.
     pub fn with_text(mut self, name: &'key str, text: &'data str) -> Self {
-        self.text.push((name, text));
+        self.text.push(TextField { name, text, mime: None });
         self
     }
 
+    /// Adds a text field to the form with the given `Content-Type`.
+    ///
+    /// The text is sent as UTF-8, so the content type should not name another charset.
+    ///
+    /// # Errors
+    /// Returns an error if the MIME type is invalid.
+    pub fn with_text_typed(mut self, name: &'key str, text: &'data str, content_type: impl AsRef<str>) -> Result<Self> {
+        let mime: Mime = match content_type.as_ref().parse() {
+            Ok(mime) => mime,
+            Err(error) => return Err(Error(Box::new(ErrorKind::InvalidMimeType(error.to_string())))),
+        };
+        self.text.push(TextField {
+            name,
+            text,
+            mime: Some(mime),
+        });
+        Ok(self)
+    }
+
+    /// Sets the charset of the text fields added with `with_text`.
+    ///
+    /// Their values are encoded with this charset and sent with a
+    /// `Content-Type: text/plain; charset=...` header, since some servers assume ISO-8859-1 for
+    /// the fields without a content type.
+    ///
+    /// This method only exists when the `charsets` feature is enabled.
+    #[cfg(feature = "charsets")]
+    pub fn default_text_charset(self, charset: Charset) -> Self {
+        Self {
+            text_charset: Some(charset),
+            ..self
+        }
+    }
+
     /// Adds a `MultipartFile` to the form.
     pub fn with_file(mut self, file: MultipartFile<'key, 'data>) -> Self {
         self.files.push(file);
         self
     }
 
+    /// Adds the files of a directory to the form, in the order of their paths.
+    ///
+    /// The file name of each part is the path of the file relative to `dir`, using `/` as the
+    /// separator, and its name is that path prefixed by `name_prefix`. The MIME type is guessed
+    /// from the extension of the file, falling back to `application/octet-stream`. The files of
+    /// subdirectories are added if `recursive` is true. Symbolic links to directories are not
+    /// followed.
+    ///
+    /// The files are read into memory when this method is called.
+    ///
+    /// # Errors
+    /// Returns an error if the directory or one of its files cannot be read.
+    pub fn with_dir(mut self, name_prefix: &str, dir: impl AsRef<Path>, recursive: bool) -> Result<Self> {
+        let mut paths = Vec::new();
+        collect_files(dir.as_ref(), "", recursive, &mut paths)?;
+        paths.sort();
+
+        for relative in paths {
+            let path = dir.as_ref().join(&relative);
+            self.files.push(MultipartFile {
+                name: Cow::Owned(format!("{name_prefix}{relative}")),
+                file: PartData::Bytes(Cow::Owned(fs::read(&path)?)),
+                mime: Some(mime_from_path(&path)),
+                filename: Some(Cow::Owned(relative)),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Sets the boundary used to separate the parts of the form.
+    ///
+    /// By default a random boundary is generated. A fixed boundary is useful to produce
+    /// reproducible requests, but it must not appear anywhere in the data of the form.
+    ///
+    /// # Errors
+    /// Returns an error if the boundary is not valid according to RFC 2046, i.e. if it is empty,
+    /// longer than 70 characters, contains characters which are not allowed or ends with a space.
+    pub fn with_boundary(self, boundary: impl Into<String>) -> Result<Self> {
+        let boundary = boundary.into();
+        if !is_valid_boundary(&boundary) {
+            return Err(Error(Box::new(ErrorKind::InvalidMultipartBoundary(boundary))));
+        }
+        Ok(Self {
+            boundary: Some(boundary),
+            ..self
+        })
+    }
+
     /// Creates a `Multipart` to be used as a body.
+    ///
+    /// A form without any field is valid, its body only contains the closing delimiter.
     pub fn build(self) -> Result<Multipart<'data>> {
-        let mut mp = mp::lazy::Multipart::new();
-        for (k, v) in self.text {
-            mp.add_text(k, v);
+        let mut parts = Vec::with_capacity(self.text.len() + self.files.len());
+
+        for field in self.text {
+            let mut header = String::new();
+            write_disposition(&mut header, field.name, None);
+            let data = match field.mime {
+                Some(mime) => {
+                    let _ = write!(header, "\r\nContent-Type: {mime}");
+                    PartData::Bytes(Cow::Borrowed(field.text.as_bytes()))
+                }
+                None => {
+                    #[cfg(feature = "charsets")]
+                    let encoded = self
+                        .text_charset
+                        .map(|charset| {
+                            let (encoded, charset, _) = charset.encode(field.text);
+                            let _ = write!(header, "\r\nContent-Type: text/plain; charset={}", charset.name());
+                            encoded
+                        })
+                        .unwrap_or(Cow::Borrowed(field.text.as_bytes()));
+                    #[cfg(not(feature = "charsets"))]
+                    let encoded = Cow::Borrowed(field.text.as_bytes());
+                    PartData::Bytes(encoded)
+                }
+            };
+            header.push_str("\r\n\r\n");
+            parts.push(Part { header, data });
         }
+
         for file in self.files {
-            mp.add_stream(file.name, Cursor::new(file.file), file.filename, file.mime);
+            let mime = file.mime.unwrap_or(mime::APPLICATION_OCTET_STREAM);
+            let mut header = String::new();
+            write_disposition(&mut header, &file.name, file.filename.as_deref());
+            let _ = write!(header, "\r\nContent-Type: {mime}\r\n\r\n");
+            parts.push(Part {
+                header,
+                data: file.file,
+            });
         }
-        let prepared = mp.prepare().map_err::<IoError, _>(Into::into)?;
-        Ok(Multipart { data: prepared })
+
+        Ok(Multipart {
+            boundary: self.boundary.unwrap_or_else(gen_boundary),
+            parts,
+        })
     }
 }
 
+/// Collect the paths of the files in `dir`, relative to the directory given to `with_dir`.
+fn collect_files(dir: &Path, prefix: &str, recursive: bool, paths: &mut Vec<String>) -> Result {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let relative = format!("{prefix}{name}");
+
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                collect_files(&entry.path(), &format!("{relative}/"), recursive, paths)?;
+            }
+        } else if fs::metadata(entry.path())?.is_file() {
+            paths.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Guess the MIME type of a file from its extension.
+fn mime_from_path(path: &Path) -> Mime {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "txt" => mime::TEXT_PLAIN,
+        "html" | "htm" => mime::TEXT_HTML,
+        "css" => mime::TEXT_CSS,
+        "csv" => mime::TEXT_CSV,
+        "js" | "mjs" => mime::TEXT_JAVASCRIPT,
+        "xml" => mime::TEXT_XML,
+        "json" => mime::APPLICATION_JSON,
+        "pdf" => mime::APPLICATION_PDF,
+        "png" => mime::IMAGE_PNG,
+        "jpg" | "jpeg" => mime::IMAGE_JPEG,
+        "gif" => mime::IMAGE_GIF,
+        "bmp" => mime::IMAGE_BMP,
+        "svg" => mime::IMAGE_SVG,
+        "woff" => mime::FONT_WOFF,
+        "woff2" => mime::FONT_WOFF2,
+        _ => mime::APPLICATION_OCTET_STREAM,
+    }
+}
+
+fn is_boundary_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "'()+_,-./:=? ".contains(c)
+}
+
+fn is_valid_boundary(boundary: &str) -> bool {
+    !boundary.is_empty()
+        && boundary.len() <= MAX_BOUNDARY_LEN
+        && boundary.chars().all(is_boundary_char)
+        && !boundary.ends_with(' ')
+}
+
+/// Generate a random boundary using the randomly seeded keys of the standard library's hasher.
+fn gen_boundary() -> String {
+    let state = RandomState::new();
+    let mut boundary = String::with_capacity(32);
+    for i in 0..2u8 {
+        let mut hasher = state.build_hasher();
+        hasher.write_u8(i);
+        let _ = write!(boundary, "{:016x}", hasher.finish());
+    }
+    boundary
+}
+
+fn write_quoted(out: &mut String, value: &str) {
+    // Same escaping as the HTML standard uses for form data.
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("%22"),
+            '\r' => out.push_str("%0D"),
+            '\n' => out.push_str("%0A"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_disposition(out: &mut String, name: &str, filename: Option<&str>) {
+    out.push_str("Content-Disposition: form-data; name=");
+    write_quoted(out, name);
+    if let Some(filename) = filename {
+        out.push_str("; filename=");
+        write_quoted(out, filename);
+    }
+}
+
+/// Data of a part, kept in memory or read from a file with the given length when the form is sent.
+#[derive(Debug, Clone)]
+enum PartData<'data> {
+    Bytes(Cow<'data, [u8]>),
+    File(Arc<fs::File>, u64),
+}
+
+impl PartData<'_> {
+    fn len(&self) -> u64 {
+        match self {
+            PartData::Bytes(bytes) => bytes.len() as u64,
+            PartData::File(_, len) => *len,
+        }
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> IoResult<()> {
+        match self {
+            PartData::Bytes(bytes) => writer.write_all(bytes),
+            PartData::File(file, len) => {
+                let file = FileAt { file, pos: 0 };
+                // The length was sent in the headers, the body would be invalid if it changed.
+                if io::copy(&mut file.take(*len), &mut writer)? < *len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "file is shorter than its length when the form was built",
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads a file from a position which is given to every read, since the offset of the file is shared
+/// by the clones of a form. The offset is moved by the reads on Windows, but not used by them. Where
+/// positional reads are not available, the file is read from its offset after seeking, so clones of
+/// a form must not be sent concurrently.
+struct FileAt<'a> {
+    file: &'a fs::File,
+    pos: u64,
+}
+
+impl Read for FileAt<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        #[cfg(unix)]
+        let read = std::os::unix::fs::FileExt::read_at(self.file, buf, self.pos)?;
+        #[cfg(windows)]
+        let read = std::os::windows::fs::FileExt::seek_read(self.file, buf, self.pos)?;
+        #[cfg(not(any(unix, windows)))]
+        let read = {
+            let mut file = self.file;
+            file.seek(io::SeekFrom::Start(self.pos))?;
+            file.read(buf)?
+        };
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+#[derive(Clone)]
+struct Part<'data> {
+    header: String,
+    data: PartData<'data>,
+}
+
 /// A multipart form created using `MultipartBuilder`.
+#[derive(Clone)]
 pub struct Multipart<'data> {
-    data: mp::lazy::PreparedFields<'data>,
+    boundary: String,
+    parts: Vec<Part<'data>>,
+}
+
+impl Multipart<'_> {
+    /// Get the boundary used to separate the parts of the form.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    fn len(&self) -> u64 {
+        // "--" boundary "\r\n" for every part, "\r\n" between parts, "--" boundary "--\r\n" at the end.
+        let delimiter = self.boundary.len() + 4;
+        let parts: u64 = self
+            .parts
+            .iter()
+            .map(|part| (delimiter + part.header.len() + 2) as u64 + part.data.len())
+            .sum();
+        parts + (delimiter + 2) as u64
+    }
 }
 
 impl Body for Multipart<'_> {
     fn kind(&mut self) -> IoResult<BodyKind> {
-        Ok(BodyKind::Chunked)
+        Ok(BodyKind::KnownLength(self.len()))
     }
 
     fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
-        copy(&mut self.data, &mut writer)?;
+        for part in &self.parts {
+            write!(writer, "--{}\r\n", self.boundary)?;
+            writer.write_all(part.header.as_bytes())?;
+            part.data.write(&mut writer)?;
+            writer.write_all(b"\r\n")?;
+        }
+        write!(writer, "--{}--\r\n", self.boundary)?;
         Ok(())
     }
 
     fn content_type(&mut self) -> IoResult<Option<String>> {
-        Ok(Some(format!("multipart/form-data; boundary={}", self.data.boundary())))
+        let mut content_type = String::from("multipart/form-data; boundary=");
+        if self
+            .boundary
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "'+_-.".contains(c))
+        {
+            content_type.push_str(&self.boundary);
+        } else {
+            write_quoted(&mut content_type, &self.boundary);
+        }
+        Ok(Some(content_type))
     }
 }
 
 impl fmt::Debug for Multipart<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Multipart").finish()
+        f.debug_struct("Multipart").field("boundary", &self.boundary).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(mut form: Multipart) -> (String, String) {
+        let content_type = form.content_type().unwrap().unwrap();
+        let len = match form.kind().unwrap() {
+            BodyKind::KnownLength(len) => len,
+            kind => panic!("unexpected body kind {:?}", kind),
+        };
+
+        let mut body = Vec::new();
+        form.write(&mut body).unwrap();
+        assert_eq!(body.len() as u64, len);
+
+        (content_type, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_fixed_boundary() {
+        let form = MultipartBuilder::new()
+            .with_text("Hello", "world!")
+            .with_file(
+                MultipartFile::new("file", b"Hello, world!")
+                    .with_type("text/plain")
+                    .unwrap()
+                    .with_filename("hello.txt"),
+            )
+            .with_boundary("fixed-boundary")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(form.boundary(), "fixed-boundary");
+
+        let (content_type, body) = serialize(form);
+        assert_eq!(content_type, "multipart/form-data; boundary=fixed-boundary");
+        assert_eq!(
+            body,
+            "--fixed-boundary\r\n\
+             Content-Disposition: form-data; name=\"Hello\"\r\n\r\n\
+             world!\r\n\
+             --fixed-boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"hello.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             Hello, world!\r\n\
+             --fixed-boundary--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_form() {
+        let form = MultipartBuilder::new().with_boundary("b").unwrap().build().unwrap();
+        assert_eq!(form.boundary(), "b");
+
+        let (content_type, body) = serialize(form);
+        assert_eq!(content_type, "multipart/form-data; boundary=b");
+        assert_eq!(body, "--b--\r\n");
+    }
+
+    #[test]
+    fn test_empty_form_random_boundary() {
+        let form = MultipartBuilder::new().build().unwrap();
+        let boundary = form.boundary().to_owned();
+        assert!(is_valid_boundary(&boundary));
+
+        let (content_type, body) = serialize(form);
+        assert_eq!(content_type, format!("multipart/form-data; boundary={boundary}"));
+        assert_eq!(body, format!("--{boundary}--\r\n"));
+    }
+
+    #[test]
+    fn test_text_only_form() {
+        let form = MultipartBuilder::new()
+            .with_text("a", "1")
+            .with_text("b", "2")
+            .with_boundary("b")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (_, body) = serialize(form);
+        assert_eq!(
+            body,
+            "--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"b\"\r\n\r\n2\r\n\
+             --b--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_files_only_form() {
+        let form = MultipartBuilder::new()
+            .with_file(MultipartFile::new("data", b"\x00\x01"))
+            .with_boundary("b")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (_, body) = serialize(form);
+        assert_eq!(
+            body,
+            "--b\r\nContent-Disposition: form-data; name=\"data\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\x00\x01\r\n\
+             --b--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_typed_text() {
+        let form = MultipartBuilder::new()
+            .with_text_typed("doc", "{}", "application/json")
+            .unwrap()
+            .with_boundary("b")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (_, body) = serialize(form);
+        assert_eq!(
+            body,
+            "--b\r\nContent-Disposition: form-data; name=\"doc\"\r\n\
+             Content-Type: application/json\r\n\r\n{}\r\n\
+             --b--\r\n"
+        );
+
+        let err = MultipartBuilder::new()
+            .with_text_typed("a", "b", "invalid")
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidMimeType(_)));
+    }
+
+    #[cfg(feature = "charsets")]
+    #[test]
+    fn test_default_text_charset() {
+        let mut form = MultipartBuilder::new()
+            .with_text("a", "café")
+            .with_text_typed("b", "é", "text/plain; charset=utf-8")
+            .unwrap()
+            .default_text_charset(crate::charsets::WINDOWS_1252)
+            .with_boundary("b")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let len = match form.kind().unwrap() {
+            BodyKind::KnownLength(len) => len,
+            kind => panic!("unexpected body kind {:?}", kind),
+        };
+        let mut body = Vec::new();
+        form.write(&mut body).unwrap();
+        assert_eq!(body.len() as u64, len);
+        assert_eq!(
+            body,
+            &b"--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\
+               Content-Type: text/plain; charset=windows-1252\r\n\r\ncaf\xe9\r\n\
+               --b\r\nContent-Disposition: form-data; name=\"b\"\r\n\
+               Content-Type: text/plain; charset=utf-8\r\n\r\n\xc3\xa9\r\n\
+               --b--\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn test_quoted_boundary() {
+        let form = MultipartBuilder::new()
+            .with_text("a", "b")
+            .with_boundary("my boundary:1")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (content_type, body) = serialize(form);
+        assert_eq!(content_type, "multipart/form-data; boundary=\"my boundary:1\"");
+        assert!(body.starts_with("--my boundary:1\r\n"));
+    }
+
+    #[test]
+    fn test_invalid_boundary() {
+        for boundary in ["", "ends with space ", "semi;colon", "quote\"", &"x".repeat(71)] {
+            let err = MultipartBuilder::new().with_boundary(boundary).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::InvalidMultipartBoundary(b) if b == boundary));
+        }
+        assert!(MultipartBuilder::new().with_boundary("x".repeat(70)).is_ok());
+    }
+
+    #[test]
+    fn test_random_boundary() {
+        let a = MultipartBuilder::new().build().unwrap();
+        let b = MultipartBuilder::new().build().unwrap();
+        assert!(is_valid_boundary(a.boundary()));
+        assert_ne!(a.boundary(), b.boundary());
+    }
+
+    #[test]
+    fn test_from_file() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"file data").unwrap();
+        let form = MultipartBuilder::new()
+            .with_file(
+                MultipartFile::from_file("upload", file)
+                    .unwrap()
+                    .with_type("text/plain")
+                    .unwrap()
+                    .with_filename("data.txt"),
+            )
+            .with_boundary("b")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let expected = "--b\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"data.txt\"\r\n\
+                        Content-Type: text/plain\r\n\r\nfile data\r\n\
+                        --b--\r\n";
+        // The file is read from the start every time the form is written.
+        assert_eq!(serialize(form.clone()).1, expected);
+        assert_eq!(serialize(form).1, expected);
+    }
+
+    #[test]
+    fn test_from_file_concurrent_writes() {
+        let mut file = tempfile::tempfile().unwrap();
+        let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        file.write_all(&data).unwrap();
+        let form = MultipartBuilder::new()
+            .with_file(MultipartFile::from_file("upload", file).unwrap())
+            .build()
+            .unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let mut form = form.clone();
+                let data = data.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        let mut body = Vec::new();
+                        form.write(&mut body).unwrap();
+                        assert!(body.windows(data.len()).any(|window| window == data));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_from_file_truncated() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"file data").unwrap();
+        let mut form = MultipartBuilder::new()
+            .with_file(MultipartFile::from_file("upload", file.try_clone().unwrap()).unwrap())
+            .build()
+            .unwrap();
+        file.set_len(4).unwrap();
+
+        let err = form.write(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    fn make_tree(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("attohttpc-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub").join("deep")).unwrap();
+        fs::write(dir.join("b.json"), "{}").unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("sub").join("c.PNG"), "c").unwrap();
+        fs::write(dir.join("sub").join("deep").join("d"), "d").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_with_dir_recursive() {
+        let dir = make_tree("with-dir-recursive");
+        let form = MultipartBuilder::new()
+            .with_dir("files/", &dir, true)
+            .unwrap()
+            .with_boundary("b")
+            .unwrap()
+            .build()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (_, body) = serialize(form);
+        assert_eq!(
+            body,
+            "--b\r\nContent-Disposition: form-data; name=\"files/a.txt\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\na\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"files/b.json\"; filename=\"b.json\"\r\n\
+             Content-Type: application/json\r\n\r\n{}\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"files/sub/c.PNG\"; filename=\"sub/c.PNG\"\r\n\
+             Content-Type: image/png\r\n\r\nc\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"files/sub/deep/d\"; filename=\"sub/deep/d\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\nd\r\n\
+             --b--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_with_dir_not_recursive() {
+        let dir = make_tree("with-dir");
+        let form = MultipartBuilder::new()
+            .with_dir("", &dir, false)
+            .unwrap()
+            .build()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = form.parts.iter().map(|part| part.header.clone()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].contains("name=\"a.txt\"; filename=\"a.txt\""));
+        assert!(names[1].contains("name=\"b.json\"; filename=\"b.json\""));
+    }
+
+    #[test]
+    fn test_with_dir_missing() {
+        let dir = std::env::temp_dir().join("attohttpc-this-dir-does-not-exist");
+        assert!(MultipartBuilder::new().with_dir("", dir, true).is_err());
     }
 }
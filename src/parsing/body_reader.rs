@@ -1,19 +1,20 @@
 use std::io::{self, BufRead, BufReader, Read, Take};
 
 use http::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
+use http::{Method, StatusCode};
 
 use crate::error::{InvalidResponseKind, Result};
 use crate::parsing::chunked_reader::ChunkedReader;
 use crate::streams::BaseStream;
 
 #[derive(Debug)]
-pub enum BodyReader {
-    Chunked(ChunkedReader<BaseStream>),
-    Length(Take<BufReader<BaseStream>>),
-    Close(BufReader<BaseStream>),
+pub enum BodyReader<R: Read = BaseStream> {
+    Chunked(ChunkedReader<R>),
+    Length(Take<BufReader<R>>),
+    Close(BufReader<R>),
 }
 
-impl Read for BodyReader {
+impl<R: Read> Read for BodyReader<R> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
@@ -24,7 +25,7 @@ impl Read for BodyReader {
     }
 }
 
-impl BufRead for BodyReader {
+impl<R: Read> BufRead for BodyReader<R> {
     #[inline]
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
         match self {
@@ -77,9 +78,23 @@ fn is_content_length(headers: &HeaderMap) -> Result<Option<u64>> {
     Ok(last)
 }
 
-impl BodyReader {
-    pub fn new(headers: &HeaderMap, reader: BufReader<BaseStream>) -> Result<BodyReader> {
-        if is_chunked(headers) {
+/// Check if the response to a request using `method` can have a body, based on its status.
+///
+/// Responses to HEAD requests and responses with a 1xx, 204 or 304 status never have a body, even
+/// if they contain a `Content-Length` header.
+pub fn response_has_body(method: &Method, status: StatusCode) -> bool {
+    method != Method::HEAD
+        && !status.is_informational()
+        && status != StatusCode::NO_CONTENT
+        && status != StatusCode::NOT_MODIFIED
+}
+
+impl<R: Read> BodyReader<R> {
+    pub fn new(headers: &HeaderMap, reader: BufReader<R>, has_body: bool) -> Result<BodyReader<R>> {
+        if !has_body {
+            debug!("creating an empty body reader");
+            Ok(BodyReader::Length(reader.take(0)))
+        } else if is_chunked(headers) {
             debug!("creating a chunked body reader");
             Ok(BodyReader::Chunked(ChunkedReader::new(reader)))
         } else if let Some(val) = is_content_length(headers)? {
@@ -90,6 +105,66 @@ impl BodyReader {
             Ok(BodyReader::Close(reader))
         }
     }
+
+    /// Number of bytes remaining according to the `Content-Length` header, if known.
+    pub fn remaining_len(&self) -> Option<u64> {
+        match self {
+            BodyReader::Length(r) => Some(r.limit()),
+            BodyReader::Chunked(_) | BodyReader::Close(_) => None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn buffer_capacity(&self) -> usize {
+        match self {
+            BodyReader::Chunked(r) => r.get_ref().capacity(),
+            BodyReader::Length(r) => r.get_ref().capacity(),
+            BodyReader::Close(r) => r.capacity(),
+        }
+    }
+
+    /// Whether there might be bytes remaining in the body. When the body is delimited by the end
+    /// of the connection, this is only known after reading it.
+    pub fn has_remaining(&self) -> bool {
+        match self {
+            BodyReader::Chunked(r) => !r.is_finished(),
+            BodyReader::Length(r) => r.limit() > 0,
+            BodyReader::Close(_) => true,
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &R {
+        match self {
+            BodyReader::Chunked(r) => r.get_ref().get_ref(),
+            BodyReader::Length(r) => r.get_ref().get_ref(),
+            BodyReader::Close(r) => r.get_ref(),
+        }
+    }
+
+    /// Get a mutable reference to the buffered reader of the underlying stream.
+    pub fn buf_reader_mut(&mut self) -> &mut BufReader<R> {
+        match self {
+            BodyReader::Chunked(r) => r.get_mut(),
+            BodyReader::Length(r) => r.get_mut(),
+            BodyReader::Close(r) => r,
+        }
+    }
+
+    /// Read and drop up to `max` bytes of the body, returning the number of bytes dropped.
+    pub fn discard(&mut self, max: u64) -> io::Result<u64> {
+        let mut discarded = 0;
+        while discarded < max {
+            let available = self.fill_buf()?.len();
+            if available == 0 {
+                break;
+            }
+            let n = (available as u64).min(max - discarded) as usize;
+            self.consume(n);
+            discarded += n as u64;
+        }
+        Ok(discarded)
+    }
 }
 
 #[test]
@@ -155,3 +230,13 @@ fn test_is_content_length_many_err() {
     assert_eq!(headers.get_all("content-length").iter().count(), 2);
     assert!(is_content_length(&headers).is_err());
 }
+
+#[test]
+fn test_response_has_body() {
+    assert!(response_has_body(&Method::GET, StatusCode::OK));
+    assert!(response_has_body(&Method::POST, StatusCode::NOT_FOUND));
+    assert!(!response_has_body(&Method::HEAD, StatusCode::OK));
+    assert!(!response_has_body(&Method::GET, StatusCode::NO_CONTENT));
+    assert!(!response_has_body(&Method::GET, StatusCode::NOT_MODIFIED));
+    assert!(!response_has_body(&Method::GET, StatusCode::CONTINUE));
+}
@@ -67,12 +67,26 @@ pub fn trim_byte_right(byte: u8, buf: &[u8]) -> &[u8] {
     buf.iter().rposition(|b| *b != byte).map_or(&[], |n| &buf[..=n])
 }
 
-pub fn replace_byte(byte: u8, replace: u8, buf: &mut [u8]) {
-    for val in buf {
-        if *val == byte {
-            *val = replace;
+/// Join the lines of a folded header value with a single space, dropping the whitespace around the
+/// line breaks.
+pub fn unfold(buf: &[u8]) -> Vec<u8> {
+    let mut unfolded = Vec::with_capacity(buf.len());
+    let parts = buf
+        .split(|&b| b == b'\n')
+        .map(|part| {
+            let is_content = |b: &u8| *b != b' ' && *b != b'\t';
+            let start = part.iter().position(is_content).unwrap_or(part.len());
+            let end = part.iter().rposition(is_content).map_or(start, |n| n + 1);
+            &part[start..end]
+        })
+        .filter(|part| !part.is_empty());
+    for part in parts {
+        if !unfolded.is_empty() {
+            unfolded.push(b' ');
         }
+        unfolded.extend_from_slice(part);
     }
+    unfolded
 }
 
 #[derive(Debug)]
@@ -81,9 +95,9 @@ pub struct BufReaderWrite<R> {
 }
 
 impl<R: Read> BufReaderWrite<R> {
-    pub fn new(inner: R) -> BufReaderWrite<R> {
+    pub fn with_capacity(capacity: usize, inner: R) -> BufReaderWrite<R> {
         BufReaderWrite {
-            inner: BufReader::new(inner),
+            inner: BufReader::with_capacity(capacity, inner),
         }
     }
 }
@@ -125,10 +139,10 @@ fn test_read_line_lf() {
     let mut reader = BufReader::new(&b"hello\nworld\n"[..]);
     let mut line = Vec::new();
 
-    assert_eq!(read_line(&mut reader, &mut line, u64::max_value()).ok(), Some(6));
+    assert_eq!(read_line(&mut reader, &mut line, u64::MAX).ok(), Some(6));
     assert_eq!(line, b"hello");
 
-    assert_eq!(read_line(&mut reader, &mut line, u64::max_value()).ok(), Some(6));
+    assert_eq!(read_line(&mut reader, &mut line, u64::MAX).ok(), Some(6));
     assert_eq!(line, b"world");
 }
 
@@ -137,10 +151,10 @@ fn test_read_line_crlf() {
     let mut reader = BufReader::new(&b"hello\r\nworld\r\n"[..]);
     let mut line = Vec::new();
 
-    assert_eq!(read_line(&mut reader, &mut line, u64::max_value()).ok(), Some(7));
+    assert_eq!(read_line(&mut reader, &mut line, u64::MAX).ok(), Some(7));
     assert_eq!(line, b"hello");
 
-    assert_eq!(read_line(&mut reader, &mut line, u64::max_value()).ok(), Some(7));
+    assert_eq!(read_line(&mut reader, &mut line, u64::MAX).ok(), Some(7));
     assert_eq!(line, b"world");
 }
 
@@ -149,7 +163,7 @@ fn test_read_line_empty_crlf() {
     let mut reader = BufReader::new(&b"\r\n"[..]);
     let mut line = Vec::new();
 
-    assert_eq!(read_line(&mut reader, &mut line, u64::max_value()).ok(), Some(2));
+    assert_eq!(read_line(&mut reader, &mut line, u64::MAX).ok(), Some(2));
     assert_eq!(line, b"");
 }
 
@@ -158,7 +172,7 @@ fn test_read_line_empty_lf() {
     let mut reader = BufReader::new(&b"\n"[..]);
     let mut line = Vec::new();
 
-    assert_eq!(read_line(&mut reader, &mut line, u64::max_value()).ok(), Some(1));
+    assert_eq!(read_line(&mut reader, &mut line, u64::MAX).ok(), Some(1));
     assert_eq!(line, b"");
 }
 
@@ -179,10 +193,7 @@ fn test_read_line_strict() {
     let mut reader = BufReader::new(&b"foo\r\nbar\r\n"[..]);
     let mut line = Vec::new();
 
-    assert_eq!(
-        read_line_strict(&mut reader, &mut line, u64::max_value()).ok(),
-        Some(3 + 2)
-    );
+    assert_eq!(read_line_strict(&mut reader, &mut line, u64::MAX).ok(), Some(3 + 2));
     assert_eq!(line, b"foo");
 }
 
@@ -191,7 +202,7 @@ fn test_read_line_strict_empty_crlf() {
     let mut reader = BufReader::new(&b"\r\n"[..]);
     let mut line = Vec::new();
 
-    assert_eq!(read_line_strict(&mut reader, &mut line, u64::max_value()).ok(), Some(2));
+    assert_eq!(read_line_strict(&mut reader, &mut line, u64::MAX).ok(), Some(2));
     assert_eq!(line, b"");
 }
 
@@ -201,9 +212,7 @@ fn test_read_line_strict_missing_crlf() {
     let mut line = Vec::new();
 
     assert_eq!(
-        read_line_strict(&mut reader, &mut line, u64::max_value())
-            .unwrap_err()
-            .kind(),
+        read_line_strict(&mut reader, &mut line, u64::MAX).unwrap_err().kind(),
         io::ErrorKind::UnexpectedEof
     );
     assert_eq!(line, b"foo\n");
@@ -215,7 +224,7 @@ fn test_read_line_strict_inner_lf() {
     let mut line = Vec::new();
 
     assert_eq!(
-        read_line_strict(&mut reader, &mut line, u64::max_value()).ok(),
+        read_line_strict(&mut reader, &mut line, u64::MAX).ok(),
         Some(10 + 3 + 2)
     );
     assert_eq!(line, b"123\n456\n789\n0");
@@ -227,7 +236,7 @@ fn test_read_line_strict_inner_cr() {
     let mut line = Vec::new();
 
     assert_eq!(
-        read_line_strict(&mut reader, &mut line, u64::max_value()).ok(),
+        read_line_strict(&mut reader, &mut line, u64::MAX).ok(),
         Some(10 + 3 + 2)
     );
     assert_eq!(line, b"123\r456\r789\r0");
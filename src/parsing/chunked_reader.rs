@@ -14,16 +14,22 @@ fn parse_chunk_size(line: &[u8]) -> io::Result<usize> {
         .map_err(|e| e.into())
 }
 
+/// Maximum number of trailer fields after the last chunk.
+const MAX_TRAILER_FIELDS: usize = 100;
+/// Maximum length of a trailer field.
+const MAX_TRAILER_LINE_LEN: u64 = 8 * 1024;
+
 #[derive(Debug)]
 pub struct ChunkedReader<R>
 where
     R: Read,
 {
     inner: BufReader<R>,
-    buffer: Vec<u8>,
-    consumed: usize,  // bytes consumed from `buffer`
-    remaining: usize, // bytes remaining until next chunk
+    buffer: Vec<u8>,  // buffer for the chunk size lines
+    remaining: usize, // bytes remaining until the end of the current chunk
+    in_chunk: bool,   // whether the line ending of the current chunk must be read
     reached_eof: bool,
+    lenient_termination: bool,
 }
 
 impl<R> ChunkedReader<R>
@@ -34,12 +40,19 @@ where
         ChunkedReader {
             inner: reader,
             buffer: Vec::new(),
-            consumed: 0,
             remaining: 0,
+            in_chunk: false,
             reached_eof: false,
+            lenient_termination: false,
         }
     }
 
+    /// Set whether the body is complete when the input ends right after the last chunk, without
+    /// the empty line which should follow it.
+    pub fn set_lenient_termination(&mut self, lenient: bool) {
+        self.lenient_termination = lenient;
+    }
+
     fn read_chunk_size(&mut self) -> io::Result<usize> {
         buffers::read_line(&mut self.inner, &mut self.buffer, 128)?;
         if self.buffer.is_empty() {
@@ -47,6 +60,49 @@ where
         }
         parse_chunk_size(&self.buffer)
     }
+
+    pub fn get_ref(&self) -> &BufReader<R> {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut BufReader<R> {
+        &mut self.inner
+    }
+
+    /// Whether the terminating chunk was read.
+    pub fn is_finished(&self) -> bool {
+        self.reached_eof
+    }
+
+    /// Skip the trailer fields which can follow the last chunk, up to the empty line ending the body.
+    fn skip_trailers(&mut self) -> io::Result<()> {
+        for idx in 0..=MAX_TRAILER_FIELDS {
+            match buffers::read_line(&mut self.inner, &mut self.buffer, MAX_TRAILER_LINE_LEN) {
+                // Nothing at all follows the last chunk.
+                Err(err)
+                    if idx == 0
+                        && self.lenient_termination
+                        && err.kind() == io::ErrorKind::UnexpectedEof
+                        && self.buffer.is_empty() =>
+                {
+                    return Ok(())
+                }
+                res => res?,
+            };
+            if self.buffer.is_empty() {
+                return Ok(());
+            }
+        }
+        Err(InvalidResponseKind::Chunk.into())
+    }
+
+    fn read_chunk_ending(&mut self) -> io::Result<()> {
+        if !buffers::read_line_ending(&mut self.inner)? {
+            self.reached_eof = true;
+            return Err(InvalidResponseKind::Chunk.into());
+        }
+        Ok(())
+    }
 }
 
 impl<R> BufRead for ChunkedReader<R>
@@ -54,34 +110,37 @@ where
     R: Read,
 {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        const MAX_BUFFER_LEN: usize = 64 * 1024;
+        if self.reached_eof {
+            return Ok(&[]);
+        }
 
-        if self.buffer.len() == self.consumed && !(self.remaining == 0 && self.reached_eof) {
-            if self.remaining == 0 {
-                self.remaining = self.read_chunk_size()?;
-                if self.remaining == 0 {
-                    self.reached_eof = true;
-                }
+        if self.remaining == 0 {
+            if self.in_chunk {
+                self.in_chunk = false;
+                self.read_chunk_ending()?;
             }
 
-            self.buffer.resize(cmp::min(self.remaining, MAX_BUFFER_LEN), 0);
-            self.inner.read_exact(&mut self.buffer)?;
-            self.consumed = 0;
-            self.remaining -= self.buffer.len();
-
-            if self.remaining == 0 && !buffers::read_line_ending(&mut self.inner)? {
-                self.buffer.clear();
+            self.remaining = self.read_chunk_size()?;
+            if self.remaining == 0 {
+                self.skip_trailers()?;
                 self.reached_eof = true;
-
-                return Err(InvalidResponseKind::Chunk.into());
+                return Ok(&[]);
             }
+            self.in_chunk = true;
         }
 
-        Ok(&self.buffer[self.consumed..])
+        // Data is served directly from the buffer of the inner reader, up to the end of the chunk.
+        let buf = self.inner.fill_buf()?;
+        if buf.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(&buf[..cmp::min(buf.len(), self.remaining)])
     }
 
     fn consume(&mut self, amt: usize) {
-        self.consumed = cmp::min(self.consumed + amt, self.buffer.len());
+        let amt = cmp::min(amt, self.remaining);
+        self.inner.consume(amt);
+        self.remaining -= amt;
     }
 }
 
@@ -105,6 +164,27 @@ fn test_read_works() {
     assert_eq!(s, "wikipedia in\r\n\r\nchunks.");
 }
 
+#[test]
+fn test_read_trailers() {
+    let msg = b"4\r\nwiki\r\n0\r\nX-Checksum: abc\r\nServer-Timing: db;dur=53\r\n\r\n";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "wiki");
+    assert!(reader.is_finished());
+}
+
+#[test]
+fn test_read_too_many_trailers() {
+    let mut msg = b"0\r\n".to_vec();
+    for _ in 0..=MAX_TRAILER_FIELDS {
+        msg.extend(b"X-Trailer: 1\r\n");
+    }
+    msg.extend(b"\r\n");
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
 #[test]
 fn test_read_empty() {
     let msg = b"0\r\n\r\n";
@@ -154,3 +234,103 @@ fn test_read_invalid_bad_terminating_chunk() {
         io::ErrorKind::UnexpectedEof
     );
 }
+
+#[test]
+fn test_read_lenient_termination() {
+    let read = |msg: &[u8]| {
+        let mut reader = ChunkedReader::new(BufReader::new(msg));
+        reader.set_lenient_termination(true);
+        let mut s = String::new();
+        reader.read_to_string(&mut s).map(|_| s)
+    };
+
+    assert_eq!(read(b"4\r\nwiki\r\n0\r\n").unwrap(), "wiki");
+    assert_eq!(read(b"0\r\n").unwrap(), "");
+    assert_eq!(read(b"4\r\nwiki\r\n0\r\n\r\n").unwrap(), "wiki");
+
+    // The terminating chunk or the empty line is cut short.
+    for msg in [
+        &b"4\r\nwiki\r\n0"[..],
+        b"0",
+        b"0\r\n\r",
+        b"0\r\nX-Trailer: 1\r\n",
+        b"4\r\nwik",
+    ] {
+        assert_eq!(read(msg).unwrap_err().kind(), io::ErrorKind::UnexpectedEof, "{:?}", msg);
+    }
+}
+
+#[test]
+fn test_read_huge_chunk_size() {
+    // Too large for a usize.
+    let msg = b"10000000000000000000\r\nwiki\r\n0\r\n\r\n";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert!(matches!(
+        err.get_ref()
+            .and_then(|err| err.downcast_ref::<crate::Error>())
+            .map(crate::Error::kind),
+        Some(crate::ErrorKind::InvalidResponse(InvalidResponseKind::ChunkSize))
+    ));
+
+    // The largest size is accepted, and the body ends before it.
+    let msg = format!("{:x}\r\nwiki", usize::MAX);
+    let mut reader = ChunkedReader::new(BufReader::new(msg.as_bytes()));
+    let mut body = Vec::new();
+    assert_eq!(
+        reader.read_to_end(&mut body).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+    assert_eq!(body, b"wiki");
+}
+
+#[test]
+fn test_read_invalid_chunk_size_line() {
+    for msg in [&b"\r\n"[..], b"-1\r\n", b"+\r\n", b"\xff\r\n", b";ext\r\n", b"4 4\r\n"] {
+        let mut reader = ChunkedReader::new(BufReader::new(msg));
+        assert!(reader.read_to_end(&mut Vec::new()).is_err(), "{:?}", msg);
+    }
+}
+
+#[cfg(test)]
+struct ThrottledReader<'a> {
+    parts: Vec<&'a [u8]>,
+}
+
+#[cfg(test)]
+impl Read for ThrottledReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.parts.first_mut() {
+            None => Ok(0),
+            Some([]) => {
+                self.parts.remove(0);
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Some(part) => {
+                let n = cmp::min(buf.len(), part.len());
+                buf[..n].copy_from_slice(&part[..n]);
+                *part = &part[n..];
+                if part.is_empty() {
+                    self.parts.remove(0);
+                }
+                Ok(n)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_read_partial_chunk() {
+    // The rest of the chunk has not arrived yet when the reader would block.
+    let parts: Vec<&[u8]> = vec![b"a\r\nhello", b"", b"world\r\n0\r\n\r\n"];
+    let mut reader = ChunkedReader::new(BufReader::new(ThrottledReader { parts }));
+
+    let mut buf = [0; 16];
+    let n = reader.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"hello");
+    assert_eq!(reader.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "world");
+}
@@ -1,33 +1,106 @@
+#[cfg(feature = "__compress")]
+use std::io::BufRead;
 use std::io::{self, Read};
 
-#[cfg(feature = "flate2")]
+#[cfg(feature = "__compress")]
 use flate2::bufread::{DeflateDecoder, GzDecoder};
-use http::header::HeaderMap;
-#[cfg(feature = "flate2")]
-use http::header::{CONTENT_ENCODING, TRANSFER_ENCODING};
-#[cfg(feature = "flate2")]
-use http::Method;
-
+#[cfg(feature = "__compress")]
+use http::header::TRANSFER_ENCODING;
+use http::header::{HeaderMap, CONTENT_ENCODING};
+
+#[cfg(not(feature = "__compress"))]
+use crate::error::ErrorKind;
+#[cfg(feature = "__compress")]
+use crate::error::InvalidResponseKind;
 use crate::error::Result;
 use crate::parsing::body_reader::BodyReader;
 use crate::request::PreparedRequest;
 
+/// Content encodings which can be decoded, in the order they are advertised by default.
+#[cfg(feature = "__compress")]
+pub const SUPPORTED_ENCODINGS: &[&str] = &["gzip", "deflate"];
+
+/// Find the supported content encoding matching the given name, ignoring case.
+#[cfg(feature = "__compress")]
+pub fn supported_encoding(name: &str) -> Option<&'static str> {
+    SUPPORTED_ENCODINGS
+        .iter()
+        .copied()
+        .find(|enc| enc.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Size of the decoded output below which the decompression ratio is not checked.
+#[cfg(feature = "__compress")]
+const RATIO_CHECK_THRESHOLD: u64 = 1024 * 1024;
+
+/// Body reader given to a decoder, which counts the compressed bytes consumed by the decoder.
+#[cfg(feature = "__compress")]
+#[derive(Debug)]
+pub struct CountingReader {
+    inner: BodyReader,
+    count: u64,
+}
+
+#[cfg(feature = "__compress")]
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "__compress")]
+impl BufRead for CountingReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.count += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Counts the bytes returned by a decoder, to detect bodies which expand too much.
+#[cfg(feature = "__compress")]
+#[derive(Debug)]
+pub struct RatioGuard {
+    output: u64,
+    max_ratio: Option<u32>,
+}
+
+#[cfg(feature = "__compress")]
+impl RatioGuard {
+    /// Count `n` more decoded bytes, failing if the output exceeds the maximum ratio to the `input`
+    /// consumed so far.
+    fn check(&mut self, input: u64, n: usize) -> io::Result<usize> {
+        self.output += n as u64;
+        if let Some(max_ratio) = self.max_ratio {
+            if self.output > RATIO_CHECK_THRESHOLD && self.output > input.saturating_mul(u64::from(max_ratio)) {
+                return Err(InvalidResponseKind::DecompressionBomb.into());
+            }
+        }
+        Ok(n)
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum CompressedReader {
     Plain(BodyReader),
-    #[cfg(feature = "flate2")]
-    Deflate(DeflateDecoder<BodyReader>),
-    #[cfg(feature = "flate2")]
-    Gzip(GzDecoder<BodyReader>),
+    #[cfg(feature = "__compress")]
+    Deflate(DeflateDecoder<CountingReader>, RatioGuard),
+    #[cfg(feature = "__compress")]
+    Gzip(GzDecoder<CountingReader>, RatioGuard),
 }
 
-#[cfg(feature = "flate2")]
+#[cfg(feature = "__compress")]
 fn have_encoding_item(value: &str, enc: &str) -> bool {
     value.split(',').map(|s| s.trim()).any(|s| s.eq_ignore_ascii_case(enc))
 }
 
-#[cfg(feature = "flate2")]
+#[cfg(feature = "__compress")]
 fn have_encoding_content_encoding(headers: &HeaderMap, enc: &str) -> bool {
     headers
         .get_all(CONTENT_ENCODING)
@@ -36,7 +109,19 @@ fn have_encoding_content_encoding(headers: &HeaderMap, enc: &str) -> bool {
         .any(|val| have_encoding_item(val, enc))
 }
 
-#[cfg(feature = "flate2")]
+/// Iterate over the items of the `Content-Encoding` headers.
+#[cfg(not(feature = "__compress"))]
+fn content_encodings(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(CONTENT_ENCODING)
+        .into_iter()
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(str::trim)
+        .filter(|enc| !enc.is_empty())
+}
+
+#[cfg(feature = "__compress")]
 fn have_encoding_transfer_encoding(headers: &HeaderMap, enc: &str) -> bool {
     headers
         .get_all(TRANSFER_ENCODING)
@@ -45,33 +130,87 @@ fn have_encoding_transfer_encoding(headers: &HeaderMap, enc: &str) -> bool {
         .any(|val| have_encoding_item(val, enc))
 }
 
-#[cfg(feature = "flate2")]
+#[cfg(feature = "__compress")]
 fn have_encoding(headers: &HeaderMap, enc: &str) -> bool {
     have_encoding_content_encoding(headers, enc) || have_encoding_transfer_encoding(headers, enc)
 }
 
 impl CompressedReader {
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     pub fn new<B>(headers: &HeaderMap, request: &PreparedRequest<B>, reader: BodyReader) -> Result<CompressedReader> {
-        if request.method() != Method::HEAD {
-            if have_encoding(headers, "gzip") {
+        let accepts = |enc| request.base_settings.accept_encoding.contains(&enc);
+        let counting = |inner| CountingReader { inner, count: 0 };
+        let guard = || RatioGuard {
+            output: 0,
+            max_ratio: request.base_settings.max_decompression_ratio,
+        };
+
+        // An empty body is not a valid compressed stream, it's left as it is.
+        if reader.has_remaining() {
+            if accepts("gzip") && have_encoding(headers, "gzip") {
                 debug!("creating gzip decoder");
-                return Ok(CompressedReader::Gzip(GzDecoder::new(reader)));
+                return Ok(CompressedReader::Gzip(GzDecoder::new(counting(reader)), guard()));
             }
 
-            if have_encoding(headers, "deflate") {
+            if accepts("deflate") && have_encoding(headers, "deflate") {
                 debug!("creating deflate decoder");
-                return Ok(CompressedReader::Deflate(DeflateDecoder::new(reader)));
+                return Ok(CompressedReader::Deflate(
+                    DeflateDecoder::new(counting(reader)),
+                    guard(),
+                ));
             }
         }
         debug!("creating plain reader");
         Ok(CompressedReader::Plain(reader))
     }
 
-    #[cfg(not(feature = "flate2"))]
-    pub fn new<B>(_: &HeaderMap, _: &PreparedRequest<B>, reader: BodyReader) -> Result<CompressedReader> {
+    #[cfg(not(feature = "__compress"))]
+    pub fn new<B>(headers: &HeaderMap, request: &PreparedRequest<B>, reader: BodyReader) -> Result<CompressedReader> {
+        // When `identity` was requested explicitly, an encoded body is refused instead of being
+        // returned as is, since it cannot be decoded.
+        if request.base_settings.no_compression && reader.has_remaining() {
+            if let Some(enc) = content_encodings(headers).find(|enc| !enc.eq_ignore_ascii_case("identity")) {
+                return Err(ErrorKind::UnexpectedContentEncoding(enc.to_owned()).into());
+            }
+        }
         Ok(CompressedReader::Plain(reader))
     }
+
+    /// Number of bytes remaining in the body, if known. The length of compressed bodies is unknown.
+    pub fn remaining_len(&self) -> Option<u64> {
+        match self {
+            CompressedReader::Plain(s) => s.remaining_len(),
+            #[cfg(feature = "__compress")]
+            CompressedReader::Deflate(..) | CompressedReader::Gzip(..) => None,
+        }
+    }
+
+    /// Whether the body is decoded before being returned by `Read`.
+    pub fn is_decoding(&self) -> bool {
+        !matches!(self, CompressedReader::Plain(_))
+    }
+
+    /// Get a reference to the raw body reader, which is read by the decoder if there is one.
+    pub fn body_reader(&self) -> &BodyReader {
+        match self {
+            CompressedReader::Plain(s) => s,
+            #[cfg(feature = "__compress")]
+            CompressedReader::Deflate(s, _) => &s.get_ref().inner,
+            #[cfg(feature = "__compress")]
+            CompressedReader::Gzip(s, _) => &s.get_ref().inner,
+        }
+    }
+
+    /// Get a mutable reference to the raw body reader, which is read by the decoder if there is one.
+    pub fn body_reader_mut(&mut self) -> &mut BodyReader {
+        match self {
+            CompressedReader::Plain(s) => s,
+            #[cfg(feature = "__compress")]
+            CompressedReader::Deflate(s, _) => &mut s.get_mut().inner,
+            #[cfg(feature = "__compress")]
+            CompressedReader::Gzip(s, _) => &mut s.get_mut().inner,
+        }
+    }
 }
 
 impl Read for CompressedReader {
@@ -80,10 +219,16 @@ impl Read for CompressedReader {
         // TODO: gzip does not read until EOF, leaving some data in the buffer.
         match self {
             CompressedReader::Plain(s) => s.read(buf),
-            #[cfg(feature = "flate2")]
-            CompressedReader::Deflate(s) => s.read(buf),
-            #[cfg(feature = "flate2")]
-            CompressedReader::Gzip(s) => s.read(buf),
+            #[cfg(feature = "__compress")]
+            CompressedReader::Deflate(s, guard) => {
+                let n = s.read(buf)?;
+                guard.check(s.get_ref().count, n)
+            }
+            #[cfg(feature = "__compress")]
+            CompressedReader::Gzip(s, guard) => {
+                let n = s.read(buf)?;
+                guard.check(s.get_ref().count, n)
+            }
         }
     }
 }
@@ -92,23 +237,25 @@ impl Read for CompressedReader {
 mod tests {
     use std::io::prelude::*;
 
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     use flate2::{
         write::{DeflateEncoder, GzEncoder},
         Compression,
     };
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     use http::header::{HeaderMap, HeaderValue};
     use http::Method;
 
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     use super::have_encoding;
+    #[cfg(feature = "__compress")]
+    use crate::error::InvalidResponseKind;
     use crate::parsing::response::parse_response;
     use crate::streams::BaseStream;
     use crate::PreparedRequest;
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_have_encoding_none() {
         let mut headers = HeaderMap::new();
         headers.insert("content-encoding", HeaderValue::from_static("gzip"));
@@ -116,7 +263,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_have_encoding_content_encoding_simple() {
         let mut headers = HeaderMap::new();
         headers.insert("content-encoding", HeaderValue::from_static("gzip"));
@@ -124,7 +271,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_have_encoding_content_encoding_multi() {
         let mut headers = HeaderMap::new();
         headers.insert("content-encoding", HeaderValue::from_static("identity, deflate"));
@@ -132,7 +279,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_have_encoding_transfer_encoding_simple() {
         let mut headers = HeaderMap::new();
         headers.insert("transfer-encoding", HeaderValue::from_static("deflate"));
@@ -140,13 +287,45 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_have_encoding_transfer_encoding_multi() {
         let mut headers = HeaderMap::new();
         headers.insert("transfer-encoding", HeaderValue::from_static("gzip, chunked"));
         assert!(have_encoding(&headers, "gzip"));
     }
 
+    #[test]
+    #[cfg(not(feature = "__compress"))]
+    fn test_no_compression_refuses_encoded_body() {
+        let response = |encoding: &str| {
+            // A gzip stream of "hello", which cannot be decoded without the compress feature.
+            let payload =
+                b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03\xcb\x48\xcd\xc9\xc9\x07\x00\x86\xa6\x10\x36\x05\x00\x00\x00";
+            let mut buf: Vec<u8> = Vec::new();
+            let _ = write!(
+                buf,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: {}\r\n\r\n",
+                payload.len(),
+                encoding
+            );
+            buf.extend(payload);
+            BaseStream::mock(buf)
+        };
+
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.no_compression = true;
+        let err = parse_response(response("gzip"), &req, req.url()).err().unwrap();
+        match err.kind() {
+            crate::ErrorKind::UnexpectedContentEncoding(enc) => assert_eq!(enc, "gzip"),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+        assert!(parse_response(response("identity"), &req, req.url()).is_ok());
+
+        // Without `no_compression`, the body is returned as is.
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+        assert!(parse_response(response("gzip"), &req, req.url()).is_ok());
+    }
+
     #[test]
     fn test_stream_plain() {
         let payload = b"Hello world!!!!!!!!";
@@ -163,7 +342,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_stream_deflate() {
         let mut payload = Vec::new();
         let mut enc = DeflateEncoder::new(&mut payload, Compression::default());
@@ -186,7 +365,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_stream_gzip() {
         let mut payload = Vec::new();
         let mut enc = GzEncoder::new(&mut payload, Compression::default());
@@ -209,8 +388,167 @@ mod tests {
         assert_eq!(response.text().unwrap(), "Hello world!!!!!!!!");
     }
 
+    #[cfg(feature = "__compress")]
+    fn truncated_gzip_response(text: &[u8], missing: usize, tolerate: bool) -> crate::Response {
+        let mut payload = Vec::new();
+        let mut enc = GzEncoder::new(&mut payload, Compression::default());
+        enc.write_all(text).unwrap();
+        enc.finish().unwrap();
+
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = write!(
+            buf,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: gzip\r\n\r\n",
+            payload.len()
+        );
+        buf.extend(&payload[..payload.len() - missing]);
+
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.tolerate_truncated_body = tolerate;
+
+        let sock = BaseStream::mock(buf);
+        parse_response(sock, &req, req.url()).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_stream_gzip_truncated() {
+        let text = "Hello world!!!!!!!!".repeat(100);
+
+        let response = truncated_gzip_response(text.as_bytes(), 3, false);
+        assert!(response.text().is_err());
+
+        let (_, _, mut reader) = truncated_gzip_response(text.as_bytes(), 3, true).split();
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, text);
+        assert!(reader.was_truncated());
+
+        let response = truncated_gzip_response(text.as_bytes(), 0, true);
+        assert!(!response.was_truncated());
+        assert_eq!(response.text().unwrap(), text);
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_stream_gzip_truncated_before_output() {
+        let response = truncated_gzip_response(b"Hello world!!!!!!!!", 30, true);
+        assert!(response.text().is_err());
+    }
+
+    /// Response with `len` zeros compressed with the given encoding, which expand about 1000 times.
+    #[cfg(feature = "__compress")]
+    fn zeros_response(encoding: &str, len: usize) -> BaseStream {
+        let mut payload = Vec::new();
+        if encoding == "gzip" {
+            let mut enc = GzEncoder::new(&mut payload, Compression::best());
+            enc.write_all(&vec![0; len]).unwrap();
+            enc.finish().unwrap();
+        } else {
+            let mut enc = DeflateEncoder::new(&mut payload, Compression::best());
+            enc.write_all(&vec![0; len]).unwrap();
+            enc.finish().unwrap();
+        }
+
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = write!(
+            buf,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: {}\r\n\r\n",
+            payload.len(),
+            encoding
+        );
+        buf.extend(payload);
+        BaseStream::mock(buf)
+    }
+
+    #[cfg(feature = "__compress")]
+    fn is_decompression_bomb(err: &crate::Error) -> bool {
+        match err.kind() {
+            crate::ErrorKind::Io(err) => matches!(
+                err.get_ref()
+                    .and_then(|err| err.downcast_ref::<crate::Error>())
+                    .map(crate::Error::kind),
+                Some(crate::ErrorKind::InvalidResponse(
+                    InvalidResponseKind::DecompressionBomb
+                ))
+            ),
+            _ => false,
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_decompression_bomb() {
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+        let response = parse_response(zeros_response("gzip", 64 * 1024 * 1024), &req, req.url()).unwrap();
+        let err = response.bytes().unwrap_err();
+        assert!(is_decompression_bomb(&err), "{:?}", err);
+
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.max_decompression_ratio = Some(100);
+        let response = parse_response(zeros_response("deflate", 4 * 1024 * 1024), &req, req.url()).unwrap();
+        let err = response.bytes().unwrap_err();
+        assert!(is_decompression_bomb(&err), "{:?}", err);
+
+        req.base_settings.max_decompression_ratio = None;
+        let response = parse_response(zeros_response("deflate", 4 * 1024 * 1024), &req, req.url()).unwrap();
+        assert_eq!(response.bytes().unwrap().len(), 4 * 1024 * 1024);
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_decompression_ratio_below_threshold() {
+        // Small bodies are not checked, whatever their ratio.
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.max_decompression_ratio = Some(2);
+        let response = parse_response(zeros_response("gzip", 1024 * 1024), &req, req.url()).unwrap();
+        assert_eq!(response.bytes().unwrap().len(), 1024 * 1024);
+
+        // A body compressing like usual text stays well under the default ratio.
+        let text = "Hello world!!!!!!!!".repeat(200_000);
+        let mut payload = Vec::new();
+        let mut enc = GzEncoder::new(&mut payload, Compression::default());
+        enc.write_all(text.as_bytes()).unwrap();
+        enc.finish().unwrap();
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = write!(
+            buf,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: gzip\r\n\r\n",
+            payload.len()
+        );
+        buf.extend(payload);
+
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+        let response = parse_response(BaseStream::mock(buf), &req, req.url()).unwrap();
+        assert_eq!(response.text().unwrap(), text);
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_stream_deflate_not_accepted() {
+        let mut payload = Vec::new();
+        let mut enc = DeflateEncoder::new(&mut payload, Compression::default());
+        enc.write_all(b"Hello world!!!!!!!!").unwrap();
+        enc.finish().unwrap();
+
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = write!(
+            buf,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: deflate\r\n\r\n",
+            payload.len()
+        );
+        buf.extend(&payload);
+
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.accept_encoding = vec!["gzip"];
+
+        let sock = BaseStream::mock(buf);
+        let response = parse_response(sock, &req, req.url()).unwrap();
+        assert_eq!(response.bytes().unwrap(), payload);
+    }
+
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_no_body_with_gzip() {
         let buf = b"HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\n\r\n";
 
@@ -221,7 +559,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     fn test_no_body_with_gzip_head() {
         let buf = b"HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\n\r\n";
 
@@ -2,12 +2,20 @@ pub mod body_reader;
 pub mod buffers;
 pub mod chunked_reader;
 pub mod compressed_reader;
+#[cfg(feature = "multipart-form")]
+pub(crate) mod multipart_reader;
 pub mod response;
+pub mod response_ext;
 pub mod response_reader;
+pub mod tee_reader;
 #[cfg(feature = "charsets")]
 pub mod text_reader;
 
-pub use self::response::{parse_response, Response};
+#[cfg(any(test, feature = "__bench"))]
+pub use self::response::parse_response;
+pub use self::response::{ConnectionInfo, RedirectHop, Response};
+pub use self::response_ext::{AuthChallenge, CacheControl, CorsInfo, Link, ServerTimingMetric, WwwAuthenticate};
 pub use self::response_reader::ResponseReader;
+pub use self::tee_reader::TeeReader;
 #[cfg(feature = "charsets")]
 pub use self::text_reader::TextReader;
@@ -0,0 +1,305 @@
+use std::io::{self, Read};
+
+/// Maximum size of the headers of a part.
+const MAX_PART_HEADERS_LEN: usize = 16 * 1024;
+
+/// Headers of a part of a multipart body. Only the headers used to save the part are kept.
+#[derive(Debug, Default)]
+pub struct PartHeaders {
+    pub content_disposition: Option<String>,
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum State {
+    /// Reading the body of a part, or the preamble before the first delimiter.
+    Body,
+    /// The body of the part ended at a delimiter, which is not consumed yet.
+    Delimiter,
+    /// The closing delimiter was read.
+    Done,
+}
+
+/// Streaming reader of the parts of a multipart body, as defined by RFC 2046.
+///
+/// `next_part` moves to the next part and returns its headers, then the body of the part is read
+/// with `Read`. The delimiter of the parts is searched in a buffer which keeps enough bytes to
+/// never return the beginning of a delimiter as part of a body.
+pub struct MultipartReader<R> {
+    inner: R,
+    /// "\r\n--" followed by the boundary.
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    state: State,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid multipart body: {msg}"))
+}
+
+impl<R: Read> MultipartReader<R> {
+    pub fn new(inner: R, boundary: &str) -> MultipartReader<R> {
+        MultipartReader {
+            inner,
+            delimiter: format!("\r\n--{boundary}").into_bytes(),
+            // The first delimiter is not preceded by a line break when there is no preamble.
+            buf: b"\r\n".to_vec(),
+            pos: 0,
+            eof: false,
+            state: State::Body,
+        }
+    }
+
+    /// Read more data into the buffer, returning false at the end of the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+
+        let len = self.buf.len();
+        self.buf.resize(len + 8192, 0);
+        let n = match self.inner.read(&mut self.buf[len..]) {
+            Ok(n) => n,
+            Err(err) => {
+                self.buf.truncate(len);
+                return Err(err);
+            }
+        };
+        self.buf.truncate(len + n);
+        self.eof = n == 0;
+        Ok(n > 0)
+    }
+
+    /// Make sure that at least `len` bytes are buffered, unless the input ends before.
+    fn fill_to(&mut self, len: usize) -> io::Result<()> {
+        while self.buf.len() - self.pos < len && self.fill()? {}
+        Ok(())
+    }
+
+    /// Read a line ending with CRLF, without the line break.
+    fn read_line(&mut self, max: usize) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(idx) = self.buf[self.pos..].windows(2).position(|w| w == b"\r\n") {
+                if idx > max {
+                    return Err(invalid("part headers are too long"));
+                }
+                let line = self.buf[self.pos..self.pos + idx].to_vec();
+                self.pos += idx + 2;
+                return Ok(line);
+            }
+            if self.buf.len() - self.pos > max {
+                return Err(invalid("part headers are too long"));
+            }
+            if !self.fill()? {
+                return Err(invalid("unexpected end of the body"));
+            }
+        }
+    }
+
+    /// Move to the next part, skipping what remains of the current one, and return its headers.
+    ///
+    /// Returns `None` after the closing delimiter.
+    pub fn next_part(&mut self) -> io::Result<Option<PartHeaders>> {
+        io::copy(self, &mut io::sink())?;
+        if self.state == State::Done {
+            return Ok(None);
+        }
+
+        // Skip the delimiter, then check if it's the closing one.
+        self.pos += self.delimiter.len();
+        self.fill_to(2)?;
+        if self.buf[self.pos..].starts_with(b"--") {
+            self.state = State::Done;
+            return Ok(None);
+        }
+
+        // The rest of the delimiter line can only contain whitespace.
+        let rest = self.read_line(MAX_PART_HEADERS_LEN)?;
+        if rest.iter().any(|&b| b != b' ' && b != b'\t') {
+            return Err(invalid("garbage after a delimiter"));
+        }
+
+        let mut headers = PartHeaders::default();
+        let mut len = 0;
+        loop {
+            let line = self.read_line(MAX_PART_HEADERS_LEN.saturating_sub(len))?;
+            len += line.len() + 2;
+            if line.is_empty() {
+                break;
+            }
+
+            let line = String::from_utf8_lossy(&line);
+            let (name, value) = line.split_once(':').ok_or_else(|| invalid("invalid part header"))?;
+            let value = Some(value.trim().to_owned());
+            if name.trim().eq_ignore_ascii_case("content-disposition") {
+                headers.content_disposition = value;
+            } else if name.trim().eq_ignore_ascii_case("content-type") {
+                headers.content_type = value;
+            }
+        }
+
+        self.state = State::Body;
+        Ok(Some(headers))
+    }
+}
+
+impl<R: Read> Read for MultipartReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.state != State::Body || out.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let available = &self.buf[self.pos..];
+            if let Some(idx) = available
+                .windows(self.delimiter.len())
+                .position(|w| w == self.delimiter)
+            {
+                if idx == 0 {
+                    self.state = State::Delimiter;
+                    return Ok(0);
+                }
+                let n = idx.min(out.len());
+                out[..n].copy_from_slice(&available[..n]);
+                self.pos += n;
+                return Ok(n);
+            }
+
+            // The end of the buffer could be the beginning of a delimiter, it's kept until more
+            // data is read.
+            let safe = available.len().saturating_sub(self.delimiter.len() - 1);
+            if safe > 0 {
+                let n = safe.min(out.len());
+                out[..n].copy_from_slice(&available[..n]);
+                self.pos += n;
+                return Ok(n);
+            }
+
+            if !self.fill()? {
+                return Err(invalid("unexpected end of the body"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::MultipartReader;
+
+    /// Yields one byte at a time to test delimiters split across reads.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; name=\"a\"; filename=\"a.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        hello\r\n--xy not a delimiter\r\n\
+        --xyz  \r\n\
+        content-disposition: form-data; name=\"b\"\r\n\
+        \r\n\
+        \r\n\
+        --xyz--\r\n\
+        epilogue";
+
+    fn parts<R: Read>(mut reader: MultipartReader<R>) -> Vec<(Option<String>, Option<String>, Vec<u8>)> {
+        let mut parts = Vec::new();
+        while let Some(headers) = reader.next_part().unwrap() {
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body).unwrap();
+            parts.push((headers.content_disposition, headers.content_type, body));
+        }
+        parts
+    }
+
+    #[test]
+    fn test_parts() {
+        for parts in [
+            parts(MultipartReader::new(BODY, "xyz")),
+            parts(MultipartReader::new(OneByte(BODY), "xyz")),
+        ] {
+            assert_eq!(
+                parts,
+                [
+                    (
+                        Some("form-data; name=\"a\"; filename=\"a.txt\"".to_owned()),
+                        Some("text/plain".to_owned()),
+                        b"hello\r\n--xy not a delimiter".to_vec()
+                    ),
+                    (Some("form-data; name=\"b\"".to_owned()), None, b"".to_vec()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_skip_unread_body() {
+        let mut reader = MultipartReader::new(BODY, "xyz");
+        assert!(reader.next_part().unwrap().is_some());
+        let headers = reader.next_part().unwrap().unwrap();
+        assert_eq!(headers.content_disposition.as_deref(), Some("form-data; name=\"b\""));
+        assert!(reader.next_part().unwrap().is_none());
+        assert!(reader.next_part().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_no_preamble() {
+        let body = b"--b\r\n\r\nx\r\n--b--";
+        assert_eq!(
+            parts(MultipartReader::new(&body[..], "b")),
+            [(None, None, b"x".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_headers_too_long() {
+        let mut body = b"--b\r\n".to_vec();
+        for _ in 0..10 {
+            body.extend(b"X-Header: ");
+            body.extend(vec![b'x'; 4000]);
+            body.extend(b"\r\n");
+        }
+        body.extend(b"\r\nx\r\n--b--");
+        let err = MultipartReader::new(&body[..], "b").next_part().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut body = b"--b\r\nX-Header: ".to_vec();
+        body.extend(vec![b'x'; 20000]);
+        body.extend(b"\r\n\r\nx\r\n--b--");
+        let err = MultipartReader::new(&body[..], "b").next_part().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut reader = MultipartReader::new(&b"--b\r\n\r\nunfinished"[..], "b");
+        assert!(reader.next_part().unwrap().is_some());
+        let mut body = Vec::new();
+        let err = reader.read_to_end(&mut body).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut reader = MultipartReader::new(&b"no delimiter"[..], "b");
+        assert!(reader.next_part().is_err());
+    }
+}
@@ -1,98 +1,295 @@
-use std::io::{self, BufReader, Read, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::SocketAddr;
 use std::str;
 
 use http::{
-    header::{HeaderName, HeaderValue, TRANSFER_ENCODING},
+    header::{HeaderName, HeaderValue, CONNECTION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE},
     HeaderMap, StatusCode,
 };
 use url::Url;
 
+use crate::cache::{CacheCapture, CacheEntry};
 use crate::error::{ErrorKind, InvalidResponseKind, Result};
-use crate::parsing::buffers::{self, trim_byte};
-use crate::parsing::{body_reader::BodyReader, compressed_reader::CompressedReader, ResponseReader};
+use crate::parsing::body_reader::{response_has_body, BodyReader};
+use crate::parsing::buffers::{self, trim_byte, trim_byte_left};
+use crate::parsing::{compressed_reader::CompressedReader, ResponseReader};
+use crate::raw::ResponseLimits;
 use crate::request::PreparedRequest;
 use crate::streams::BaseStream;
 
+#[cfg(feature = "har")]
+use crate::har::BodyCapture;
 #[cfg(feature = "charsets")]
 use crate::{charsets::Charset, parsing::TextReader};
 
 #[cfg(feature = "json")]
 use serde::de::DeserializeOwned;
 
-pub fn parse_response_head<R>(reader: &mut BufReader<R>, max_headers: usize) -> Result<(StatusCode, HeaderMap)>
+/// Status line and headers of a response.
+#[derive(Debug)]
+pub struct ResponseHead {
+    pub status: StatusCode,
+    /// Reason phrase of the status line, decoded lossily, or `None` if it is empty.
+    pub reason: Option<String>,
+    pub headers: HeaderMap,
+    /// Number of headers dropped because their name or value was invalid.
+    pub dropped_headers: usize,
+}
+
+/// Split the first token delimited by spaces from the rest of the line.
+fn split_token(line: &[u8]) -> (&[u8], &[u8]) {
+    let line = trim_byte_left(b' ', line);
+    let end = line.iter().position(|&b| b == b' ').unwrap_or(line.len());
+    line.split_at(end)
+}
+
+/// Buffers used to parse response heads, kept between the hops of a request so that their
+/// allocations are reused.
+#[derive(Debug, Default)]
+pub struct HeadScratch {
+    line: Vec<u8>,
+    continuation: Vec<u8>,
+    headers: Option<HeaderMap>,
+}
+
+impl HeadScratch {
+    /// Give back the headers of a response which is no longer needed, to parse the next head into.
+    pub fn recycle(&mut self, headers: HeaderMap) {
+        self.headers = Some(headers);
+    }
+
+    fn take_headers(&mut self) -> HeaderMap {
+        match self.headers.take() {
+            Some(mut headers) => {
+                headers.clear();
+                headers
+            }
+            None => HeaderMap::with_capacity(16),
+        }
+    }
+}
+
+/// Parse the status line and the headers of a response.
+///
+/// Headers with an invalid name or value make the parsing fail when `limits.strict_headers` is
+/// true, otherwise they are dropped and counted. Folded header values are joined with a single
+/// space, unless `limits.reject_obs_fold` is true in which case they make the parsing fail.
+pub fn parse_response_head<R>(reader: &mut BufReader<R>, limits: &ResponseLimits) -> Result<ResponseHead>
+where
+    R: Read,
+{
+    parse_response_head_with(reader, &mut HeadScratch::default(), limits)
+}
+
+/// Same as `parse_response_head`, using the buffers of `scratch`.
+pub fn parse_response_head_with<R>(
+    reader: &mut BufReader<R>,
+    scratch: &mut HeadScratch,
+    limits: &ResponseLimits,
+) -> Result<ResponseHead>
 where
     R: Read,
 {
     const MAX_LINE_LEN: u64 = 16 * 1024;
 
-    let mut line = Vec::new();
-    let mut headers = HeaderMap::new();
+    let mut headers = scratch.take_headers();
+    let HeadScratch { line, continuation, .. } = scratch;
+    let mut dropped = 0;
 
     // status line
-    let status: StatusCode = {
-        buffers::read_line(reader, &mut line, MAX_LINE_LEN)?;
-        let mut parts = line.split(|&b| b == b' ').filter(|x| !x.is_empty());
-
-        let _ = parts.next().ok_or(InvalidResponseKind::StatusLine)?;
-        let code = parts.next().ok_or(InvalidResponseKind::StatusLine)?;
+    buffers::read_line(reader, line, MAX_LINE_LEN)?;
+    let (version, rest) = split_token(line);
+    let (code, rest) = split_token(rest);
+    if version.is_empty() || code.is_empty() {
+        return Err(InvalidResponseKind::StatusLine.into());
+    }
 
-        str::from_utf8(code)
-            .map_err(|_| InvalidResponseKind::StatusCode)?
-            .parse()
-            .map_err(|_| InvalidResponseKind::StatusCode)?
+    let status: StatusCode = str::from_utf8(code)
+        .map_err(|_| InvalidResponseKind::StatusCode)?
+        .parse()
+        .map_err(|_| InvalidResponseKind::StatusCode)?;
+    let reason = trim_byte(b' ', rest);
+    let reason = if reason.is_empty() {
+        None
+    } else {
+        Some(String::from_utf8_lossy(reason).into_owned())
     };
 
     // headers
     loop {
-        buffers::read_line_strict(reader, &mut line, MAX_LINE_LEN)?;
+        buffers::read_line_strict(reader, line, MAX_LINE_LEN)?;
         if line.is_empty() {
             break;
-        } else if headers.len() == max_headers {
+        } else if headers.len() == limits.max_headers {
             return Err(InvalidResponseKind::Header.into());
         }
 
+        // Lines starting with whitespace continue the value of the previous line (obs-fold).
+        while let Some(b' ') | Some(b'\t') = reader.fill_buf()?.first() {
+            let max_len = MAX_LINE_LEN.saturating_sub(line.len() as u64);
+            buffers::read_line_strict(reader, continuation, max_len)?;
+            line.push(b'\n');
+            line.extend_from_slice(continuation);
+        }
+
         let col = line
             .iter()
             .position(|&c| c == b':')
             .ok_or(InvalidResponseKind::Header)?;
 
-        buffers::replace_byte(b'\n', b' ', &mut line[col + 1..]);
-
         let header = trim_byte(b' ', &line[..col]);
-        let value = trim_byte(b' ', &line[col + 1..]);
-
-        let header = match HeaderName::from_bytes(header) {
-            Ok(val) => val,
-            Err(err) => {
-                warn!("Dropped invalid response header: {}", err);
-                continue;
+        let unfolded;
+        let value = if line[col + 1..].contains(&b'\n') {
+            if limits.reject_obs_fold {
+                return Err(InvalidResponseKind::Header.into());
             }
+            unfolded = buffers::unfold(&line[col + 1..]);
+            &unfolded[..]
+        } else {
+            trim_byte(b' ', &line[col + 1..])
         };
 
-        headers.append(header, HeaderValue::from_bytes(value).map_err(http::Error::from)?);
+        match (HeaderName::from_bytes(header), HeaderValue::from_bytes(value)) {
+            (Ok(header), Ok(value)) => {
+                headers.append(header, value);
+            }
+            _ => {
+                let name = String::from_utf8_lossy(header).into_owned();
+                if limits.strict_headers {
+                    return Err(InvalidResponseKind::InvalidHeader { name }.into());
+                }
+                warn!("Dropped invalid response header {:?}", name);
+                dropped += 1;
+            }
+        }
     }
 
-    Ok((status, headers))
+    Ok(ResponseHead {
+        status,
+        reason,
+        headers,
+        dropped_headers: dropped,
+    })
+}
+
+/// Remove the hop-by-hop headers, which only concern the connection the response was received on,
+/// along with the headers named in the `Connection` header.
+///
+/// `Proxy-Authenticate` is kept since it is needed to answer the challenges of a proxy.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let connection_options: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|token| HeaderName::from_bytes(token.trim().as_bytes()).ok())
+        .collect();
+    for name in connection_options {
+        headers.remove(name);
+    }
+
+    for name in [CONNECTION, TRANSFER_ENCODING, TE, TRAILER, UPGRADE] {
+        headers.remove(name);
+    }
+    headers.remove("keep-alive");
+    headers.remove("proxy-connection");
 }
 
+/// Parse the names of the trailer fields announced by the `Trailer` header.
+fn parse_declared_trailers(headers: &HeaderMap) -> Vec<HeaderName> {
+    let mut names: Vec<HeaderName> = Vec::new();
+    let tokens = headers
+        .get_all(TRAILER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for token in tokens {
+        if let Ok(name) = HeaderName::from_bytes(token.trim().as_bytes()) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+#[cfg(any(test, feature = "__bench"))]
 pub fn parse_response<B>(reader: BaseStream, request: &PreparedRequest<B>, url: &Url) -> Result<Response> {
-    let mut reader = BufReader::new(reader);
-    let (status, mut headers) = parse_response_head(&mut reader, request.base_settings.max_headers)?;
-    let body_reader = BodyReader::new(&headers, reader)?;
+    parse_response_with(reader, request, url, &mut HeadScratch::default())
+}
+
+/// Same as `parse_response`, using the buffers of `scratch` to parse the head.
+pub fn parse_response_with<B>(
+    reader: BaseStream,
+    request: &PreparedRequest<B>,
+    url: &Url,
+    scratch: &mut HeadScratch,
+) -> Result<Response> {
+    let mut reader = BufReader::with_capacity(request.base_settings.read_buffer_size, reader);
+    let settings = &request.base_settings;
+    let ResponseHead {
+        status,
+        reason,
+        mut headers,
+        dropped_headers,
+    } = parse_response_head_with(&mut reader, scratch, &settings.response_limits())?;
+    if let Some(timeout) = settings.response_body_read_timeout {
+        reader.get_ref().set_read_timeout(Some(timeout))?;
+    }
+    let has_body = response_has_body(request.method(), status);
+    let mut body_reader = BodyReader::new(&headers, reader, has_body)?;
+    let chunked = matches!(body_reader, BodyReader::Chunked(_));
+    let declared_trailers = parse_declared_trailers(&headers);
+    if let BodyReader::Chunked(reader) = &mut body_reader {
+        reader.set_lenient_termination(settings.lenient_chunked_termination && declared_trailers.is_empty());
+    }
+    // The decoder might read from the body as soon as it's created.
+    let body_len = body_reader.remaining_len();
     let compressed_reader = CompressedReader::new(&headers, request, body_reader)?;
-    let response_reader = ResponseReader::new(&headers, request, compressed_reader);
+    let response_reader = ResponseReader::new(&headers, request, compressed_reader, body_len);
 
-    // Remove HOP-BY-HOP headers
-    headers.remove(TRANSFER_ENCODING);
+    if !settings.keep_hop_headers {
+        remove_hop_by_hop_headers(&mut headers);
+    }
 
     Ok(Response {
         url: url.clone(),
         status,
         headers,
         reader: response_reader,
+        redirect_history: Vec::new(),
+        reason_phrase: reason,
+        dropped_header_count: dropped_headers,
+        connection_info: None,
+        upload_interrupted: false,
+        chunked,
+        declared_trailers,
+        from_cache: false,
     })
 }
 
+/// Addresses of the connection a `Response` was received on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// Address of the server, or of the proxy if the request went through one.
+    pub peer: SocketAddr,
+    /// Local address of the connection.
+    pub local: SocketAddr,
+    /// Whether the request went through a proxy.
+    pub proxied: bool,
+}
+
+/// A redirection followed before receiving a `Response`.
+#[derive(Clone, Debug)]
+pub struct RedirectHop {
+    /// Status code of the redirection.
+    pub status: StatusCode,
+    /// URL which answered with the redirection.
+    pub url: Url,
+    /// Value of the `Location` header, as received.
+    pub location: String,
+}
+
 /// `Response` represents a response returned by a server.
 #[derive(Debug)]
 pub struct Response {
@@ -100,15 +297,148 @@ pub struct Response {
     status: StatusCode,
     headers: HeaderMap,
     reader: ResponseReader,
+    redirect_history: Vec<RedirectHop>,
+    reason_phrase: Option<String>,
+    dropped_header_count: usize,
+    connection_info: Option<ConnectionInfo>,
+    upload_interrupted: bool,
+    chunked: bool,
+    declared_trailers: Vec<HeaderName>,
+    from_cache: bool,
 }
 
 impl Response {
+    /// Create the response to `request` served from the cache, reading the cached body from
+    /// memory.
+    pub(crate) fn cached<B>(request: &PreparedRequest<B>, url: &Url, entry: &CacheEntry) -> Result<Response> {
+        let len = entry.body.len() as u64;
+        let reader = BufReader::new(BaseStream::mock(entry.body.clone())).take(len);
+        let compressed_reader = CompressedReader::new(&entry.headers, request, BodyReader::Length(reader))?;
+        let response_reader = ResponseReader::new(&entry.headers, request, compressed_reader, Some(len));
+
+        Ok(Response {
+            url: url.clone(),
+            status: StatusCode::OK,
+            headers: entry.headers.clone(),
+            reader: response_reader,
+            redirect_history: Vec::new(),
+            reason_phrase: entry.reason.clone(),
+            dropped_header_count: 0,
+            connection_info: None,
+            upload_interrupted: false,
+            chunked: false,
+            declared_trailers: Vec::new(),
+            from_cache: true,
+        })
+    }
+
+    pub(crate) fn set_cache_capture(&mut self, capture: CacheCapture) {
+        self.reader.set_cache_capture(capture);
+    }
+
+    pub(crate) fn is_decoding(&self) -> bool {
+        self.reader.is_decoding()
+    }
+
+    #[cfg(feature = "har")]
+    pub(crate) fn set_har_capture(&mut self, capture: BodyCapture) {
+        self.reader.set_har_capture(capture);
+    }
+
+    /// Take the connection of this response, once the server switched to another protocol.
+    pub(crate) fn take_connection(&mut self) -> (Vec<u8>, BaseStream) {
+        self.reader.take_connection()
+    }
+
+    pub(crate) fn set_connection_info(&mut self, connection_info: Option<ConnectionInfo>) {
+        self.connection_info = connection_info;
+    }
+
+    pub(crate) fn set_upload_interrupted(&mut self, upload_interrupted: bool) {
+        self.upload_interrupted = upload_interrupted;
+    }
+
+    pub(crate) fn set_redirect_history(&mut self, redirect_history: Vec<RedirectHop>) {
+        self.redirect_history = redirect_history;
+    }
+
     /// Get the final URL of this `Response`.
     #[inline]
     pub fn url(&self) -> &Url {
         &self.url
     }
 
+    /// Get the redirections followed before receiving this `Response`, in order.
+    ///
+    /// This is empty if the request was not redirected.
+    #[inline]
+    pub fn redirect_history(&self) -> &[RedirectHop] {
+        &self.redirect_history
+    }
+
+    /// Get the reason phrase of the status line, like `Not Found`, as sent by the server.
+    ///
+    /// Bytes which are not valid UTF-8 are replaced. Returns `None` if the reason phrase is empty.
+    #[inline]
+    pub fn reason_phrase(&self) -> Option<&str> {
+        self.reason_phrase.as_deref()
+    }
+
+    /// Get the addresses of the connection this `Response` was received on.
+    ///
+    /// When the request went through a proxy, the peer is the proxy.
+    #[inline]
+    pub fn connection_info(&self) -> Option<&ConnectionInfo> {
+        self.connection_info.as_ref()
+    }
+
+    /// Check if the server closed the connection before the whole request body was sent.
+    ///
+    /// This happens when a server answers early, for instance with `413 Payload Too Large`, and
+    /// stops reading the body. The response is still returned since it explains the problem.
+    #[inline]
+    pub fn upload_interrupted(&self) -> bool {
+        self.upload_interrupted
+    }
+
+    /// Get the number of response headers which were dropped because their name or value was
+    /// invalid.
+    ///
+    /// This is always zero when `strict_response_headers` is enabled, since invalid headers make
+    /// the request fail instead.
+    #[inline]
+    pub fn dropped_header_count(&self) -> usize {
+        self.dropped_header_count
+    }
+
+    /// Check if the body of this `Response` was sent using the chunked transfer encoding.
+    ///
+    /// The `Transfer-Encoding` header is removed from the headers along with the other
+    /// hop-by-hop headers, unless `keep_hop_headers` is enabled.
+    #[inline]
+    pub fn was_chunked(&self) -> bool {
+        self.chunked
+    }
+
+    /// Get the names of the trailer fields announced by the `Trailer` header of this `Response`.
+    ///
+    /// The `Trailer` header is removed from the headers along with the other hop-by-hop headers,
+    /// unless `keep_hop_headers` is enabled. Servers usually only send trailers when the request
+    /// advertised them with `te_trailers`.
+    #[inline]
+    pub fn declared_trailers(&self) -> &[HeaderName] {
+        &self.declared_trailers
+    }
+
+    /// Check if this `Response` was served from the cache of the `Session`, after the server
+    /// answered `304 Not Modified` to the conditional request.
+    ///
+    /// See [`Session::cache`](crate::Session::cache) for details.
+    #[inline]
+    pub fn from_cache(&self) -> bool {
+        self.from_cache
+    }
+
     /// Get the status code of this `Response`.
     #[inline]
     pub fn status(&self) -> StatusCode {
@@ -121,6 +451,23 @@ impl Response {
         &self.headers
     }
 
+    /// Get the estimated size of the response body, as a `(compressed, decompressed)` pair.
+    ///
+    /// See [`ResponseReader::size_hint`] for details.
+    #[inline]
+    pub fn size_hint(&self) -> (Option<u64>, Option<u64>) {
+        self.reader.size_hint()
+    }
+
+    /// Check if the end of the response body was missing and ignored because
+    /// `tolerate_truncated_body` is enabled.
+    ///
+    /// See [`ResponseReader::was_truncated`] for details.
+    #[inline]
+    pub fn was_truncated(&self) -> bool {
+        self.reader.was_truncated()
+    }
+
     /// Checks if the status code of this `Response` was a success code.
     #[inline]
     pub fn is_success(&self) -> bool {
@@ -128,11 +475,17 @@ impl Response {
     }
 
     /// Returns error variant if the status code was not a success code.
+    ///
+    /// The error contains the status code and the headers of the response.
     pub fn error_for_status(self) -> Result<Self> {
         if self.is_success() {
             Ok(self)
         } else {
-            Err(ErrorKind::StatusCode(self.status).into())
+            Err(ErrorKind::StatusCode {
+                status: self.status,
+                headers: self.headers,
+            }
+            .into())
         }
     }
 
@@ -144,6 +497,15 @@ impl Response {
         (self.status, self.headers, self.reader)
     }
 
+    /// Get the `StatusCode` and `HeaderMap` of this `Response`, dropping the body.
+    ///
+    /// This is meant for the responses to HEAD requests, which never have a body. For other
+    /// requests, the body is not read and the connection is closed.
+    #[inline]
+    pub fn into_head(self) -> (StatusCode, HeaderMap) {
+        (self.status, self.headers)
+    }
+
     /// Write the response to any object that implements `Write`.
     #[inline]
     pub fn write_to<W>(self, writer: W) -> Result<u64>
@@ -159,6 +521,31 @@ impl Response {
         self.reader.bytes()
     }
 
+    /// Read the response to a `bytes::Bytes` buffer.
+    ///
+    /// This method only exists when the `bytes` feature is enabled.
+    #[cfg(feature = "bytes")]
+    #[inline]
+    pub fn bytes_buf(self) -> Result<bytes::Bytes> {
+        self.reader.bytes_buf()
+    }
+
+    /// Read the response, appending it to the given `Vec` of bytes.
+    ///
+    /// Returns the number of bytes appended.
+    #[inline]
+    pub fn read_into(self, buf: &mut Vec<u8>) -> Result<u64> {
+        self.reader.read_into(buf)
+    }
+
+    /// Read the response body and drop it, returning the number of bytes dropped.
+    ///
+    /// See [`ResponseReader::discard`] for details.
+    #[inline]
+    pub fn discard_body(self) -> Result<u64> {
+        self.reader.discard()
+    }
+
     /// Read the response to a `String`.
     ///
     /// If the `charsets` feature is enabled, it will try to decode the response using
@@ -187,6 +574,28 @@ impl Response {
         self.reader.text_with(charset)
     }
 
+    /// Read the response to a `String`, returning an error if the body contains invalid data.
+    ///
+    /// The charset is selected like in `text`, but decoding is strict: instead of inserting
+    /// replacement characters, an `InvalidTextEncoding` error is returned when the first invalid
+    /// sequence is found.
+    #[inline]
+    pub fn text_strict(self) -> Result<String> {
+        self.reader.text_strict()
+    }
+
+    /// Read the response to a `String`, strictly decoding with the given `Charset`.
+    ///
+    /// This will ignore the encoding from the response headers and the default encoding, if any.
+    /// An `InvalidTextEncoding` error is returned when the first invalid sequence is found.
+    ///
+    /// This method only exists when the `charsets` feature is enabled.
+    #[cfg(feature = "charsets")]
+    #[inline]
+    pub fn text_with_strict(self, charset: Charset) -> Result<String> {
+        self.reader.text_with_strict(charset)
+    }
+
     /// Create a `TextReader` from this `ResponseReader`.
     ///
     /// If the response headers contain charset information, that charset will be used to decode the body.
@@ -210,6 +619,17 @@ impl Response {
         self.reader.text_reader_with(charset)
     }
 
+    /// Iterate over the lines of the response body, decoded like in `text_reader`.
+    ///
+    /// Lines can end with `\n` or `\r\n`, the line ending is not included in the returned strings.
+    ///
+    /// This method only exists when the `charsets` feature is enabled.
+    #[cfg(feature = "charsets")]
+    #[inline]
+    pub fn lines(self) -> io::Lines<TextReader<BufReader<ResponseReader>>> {
+        self.reader.lines()
+    }
+
     /// Read the response body to a String using the UTF-8 encoding.
     ///
     /// This method ignores headers and the default encoding.
@@ -239,6 +659,32 @@ impl Response {
         self.reader.json()
     }
 
+    /// Parse the response as a JSON object, decoding the body strictly.
+    ///
+    /// The body is decoded like in `text_strict`, so an `InvalidTextEncoding` error is returned
+    /// if it contains invalid data instead of parsing replacement characters.
+    #[cfg(feature = "json")]
+    #[inline]
+    pub fn json_strict<T>(self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.reader.json_strict()
+    }
+
+    /// Parse the response as a JSON object like `json`, returning a `TrailingData` error if the
+    /// JSON value is followed by anything but whitespace.
+    ///
+    /// See [`ResponseReader::json_strict_eof`] for details.
+    #[cfg(feature = "json")]
+    #[inline]
+    pub fn json_strict_eof<T>(self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.reader.json_strict_eof()
+    }
+
     /// Parse the response as a JSON object encoded in UTF-8.
     ///
     /// This method ignores headers and the default encoding.
@@ -264,31 +710,313 @@ impl Read for Response {
 fn test_read_request_head() {
     let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nContent-Type: text/plain\r\n\r\nhello";
     let mut reader = BufReader::new(&response[..]);
-    let (status, headers) = parse_response_head(&mut reader, 100).unwrap();
+    let ResponseHead { status, headers, .. } = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
     assert_eq!(status, StatusCode::OK);
     assert_eq!(headers.len(), 2);
     assert_eq!(headers[http::header::CONTENT_LENGTH], "5");
     assert_eq!(headers[http::header::CONTENT_TYPE], "text/plain");
 }
 
+#[test]
+fn test_status_line_extra_spaces() {
+    let response = b"HTTP/1.1  200   OK\x80\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let head = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
+    assert_eq!(head.status, StatusCode::OK);
+    assert_eq!(head.reason.as_deref(), Some("OK\u{fffd}"));
+}
+
+#[test]
+fn test_status_line_missing_reason() {
+    for response in [&b"HTTP/1.1 204\r\n\r\n"[..], &b"HTTP/1.1 204 \r\n\r\n"[..]] {
+        let mut reader = BufReader::new(response);
+        let head = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
+        assert_eq!(head.status, StatusCode::NO_CONTENT);
+        assert_eq!(head.reason, None);
+    }
+
+    for response in [&b"HTTP/1.1\r\n\r\n"[..], &b" \r\n\r\n"[..]] {
+        let mut reader = BufReader::new(response);
+        let err = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::InvalidResponse(InvalidResponseKind::StatusLine)
+        ));
+    }
+}
+
+#[test]
+fn test_status_line_http_1_0() {
+    let response = b"HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.reason_phrase(), Some("Not Found"));
+}
+
+#[test]
+fn test_latin1_header_value() {
+    let response = b"HTTP/1.1 200 OK\r\nX-Name: Andr\xe9\r\nContent-Length: 0\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert_eq!(resp.dropped_header_count(), 0);
+    assert_eq!(resp.headers()["x-name"].as_bytes(), b"Andr\xe9");
+    assert_eq!(resp.header_str("x-name"), None);
+}
+
 #[test]
 fn test_line_folded_header() {
     let response = b"HTTP/1.1 200 OK\r\nheader-of-great-many-lines: foo\nbar\nbaz\nqux\r\nthe-other-kind-of-header: foobar\r\n\r\n";
     let mut reader = BufReader::new(&response[..]);
-    let (status, headers) = parse_response_head(&mut reader, 100).unwrap();
+    let ResponseHead { status, headers, .. } = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
     assert_eq!(status, StatusCode::OK);
     assert_eq!(headers.len(), 2);
     assert_eq!(headers["header-of-great-many-lines"], "foo bar baz qux");
     assert_eq!(headers["the-other-kind-of-header"], "foobar");
 }
 
+#[test]
+fn test_line_folded_header_obs_fold() {
+    let response = b"HTTP/1.1 200 OK\r\nx-folded: foo \r\n\tbar\r\n  \t baz\r\nx-next: qux\r\nx-empty:\r\n \r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let ResponseHead { status, headers, .. } = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.len(), 3);
+    assert_eq!(headers["x-folded"], "foo bar baz");
+    assert_eq!(headers["x-next"], "qux");
+    assert_eq!(headers["x-empty"], "");
+}
+
+#[test]
+fn test_line_folded_header_rejected() {
+    for response in [
+        &b"HTTP/1.1 200 OK\r\nx-folded: foo\r\n\tbar\r\n\r\n"[..],
+        &b"HTTP/1.1 200 OK\r\nx-folded: foo\nbar\r\n\r\n"[..],
+    ] {
+        let mut reader = BufReader::new(response);
+        let err = parse_response_head(
+            &mut reader,
+            &ResponseLimits {
+                reject_obs_fold: true,
+                ..ResponseLimits::default()
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::InvalidResponse(InvalidResponseKind::Header)
+        ));
+    }
+
+    let response = b"HTTP/1.1 200 OK\r\nx-plain: foo\tbar\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let ResponseHead { headers, .. } = parse_response_head(
+        &mut reader,
+        &ResponseLimits {
+            reject_obs_fold: true,
+            ..ResponseLimits::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(headers["x-plain"], "foo\tbar");
+}
+
+#[test]
+fn test_line_length_independent_of_buffer_size() {
+    let value = "x".repeat(10 * 1024);
+    let response = format!("HTTP/1.1 200 OK\r\nlong-header: {value}\r\n\r\n");
+    let mut reader = BufReader::with_capacity(1024, response.as_bytes());
+    let ResponseHead { headers, .. } = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
+    assert_eq!(headers["long-header"], value.as_str());
+
+    let value = "x".repeat(20 * 1024);
+    let response = format!("HTTP/1.1 200 OK\r\nlong-header: {value}\r\n\r\n");
+    let mut reader = BufReader::with_capacity(256 * 1024, response.as_bytes());
+    assert!(parse_response_head(&mut reader, &ResponseLimits::default()).is_err());
+}
+
+#[test]
+fn test_invalid_headers_lenient() {
+    let response = b"HTTP/1.1 200 OK\r\nbad name: foo\r\nx-control: a\x00b\r\nx-delete: a\x7fb\r\nx-latin1: caf\xe9\r\ngood: bar\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let ResponseHead {
+        status,
+        headers,
+        dropped_headers: dropped,
+        ..
+    } = parse_response_head(&mut reader, &ResponseLimits::default()).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(dropped, 3);
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers["good"], "bar");
+    // Bytes which are not UTF-8 are allowed in header values as obs-text.
+    assert_eq!(headers["x-latin1"].as_bytes(), b"caf\xe9");
+}
+
+#[test]
+fn test_invalid_headers_strict() {
+    let cases: [(&[u8], &str); 3] = [
+        (b"HTTP/1.1 200 OK\r\nbad name: foo\r\n\r\n", "bad name"),
+        (b"HTTP/1.1 200 OK\r\nx-control: a\x00b\r\n\r\n", "x-control"),
+        (b"HTTP/1.1 200 OK\r\nx-\xff: foo\r\n\r\n", "x-\u{fffd}"),
+    ];
+    for (response, expected) in cases {
+        let mut reader = BufReader::new(response);
+        match parse_response_head(
+            &mut reader,
+            &ResponseLimits {
+                strict_headers: true,
+                ..ResponseLimits::default()
+            },
+        )
+        .unwrap_err()
+        .kind()
+        {
+            ErrorKind::InvalidResponse(InvalidResponseKind::InvalidHeader { name }) => assert_eq!(name, expected),
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    let response = b"HTTP/1.1 200 OK\r\nx-latin1: caf\xe9\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    assert_eq!(
+        parse_response_head(
+            &mut reader,
+            &ResponseLimits {
+                strict_headers: true,
+                ..ResponseLimits::default()
+            }
+        )
+        .unwrap()
+        .dropped_headers,
+        0
+    );
+}
+
+#[test]
+fn test_hop_by_hop_headers_removed() {
+    let response = b"HTTP/1.1 200 OK\r\nConnection: close, X-Custom-Hop\r\nConnection: x-other-hop\r\nKeep-Alive: timeout=5\r\nProxy-Connection: keep-alive\r\nX-Custom-Hop: 1\r\nX-Other-Hop: 2\r\nX-Kept: 3\r\nTrailer: x-checksum\r\nUpgrade: h2c\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+    let mut req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert_eq!(resp.headers().len(), 1);
+    assert_eq!(resp.headers()["x-kept"], "3");
+
+    req.base_settings.keep_hop_headers = true;
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert_eq!(resp.headers().len(), 10);
+    assert_eq!(resp.headers()["x-custom-hop"], "1");
+    assert_eq!(resp.headers()["transfer-encoding"], "chunked");
+}
+
+#[test]
+fn test_chunked_framing_and_declared_trailers() {
+    let response = b"HTTP/1.1 200 OK\r\nTrailer: X-Checksum, server-timing\r\nTrailer: x-checksum, bad name\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\nX-Checksum: abc\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert!(resp.was_chunked());
+    assert_eq!(resp.declared_trailers(), ["x-checksum", "server-timing"]);
+    assert!(resp.headers().get("trailer").is_none());
+    assert_eq!(resp.text().unwrap(), "hello");
+
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert!(!resp.was_chunked());
+    assert!(resp.declared_trailers().is_empty());
+
+    // A response to a HEAD request has no body, whatever its headers say.
+    let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::HEAD, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert!(!resp.was_chunked());
+}
+
+#[test]
+fn test_lenient_chunked_termination() {
+    let mut req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let text = |req: &PreparedRequest<_>, body: &str| {
+        let response = format!("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{body}");
+        parse_response(BaseStream::mock(response.into_bytes()), req, req.url())?.text()
+    };
+
+    assert_eq!(text(&req, "5\r\nhello\r\n0\r\n\r\n").unwrap(), "hello");
+    assert_eq!(text(&req, "5\r\nhello\r\n0\r\n").unwrap(), "hello");
+    assert!(text(&req, "5\r\nhello\r\n0").is_err());
+    assert!(text(&req, "5\r\nhel").is_err());
+
+    // The trailers announced by the response are expected.
+    let response = b"HTTP/1.1 200 OK\r\nTrailer: X-Checksum\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n";
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert!(resp.text().is_err());
+
+    req.base_settings.lenient_chunked_termination = false;
+    assert!(text(&req, "5\r\nhello\r\n0\r\n").is_err());
+    assert_eq!(text(&req, "5\r\nhello\r\n0\r\n\r\n").unwrap(), "hello");
+}
+
+#[test]
+fn test_dropped_header_count() {
+    let response = b"HTTP/1.1 200 OK\r\nbad name: foo\r\nContent-Length: 0\r\n\r\n";
+    let mut req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert_eq!(resp.dropped_header_count(), 1);
+
+    req.base_settings.strict_response_headers = true;
+    assert!(parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).is_err());
+}
+
 #[test]
 fn test_max_headers_limit() {
     let response = b"HTTP/1.1 200 OK\r\nfirst-header: foo\r\nsecond-header: bar\r\none-header-too-many: baz\r\n\r\n";
     let mut reader = BufReader::new(&response[..]);
-    let err = parse_response_head(&mut reader, 2).unwrap_err();
+    let err = parse_response_head(
+        &mut reader,
+        &ResponseLimits {
+            max_headers: 2,
+            ..ResponseLimits::default()
+        },
+    )
+    .unwrap_err();
     assert!(matches!(
         err.kind(),
         ErrorKind::InvalidResponse(InvalidResponseKind::Header)
     ));
 }
+
+#[test]
+fn test_error_for_status_headers() {
+    let response = b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 120\r\nContent-Length: 0\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+
+    let err = resp.error_for_status().unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+    assert_eq!(err.response_headers().unwrap()[http::header::RETRY_AFTER], "120");
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::StatusCode { status, .. } if *status == StatusCode::TOO_MANY_REQUESTS
+    ));
+}
+
+#[test]
+fn test_not_modified_with_content_length() {
+    // The bytes after the head belong to the next response on the connection.
+    let response = b"HTTP/1.1 304 Not Modified\r\nContent-Length: 10\r\n\r\nHTTP/1.1 200 OK";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+    let resp = parse_response(BaseStream::mock(response.to_vec()), &req, req.url()).unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(resp.text().unwrap(), "");
+}
+
+#[test]
+fn test_no_content_ignores_framing_headers() {
+    for head in [
+        "HTTP/1.1 204 No Content\r\nContent-Length: 10\r\n\r\n",
+        "HTTP/1.1 204 No Content\r\nTransfer-Encoding: chunked\r\n\r\n",
+        "HTTP/1.1 103 Early Hints\r\nContent-Length: 10\r\n\r\n",
+    ] {
+        let response = format!("{head}HTTP/1.1 200 OK");
+        let req = PreparedRequest::new(http::Method::GET, "http://example.com/");
+        let resp = parse_response(BaseStream::mock(response.into_bytes()), &req, req.url()).unwrap();
+        assert_eq!(resp.text().unwrap(), "", "{}", head);
+    }
+}
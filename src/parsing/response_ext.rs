@@ -0,0 +1,1251 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(feature = "cookies")]
+use cookie::Cookie;
+#[cfg(feature = "multipart-form")]
+use http::header::CONTENT_TYPE;
+#[cfg(feature = "cookies")]
+use http::header::SET_COOKIE;
+use http::header::{
+    AsHeaderName, HeaderName, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION, LINK, WWW_AUTHENTICATE,
+};
+use http::HeaderMap;
+use http::Method;
+
+#[cfg(any(feature = "checksum", feature = "multipart-form"))]
+use crate::error::ErrorKind;
+use crate::error::Result;
+#[cfg(feature = "multipart-form")]
+use crate::parsing::multipart_reader::MultipartReader;
+use crate::parsing::Response;
+#[cfg(feature = "checksum")]
+use crate::request::body::HashWriter;
+
+/// Split a header value on `separator`, ignoring separators inside quoted strings and `<>` references.
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut reference = false;
+
+    for (idx, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' if !reference => quoted = !quoted,
+            '<' if !quoted => reference = true,
+            '>' if !quoted => reference = false,
+            c if c == separator && !quoted && !reference => {
+                parts.push(&value[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Split the parameters of a header value on `;`, ignoring separators inside quoted strings.
+fn split_params(value: &str) -> Vec<&str> {
+    split_unquoted(value, ';')
+}
+
+/// Split a parameter into its lowercase name and its unquoted value, if any.
+fn parse_param(param: &str) -> (String, Option<String>) {
+    match param.split_once('=') {
+        Some((name, value)) => (name.trim().to_ascii_lowercase(), Some(unquote(value.trim()))),
+        None => (param.trim().to_ascii_lowercase(), None),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => {
+            let mut out = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => out.extend(chars.next()),
+                    c => out.push(c),
+                }
+            }
+            out
+        }
+        None => value.to_owned(),
+    }
+}
+
+fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = bytes.get(idx + 1..idx + 3)?;
+            let hex = std::str::from_utf8(hex).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            idx += 3;
+        } else {
+            out.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+
+    Some(out)
+}
+
+/// Decode an RFC 5987 extended value, `charset'language'percent-encoded-value`.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let bytes = percent_decode(parts.next()?)?;
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        #[cfg(feature = "charsets")]
+        {
+            let encoding = encoding_rs::Encoding::for_label(charset.as_bytes())?;
+            encoding
+                .decode_without_bom_handling_and_without_replacement(&bytes)
+                .map(|text| text.into_owned())
+        }
+        #[cfg(not(feature = "charsets"))]
+        None
+    }
+}
+
+/// Make a file name safe to join to a directory by keeping only its last path component, and
+/// replacing the `:` of drive prefixes and alternate data streams.
+fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| if c == ':' { '_' } else { c })
+        .collect();
+    let name = name.trim();
+
+    match name {
+        "" | "." | ".." => None,
+        name => Some(name.to_owned()),
+    }
+}
+
+/// Extract the file name from the value of a `Content-Disposition` header.
+///
+/// The extended `filename*` parameter takes precedence over `filename`.
+fn parse_content_disposition(value: &str) -> Option<String> {
+    sanitize_filename(&content_disposition_filename(value)?)
+}
+
+/// Extract the file name from the value of a `Content-Disposition` header, as sent.
+fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut filename_ext = None;
+
+    for param in split_params(value).into_iter().skip(1) {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => continue,
+        };
+
+        if name.eq_ignore_ascii_case("filename*") {
+            filename_ext = decode_ext_value(&unquote(value));
+        } else if name.eq_ignore_ascii_case("filename") {
+            filename = Some(unquote(value));
+        }
+    }
+
+    filename_ext.or(filename)
+}
+
+/// Directives of a `Cache-Control` response header.
+///
+/// Directives which are not represented are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    /// Value of the `max-age` directive.
+    pub max_age: Option<Duration>,
+    /// Whether the `no-store` directive is present.
+    pub no_store: bool,
+    /// Whether the `no-cache` directive is present.
+    pub no_cache: bool,
+    /// Whether the `public` directive is present.
+    pub public: bool,
+    /// Whether the `private` directive is present.
+    pub private: bool,
+}
+
+fn parse_cache_control(value: &str) -> CacheControl {
+    let mut cache_control = CacheControl::default();
+
+    for directive in split_unquoted(value, ',') {
+        match parse_param(directive) {
+            (name, Some(value)) if name == "max-age" => {
+                cache_control.max_age = value.parse().ok().map(Duration::from_secs);
+            }
+            (name, _) if name == "no-store" => cache_control.no_store = true,
+            (name, _) if name == "no-cache" => cache_control.no_cache = true,
+            (name, _) if name == "public" => cache_control.public = true,
+            (name, _) if name == "private" => cache_control.private = true,
+            _ => {}
+        }
+    }
+
+    cache_control
+}
+
+/// CORS permissions granted by the `Access-Control-Allow-*` headers of a response.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorsInfo {
+    /// Value of the `Access-Control-Allow-Origin` header, either an origin or `*`.
+    pub allow_origin: Option<String>,
+    /// Whether `Access-Control-Allow-Credentials` is `true`.
+    pub allow_credentials: bool,
+    /// Methods listed in `Access-Control-Allow-Methods`, as written.
+    pub allow_methods: Vec<String>,
+    /// Headers listed in `Access-Control-Allow-Headers`, in lowercase.
+    pub allow_headers: Vec<String>,
+    /// Headers listed in `Access-Control-Expose-Headers`, in lowercase.
+    pub expose_headers: Vec<String>,
+    /// Value of the `Access-Control-Max-Age` header.
+    pub max_age: Option<Duration>,
+}
+
+impl CorsInfo {
+    /// Check if requests from the given origin are allowed.
+    ///
+    /// The `*` wildcard does not allow requests with credentials, so it's ignored when
+    /// `allow_credentials` is true, like browsers do.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        match self.allow_origin.as_deref() {
+            Some("*") => !self.allow_credentials,
+            Some(allowed) => allowed == origin,
+            None => false,
+        }
+    }
+
+    /// Check if the given method is allowed.
+    ///
+    /// `GET`, `HEAD` and `POST` are always allowed. The `*` wildcard is ignored when
+    /// `allow_credentials` is true.
+    pub fn allows_method(&self, method: &Method) -> bool {
+        matches!(*method, Method::GET | Method::HEAD | Method::POST)
+            || self
+                .allow_methods
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(method.as_str()) || (m == "*" && !self.allow_credentials))
+    }
+
+    /// Check if the given request header is allowed.
+    ///
+    /// The `*` wildcard is ignored when `allow_credentials` is true and never covers `Authorization`,
+    /// which has to be listed explicitly.
+    pub fn allows_header(&self, name: &HeaderName) -> bool {
+        self.allow_headers
+            .iter()
+            .any(|h| h == name.as_str() || (h == "*" && !self.allow_credentials && *name != AUTHORIZATION))
+    }
+}
+
+/// Split a comma-separated list of tokens, skipping empty items.
+fn parse_token_list(value: Option<String>) -> Vec<String> {
+    value
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn lowercase_all(mut tokens: Vec<String>) -> Vec<String> {
+    tokens.iter_mut().for_each(|token| token.make_ascii_lowercase());
+    tokens
+}
+
+/// An authentication challenge of a `WWW-Authenticate` header, as defined by RFC 9110.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthChallenge {
+    /// Authentication scheme, such as `Basic` or `Bearer`, as written.
+    pub scheme: String,
+    /// Token following the scheme instead of parameters, if any.
+    pub token68: Option<String>,
+    /// Parameters of the challenge, with lowercase names and unquoted values.
+    pub params: Vec<(String, String)>,
+}
+
+impl AuthChallenge {
+    /// Get the value of the given parameter, ignoring case.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Get the `realm` parameter.
+    pub fn realm(&self) -> Option<&str> {
+        self.param("realm")
+    }
+}
+
+/// The authentication challenges of the `WWW-Authenticate` headers of a response.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WwwAuthenticate {
+    /// The challenges, in the order they were received.
+    pub challenges: Vec<AuthChallenge>,
+}
+
+impl WwwAuthenticate {
+    /// Parse the challenges of the `WWW-Authenticate` headers in the given headers, skipping
+    /// malformed items.
+    pub fn from_headers(headers: &HeaderMap) -> WwwAuthenticate {
+        let mut challenges: Vec<AuthChallenge> = Vec::new();
+
+        for value in headers.get_all(WWW_AUTHENTICATE) {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            for item in split_unquoted(&value, ',') {
+                let item = item.trim();
+                if item.is_empty() {
+                    continue;
+                }
+
+                // A new challenge starts with a scheme, which is a token not followed by `=`.
+                let (first, rest) = item.split_once(' ').unwrap_or((item, ""));
+                if first.contains('=') {
+                    if let (Some(challenge), (name, Some(value))) = (challenges.last_mut(), parse_param(item)) {
+                        challenge.params.push((name, value));
+                    }
+                    continue;
+                }
+
+                let mut challenge = AuthChallenge {
+                    scheme: first.to_owned(),
+                    token68: None,
+                    params: Vec::new(),
+                };
+                let rest = rest.trim();
+                if !rest.is_empty() {
+                    if rest.trim_end_matches('=').contains('=') {
+                        if let (name, Some(value)) = parse_param(rest) {
+                            challenge.params.push((name, value));
+                        }
+                    } else {
+                        challenge.token68 = Some(rest.to_owned());
+                    }
+                }
+                challenges.push(challenge);
+            }
+        }
+
+        WwwAuthenticate { challenges }
+    }
+
+    /// Get the first challenge using the given scheme, ignoring case.
+    pub fn challenge(&self, scheme: &str) -> Option<&AuthChallenge> {
+        self.challenges.iter().find(|c| c.scheme.eq_ignore_ascii_case(scheme))
+    }
+}
+
+/// A link of a `Link` response header, as defined by RFC 8288.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    /// Target of the link, as written in the header. Relative references are not resolved.
+    pub href: String,
+    /// Value of the `rel` parameter, which may contain several space separated relation types.
+    pub rel: Option<String>,
+    /// Other parameters of the link, with lowercase names and unquoted values.
+    pub params: Vec<(String, Option<String>)>,
+}
+
+impl Link {
+    /// Check if the given relation type is one of the relation types of this link, ignoring case.
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rel
+            .as_deref()
+            .is_some_and(|rels| rels.split_whitespace().any(|r| r.eq_ignore_ascii_case(rel)))
+    }
+}
+
+/// Parse the links of a `Link` header value, skipping malformed links.
+fn parse_links(value: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+
+    for link in split_unquoted(value, ',') {
+        let (href, params) = match link.trim().strip_prefix('<').and_then(|link| link.split_once('>')) {
+            Some(parts) => parts,
+            None => continue,
+        };
+
+        let mut rel = None;
+        let mut other_params = Vec::new();
+        for param in split_params(params).into_iter().skip(1) {
+            match parse_param(param) {
+                (name, _) if name.is_empty() => {}
+                // Only the first rel parameter is used.
+                (name, value) if name == "rel" => {
+                    if rel.is_none() {
+                        rel = value;
+                    }
+                }
+                param => other_params.push(param),
+            }
+        }
+
+        links.push(Link {
+            href: href.trim().to_owned(),
+            rel,
+            params: other_params,
+        });
+    }
+
+    links
+}
+
+/// A metric of a `Server-Timing` response header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerTimingMetric {
+    /// Name of the metric.
+    pub name: String,
+    /// Value of the `dur` parameter, a duration in milliseconds.
+    pub duration: Option<f64>,
+    /// Value of the `desc` parameter, unquoted.
+    pub description: Option<String>,
+}
+
+/// Parse the metrics of a `Server-Timing` header value, skipping the metrics without a name.
+fn parse_server_timing(value: &str) -> Vec<ServerTimingMetric> {
+    let mut metrics = Vec::new();
+
+    for metric in split_unquoted(value, ',') {
+        let params = split_params(metric);
+        let name = params[0].trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut duration = None;
+        let mut description = None;
+        // Only the first occurrence of a parameter is used.
+        let mut seen_duration = false;
+        for param in params.into_iter().skip(1) {
+            match parse_param(param) {
+                (name, value) if name == "dur" && !seen_duration => {
+                    seen_duration = true;
+                    duration = value.and_then(|v| v.parse().ok()).filter(|d: &f64| d.is_finite());
+                }
+                (name, value) if name == "desc" && description.is_none() => {
+                    description = Some(value.unwrap_or_default());
+                }
+                _ => {}
+            }
+        }
+
+        metrics.push(ServerTimingMetric {
+            name: name.to_owned(),
+            duration,
+            description,
+        });
+    }
+
+    metrics
+}
+
+fn part_path(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "destination path has no file name"))?
+        .to_os_string();
+    name.push(".part");
+    Ok(path.with_file_name(name))
+}
+
+/// Write to a temporary file next to `path` and move it into place only if `write` succeeds.
+fn save_atomic<F>(path: &Path, write: F) -> Result<u64>
+where
+    F: FnOnce(&mut File) -> Result<u64>,
+{
+    let part = part_path(path)?;
+
+    let res = File::create(&part).map_err(Into::into).and_then(|mut file| {
+        let n = write(&mut file)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&part, path)?;
+        Ok(n)
+    });
+
+    if res.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+
+    res
+}
+
+/// Make a relative path of a multipart part safe to join to a directory, by dropping the empty,
+/// `.` and `..` components, as well as the characters which are special on some platforms.
+#[cfg(feature = "multipart-form")]
+fn sanitize_relative_path(path: &str) -> Option<PathBuf> {
+    let mut sanitized = PathBuf::new();
+    for component in path.split(['/', '\\']) {
+        let component: String = component
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| if c == ':' { '_' } else { c })
+            .collect();
+        match component.trim() {
+            "" | "." | ".." => {}
+            component => sanitized.push(component),
+        }
+    }
+    if sanitized.as_os_str().is_empty() {
+        None
+    } else {
+        Some(sanitized)
+    }
+}
+
+impl Response {
+    /// Get the first value of the given header as a string.
+    ///
+    /// Returns `None` if the header is missing or if its value is not visible ASCII.
+    pub fn header_str<K: AsHeaderName>(&self, name: K) -> Option<&str> {
+        self.headers().get(name)?.to_str().ok()
+    }
+
+    /// Get all the values of the given header joined with `, `, which is equivalent to a single
+    /// header for headers defined as comma-separated lists.
+    ///
+    /// Values which are not visible ASCII are skipped. Returns `None` if there are no values.
+    pub fn header_all_joined<K: AsHeaderName>(&self, name: K) -> Option<String> {
+        let values: Vec<&str> = self
+            .headers()
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.join(", "))
+        }
+    }
+
+    /// Get the directives of the `Cache-Control` header of this `Response`.
+    ///
+    /// Returns `None` if the header is missing.
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        self.header_all_joined(CACHE_CONTROL)
+            .map(|value| parse_cache_control(&value))
+    }
+
+    /// Get the CORS permissions granted by the `Access-Control-Allow-*` headers of this `Response`,
+    /// typically the response to a preflight request sent with `RequestBuilder::cors_preflight`.
+    ///
+    /// Header lists spread over several headers are merged. An invalid `Access-Control-Max-Age`
+    /// is ignored.
+    pub fn cors_allowed(&self) -> CorsInfo {
+        CorsInfo {
+            allow_origin: self
+                .header_str(ACCESS_CONTROL_ALLOW_ORIGIN)
+                .map(|v| v.trim().to_owned()),
+            allow_credentials: self
+                .header_str(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .is_some_and(|v| v.trim() == "true"),
+            allow_methods: parse_token_list(self.header_all_joined(ACCESS_CONTROL_ALLOW_METHODS)),
+            allow_headers: lowercase_all(parse_token_list(self.header_all_joined(ACCESS_CONTROL_ALLOW_HEADERS))),
+            expose_headers: lowercase_all(parse_token_list(self.header_all_joined(ACCESS_CONTROL_EXPOSE_HEADERS))),
+            max_age: self
+                .header_str(ACCESS_CONTROL_MAX_AGE)
+                .and_then(|v| v.trim().parse().ok())
+                .map(Duration::from_secs),
+        }
+    }
+
+    /// Get the authentication challenges of the `WWW-Authenticate` headers of this `Response`.
+    ///
+    /// Malformed challenges are skipped.
+    pub fn www_authenticate(&self) -> WwwAuthenticate {
+        WwwAuthenticate::from_headers(self.headers())
+    }
+
+    /// Get the links of the `Link` headers of this `Response`, such as the pagination links
+    /// returned by many APIs.
+    ///
+    /// Malformed links are skipped.
+    pub fn links(&self) -> Vec<Link> {
+        self.headers()
+            .get_all(LINK)
+            .iter()
+            .flat_map(|value| parse_links(&String::from_utf8_lossy(value.as_bytes())))
+            .collect()
+    }
+
+    /// Get the metrics of the `Server-Timing` headers of this `Response`, which report the time
+    /// spent by the server on its side.
+    ///
+    /// Metrics without a name are skipped, and an invalid duration is ignored.
+    pub fn server_timing(&self) -> Vec<ServerTimingMetric> {
+        self.headers()
+            .get_all("server-timing")
+            .iter()
+            .flat_map(|value| parse_server_timing(&String::from_utf8_lossy(value.as_bytes())))
+            .collect()
+    }
+
+    /// Get the cookies set by the `Set-Cookie` headers of this `Response`, with their attributes.
+    ///
+    /// The response parser keeps each `Set-Cookie` header as a separate value, they are never
+    /// folded into a single one since cookie attributes like `Expires` contain commas. Malformed
+    /// cookies are skipped with a warning. No cookie jar is kept by this crate, the cookies can be
+    /// stored and sent again with the `Cookie` header as needed.
+    ///
+    /// This method only exists when the `cookies` feature is enabled.
+    #[cfg(feature = "cookies")]
+    pub fn cookies(&self) -> Vec<Cookie<'static>> {
+        self.headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                match Cookie::parse(value) {
+                    Ok(cookie) => Some(cookie),
+                    Err(err) => {
+                        warn!("ignoring an invalid Set-Cookie header from {}: {}", self.url(), err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Get the file name suggested by the `Content-Disposition` header of this `Response`.
+    ///
+    /// Both the `filename` and the extended `filename*` parameters are supported, the latter
+    /// taking precedence. Directory components are removed from the file name so that it can
+    /// be joined safely to a directory.
+    pub fn attachment_filename(&self) -> Option<String> {
+        let value = self.headers().get(CONTENT_DISPOSITION)?;
+        parse_content_disposition(&String::from_utf8_lossy(value.as_bytes()))
+    }
+
+    /// Write the response body to a file in the given directory.
+    ///
+    /// The file is named using `attachment_filename`, falling back to the last segment of the URL's
+    /// path. The file is written like in `save_to_file`. An error is returned if the file name
+    /// would still resolve outside of `dir`.
+    ///
+    /// Returns the path of the file.
+    pub fn save_to_dir(self, dir: impl AsRef<Path>) -> Result<PathBuf> {
+        let filename = self
+            .attachment_filename()
+            .or_else(|| {
+                let segment = self.url().path_segments()?.next_back()?;
+                let segment = percent_decode(segment)?;
+                sanitize_filename(&String::from_utf8_lossy(&segment))
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "could not determine file name"))?;
+
+        let dir = dir.as_ref();
+        let path = dir.join(&filename);
+        if Path::new(&filename).is_absolute() || path.parent() != Some(dir) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "file name escapes the directory").into());
+        }
+        self.save_to_file(&path)?;
+        Ok(path)
+    }
+
+    /// Write each part of a `multipart/*` response body to a file in the given directory.
+    ///
+    /// Files are named using the `filename` of the `Content-Disposition` header of the part, falling
+    /// back to its `name`, then to `part-N`. Directory components are kept, so that a body made with
+    /// `MultipartBuilder::with_dir` is unpacked to the same tree, but `.` and `..` components and
+    /// absolute paths are removed so that files are never written outside of `dir`. Missing
+    /// directories are created, and files are written like in `save_to_file`.
+    ///
+    /// The body is streamed, parts are never buffered in memory. An `UnexpectedContentType` error
+    /// is returned if the response is not a multipart response.
+    ///
+    /// Returns the paths of the files, in the order of the parts.
+    ///
+    /// This method only exists when the `multipart-form` feature is enabled.
+    #[cfg(feature = "multipart-form")]
+    pub fn unpack_multipart_to_dir(self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let content_type = self.header_str(CONTENT_TYPE).map(str::to_owned);
+        let boundary = content_type
+            .as_deref()
+            .filter(|value| {
+                let mime = value.split(';').next().unwrap_or("").trim();
+                mime.len() > 10 && mime[..10].eq_ignore_ascii_case("multipart/")
+            })
+            .ok_or_else(|| ErrorKind::UnexpectedContentType {
+                expected: vec!["multipart/*".to_owned()],
+                actual: content_type.clone(),
+            })?;
+        let boundary = split_params(boundary)
+            .into_iter()
+            .skip(1)
+            .map(parse_param)
+            .find(|(name, _)| name == "boundary")
+            .and_then(|(_, value)| value)
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or_else(|| ErrorKind::InvalidMultipartBoundary(content_type.clone().unwrap_or_default()))?;
+
+        let dir = dir.as_ref();
+        let mut reader = MultipartReader::new(self, &boundary);
+        let mut paths = Vec::new();
+        while let Some(headers) = reader.next_part()? {
+            let disposition = headers.content_disposition.unwrap_or_default();
+            let name = content_disposition_filename(&disposition)
+                .or_else(|| {
+                    split_params(&disposition)
+                        .into_iter()
+                        .skip(1)
+                        .map(parse_param)
+                        .find(|(name, _)| name == "name")
+                        .and_then(|(_, value)| value)
+                })
+                .and_then(|name| sanitize_relative_path(&name))
+                .unwrap_or_else(|| PathBuf::from(format!("part-{}", paths.len())));
+
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            save_atomic(&path, |file| Ok(io::copy(&mut reader, file)?))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Write the response body to the file at the given path.
+    ///
+    /// The body is first written to a temporary file in the same directory, named after the
+    /// destination with a `.part` suffix. It is moved into place once the whole body has been
+    /// written and synced to disk, so that an existing file is never replaced by a partial download.
+    /// The temporary file is removed if an error occurs.
+    ///
+    /// Returns the number of bytes written.
+    pub fn save_to_file(self, path: impl AsRef<Path>) -> Result<u64> {
+        save_atomic(path.as_ref(), |file| self.write_to(file))
+    }
+
+    /// Write the response body to the file at the given path, verifying its SHA-256 digest.
+    ///
+    /// This works like `save_to_file`, but the file is only moved into place if the digest of the body
+    /// matches `sha256_hex`. Otherwise a `ChecksumMismatch` error is returned.
+    ///
+    /// This method only exists when the `checksum` feature is enabled.
+    #[cfg(feature = "checksum")]
+    pub fn save_to_file_verified(self, path: impl AsRef<Path>, sha256_hex: &str) -> Result<u64> {
+        save_atomic(path.as_ref(), |file| {
+            let mut writer = HashWriter::new(file);
+            let n = self.write_to(&mut writer)?;
+
+            let actual = writer.hex_digest();
+            let expected = sha256_hex.trim();
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(ErrorKind::ChecksumMismatch {
+                    expected: expected.to_owned(),
+                    actual,
+                }
+                .into());
+            }
+
+            Ok(n)
+        })
+    }
+}
+
+#[test]
+fn test_part_path() {
+    assert_eq!(
+        part_path(Path::new("foo/bar.tar.gz")).unwrap(),
+        Path::new("foo/bar.tar.gz.part")
+    );
+    assert!(part_path(Path::new("/")).is_err());
+}
+
+#[test]
+fn test_content_disposition_plain() {
+    assert_eq!(
+        parse_content_disposition("attachment; filename=report.pdf").as_deref(),
+        Some("report.pdf")
+    );
+}
+
+#[test]
+fn test_content_disposition_quoted() {
+    assert_eq!(
+        parse_content_disposition(r#"attachment; filename="report; \"2024\".pdf""#).as_deref(),
+        Some(r#"report; "2024".pdf"#)
+    );
+}
+
+#[test]
+fn test_content_disposition_extended_utf8() {
+    assert_eq!(
+        parse_content_disposition(
+            "attachment; filename=\"fallback.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9%202024.pdf"
+        )
+        .as_deref(),
+        Some("résumé 2024.pdf")
+    );
+}
+
+#[test]
+fn test_content_disposition_extended_latin1() {
+    assert_eq!(
+        parse_content_disposition("attachment; filename*=iso-8859-1'fr'caf%E9.txt").as_deref(),
+        Some("café.txt")
+    );
+}
+
+#[test]
+fn test_content_disposition_malicious() {
+    assert_eq!(
+        parse_content_disposition("attachment; filename=\"../../etc/passwd\"").as_deref(),
+        Some("passwd")
+    );
+    assert_eq!(
+        parse_content_disposition("attachment; filename*=UTF-8''..%5C..%5Cwindows%5Cwin.ini").as_deref(),
+        Some("win.ini")
+    );
+    assert_eq!(
+        parse_content_disposition("attachment; filename=\"C:evil.exe\"").as_deref(),
+        Some("C_evil.exe")
+    );
+    assert_eq!(
+        parse_content_disposition("attachment; filename*=UTF-8''C%3A..%5Cevil.exe").as_deref(),
+        Some("evil.exe")
+    );
+    assert_eq!(
+        parse_content_disposition("attachment; filename=\"C:..\"").as_deref(),
+        Some("C_..")
+    );
+    assert_eq!(
+        parse_content_disposition("attachment; filename=\"..\"").as_deref(),
+        None
+    );
+}
+
+#[test]
+fn test_content_disposition_missing() {
+    assert_eq!(parse_content_disposition("inline").as_deref(), None);
+}
+
+#[test]
+fn test_cache_control() {
+    assert_eq!(
+        parse_cache_control("public, max-age=31536000, immutable"),
+        CacheControl {
+            max_age: Some(Duration::from_secs(31536000)),
+            public: true,
+            ..CacheControl::default()
+        }
+    );
+    assert_eq!(
+        parse_cache_control("no-cache, no-store, must-revalidate"),
+        CacheControl {
+            no_cache: true,
+            no_store: true,
+            ..CacheControl::default()
+        }
+    );
+}
+
+#[test]
+fn test_cache_control_lenient() {
+    assert_eq!(
+        parse_cache_control(r#"PRIVATE, no-cache="Set-Cookie, Vary", Max-Age="60", max-stale"#),
+        CacheControl {
+            max_age: Some(Duration::from_secs(60)),
+            no_cache: true,
+            private: true,
+            ..CacheControl::default()
+        }
+    );
+    assert_eq!(parse_cache_control("max-age=forever,,"), CacheControl::default());
+}
+
+#[test]
+fn test_links_github_pagination() {
+    let links = parse_links(
+        r#"<https://api.github.com/repositories/1300192/issues?page=2>; rel="prev", <https://api.github.com/repositories/1300192/issues?page=4>; rel="next", <https://api.github.com/repositories/1300192/issues?page=515>; rel="last", <https://api.github.com/repositories/1300192/issues?page=1>; rel="first""#,
+    );
+
+    let rels: Vec<_> = links.iter().map(|link| link.rel.as_deref().unwrap()).collect();
+    assert_eq!(rels, ["prev", "next", "last", "first"]);
+    assert_eq!(
+        links[1].href,
+        "https://api.github.com/repositories/1300192/issues?page=4"
+    );
+}
+
+#[test]
+fn test_links_params() {
+    let links = parse_links(
+        r#"</a,b;c>; rel="next alternate"; title="Page 2; \"draft\""; type=text/html; rel=ignored, </d>;anchor"#,
+    );
+
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].href, "/a,b;c");
+    assert!(links[0].has_rel("next"));
+    assert!(links[0].has_rel("Alternate"));
+    assert!(!links[0].has_rel("ignored"));
+    assert_eq!(
+        links[0].params,
+        [
+            ("title".to_owned(), Some(r#"Page 2; "draft""#.to_owned())),
+            ("type".to_owned(), Some("text/html".to_owned())),
+        ]
+    );
+    assert_eq!(links[1].href, "/d");
+    assert_eq!(links[1].rel, None);
+    assert_eq!(links[1].params, [("anchor".to_owned(), None)]);
+}
+
+#[test]
+fn test_links_malformed() {
+    let links = parse_links(r#"garbage, <https://example.com/>; rel=next, <unterminated; rel=last"#);
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].href, "https://example.com/");
+}
+
+#[cfg(test)]
+fn metric(name: &str, duration: Option<f64>, description: Option<&str>) -> ServerTimingMetric {
+    ServerTimingMetric {
+        name: name.to_owned(),
+        duration,
+        description: description.map(str::to_owned),
+    }
+}
+
+#[test]
+fn test_server_timing() {
+    assert_eq!(
+        parse_server_timing(r#"db;dur=12.3, cache;desc="hit", total;dur=40;desc=all"#),
+        [
+            metric("db", Some(12.3), None),
+            metric("cache", None, Some("hit")),
+            metric("total", Some(40.0), Some("all")),
+        ]
+    );
+}
+
+#[test]
+fn test_server_timing_grammar() {
+    assert_eq!(
+        parse_server_timing(r#"cache;desc="hit, then \"miss\"";dur=1"#),
+        [metric("cache", Some(1.0), Some(r#"hit, then "miss""#))]
+    );
+    assert_eq!(
+        parse_server_timing("db;DUR=2.5;Desc=query"),
+        [metric("db", Some(2.5), Some("query"))]
+    );
+    assert_eq!(
+        parse_server_timing("a;dur=1;dur=2;desc=x;desc=y, b;dur=oops;desc, c;dur=inf"),
+        [
+            metric("a", Some(1.0), Some("x")),
+            metric("b", None, Some("")),
+            metric("c", None, None),
+        ]
+    );
+    assert_eq!(parse_server_timing(" miss "), [metric("miss", None, None)]);
+    assert_eq!(parse_server_timing(""), []);
+    assert_eq!(parse_server_timing(" , ;dur=1, "), []);
+}
+
+#[test]
+fn test_response_header_helpers() {
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+    use crate::PreparedRequest;
+
+    let raw = b"HTTP/1.1 200 OK\r\n\
+        Vary: Accept\r\n\
+        Vary: Accept-Encoding\r\n\
+        Cache-Control: private\r\n\
+        Cache-Control: max-age=60\r\n\
+        Link: <https://example.com/?page=2>; rel=next\r\n\
+        Link: <https://example.com/?page=9>; rel=last\r\n\
+        Server-Timing: db;dur=12.3\r\n\
+        Server-Timing: \r\n\
+        Server-Timing: cache;desc=\"hit\"\r\n\
+        Content-Length: 0\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com");
+    let resp = parse_response(BaseStream::mock(raw.to_vec()), &req, req.url()).unwrap();
+
+    assert_eq!(resp.header_str("vary"), Some("Accept"));
+    assert_eq!(
+        resp.header_all_joined("vary").as_deref(),
+        Some("Accept, Accept-Encoding")
+    );
+    assert_eq!(resp.header_all_joined("etag"), None);
+    assert_eq!(
+        resp.cache_control(),
+        Some(CacheControl {
+            max_age: Some(Duration::from_secs(60)),
+            private: true,
+            ..CacheControl::default()
+        })
+    );
+
+    let links = resp.links();
+    assert_eq!(links.len(), 2);
+    assert!(links[0].has_rel("next"));
+    assert_eq!(links[1].href, "https://example.com/?page=9");
+
+    assert_eq!(
+        resp.server_timing(),
+        [metric("db", Some(12.3), None), metric("cache", None, Some("hit"))]
+    );
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn test_cookies() {
+    use cookie::SameSite;
+
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+    use crate::PreparedRequest;
+
+    let raw = b"HTTP/1.1 200 OK\r\n\
+        Set-Cookie: session=abc; Domain=example.com; Path=/app; Secure; HttpOnly; SameSite=Strict\r\n\
+        Set-Cookie: =missing-name\r\n\
+        Set-Cookie: theme=dark; Max-Age=3600\r\n\
+        Set-Cookie: id=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; SameSite=Lax\r\n\
+        Content-Length: 0\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com");
+    let resp = parse_response(BaseStream::mock(raw.to_vec()), &req, req.url()).unwrap();
+
+    // The headers are kept separate, even though their values contain commas.
+    assert_eq!(resp.headers().get_all("set-cookie").iter().count(), 4);
+
+    let cookies = resp.cookies();
+    assert_eq!(cookies.len(), 3);
+
+    assert_eq!(cookies[0].name_value(), ("session", "abc"));
+    assert_eq!(cookies[0].domain(), Some("example.com"));
+    assert_eq!(cookies[0].path(), Some("/app"));
+    assert_eq!(cookies[0].secure(), Some(true));
+    assert_eq!(cookies[0].http_only(), Some(true));
+    assert_eq!(cookies[0].same_site(), Some(SameSite::Strict));
+
+    assert_eq!(cookies[1].name_value(), ("theme", "dark"));
+    assert_eq!(cookies[1].max_age(), Some(cookie::time::Duration::hours(1)));
+    assert_eq!(cookies[1].secure(), None);
+
+    assert_eq!(cookies[2].name_value(), ("id", "1"));
+    assert_eq!(
+        cookies[2].expires_datetime().map(|t| t.unix_timestamp()),
+        Some(1_445_412_480)
+    );
+    assert_eq!(cookies[2].same_site(), Some(SameSite::Lax));
+}
+
+#[test]
+fn test_cors_allowed() {
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+    use crate::PreparedRequest;
+
+    let raw = b"HTTP/1.1 204 No Content\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Access-Control-Allow-Methods: GET, PUT,\r\n\
+        Access-Control-Allow-Methods: *\r\n\
+        Access-Control-Allow-Headers: X-Token, *\r\n\
+        Access-Control-Expose-Headers: ETag\r\n\
+        Access-Control-Max-Age: 600\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::OPTIONS, "http://example.com");
+    let resp = parse_response(BaseStream::mock(raw.to_vec()), &req, req.url()).unwrap();
+
+    let mut cors = resp.cors_allowed();
+    assert_eq!(cors.allow_origin.as_deref(), Some("*"));
+    assert!(!cors.allow_credentials);
+    assert_eq!(cors.allow_methods, ["GET", "PUT", "*"]);
+    assert_eq!(cors.allow_headers, ["x-token", "*"]);
+    assert_eq!(cors.expose_headers, ["etag"]);
+    assert_eq!(cors.max_age, Some(Duration::from_secs(600)));
+
+    assert!(cors.allows_origin("https://example.org"));
+    assert!(cors.allows_method(&Method::DELETE));
+    assert!(cors.allows_header(&HeaderName::from_static("x-other")));
+    assert!(!cors.allows_header(&AUTHORIZATION));
+
+    // The wildcards do not apply to requests with credentials.
+    cors.allow_credentials = true;
+    assert!(!cors.allows_origin("https://example.org"));
+    assert!(!cors.allows_method(&Method::DELETE));
+    assert!(cors.allows_method(&Method::PUT));
+    assert!(cors.allows_method(&Method::POST));
+    assert!(!cors.allows_header(&HeaderName::from_static("x-other")));
+    assert!(cors.allows_header(&HeaderName::from_static("x-token")));
+}
+
+#[test]
+fn test_cors_allowed_missing() {
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+    use crate::PreparedRequest;
+
+    let raw = b"HTTP/1.1 200 OK\r\nAccess-Control-Max-Age: -1\r\nContent-Length: 0\r\n\r\n";
+    let req = PreparedRequest::new(http::Method::OPTIONS, "http://example.com");
+    let resp = parse_response(BaseStream::mock(raw.to_vec()), &req, req.url()).unwrap();
+
+    let cors = resp.cors_allowed();
+    assert_eq!(cors, CorsInfo::default());
+    assert!(!cors.allows_origin("https://example.org"));
+    assert!(cors.allows_method(&Method::GET));
+    assert!(!cors.allows_method(&Method::PUT));
+}
+
+#[test]
+fn test_www_authenticate() {
+    let mut headers = HeaderMap::new();
+    headers.append(
+        WWW_AUTHENTICATE,
+        http::HeaderValue::from_static(
+            r#"Newauth realm="apps", type=1, title="Login to \"apps\"", Basic realm="simple""#,
+        ),
+    );
+    headers.append(
+        WWW_AUTHENTICATE,
+        http::HeaderValue::from_static(r#"Bearer realm="api", error="invalid_token", Negotiate abc123=="#),
+    );
+
+    let auth = WwwAuthenticate::from_headers(&headers);
+    let schemes: Vec<&str> = auth.challenges.iter().map(|c| c.scheme.as_str()).collect();
+    assert_eq!(schemes, ["Newauth", "Basic", "Bearer", "Negotiate"]);
+
+    let newauth = auth.challenge("newauth").unwrap();
+    assert_eq!(newauth.realm(), Some("apps"));
+    assert_eq!(newauth.param("TYPE"), Some("1"));
+    assert_eq!(newauth.param("title"), Some(r#"Login to "apps""#));
+    assert_eq!(auth.challenge("basic").unwrap().realm(), Some("simple"));
+    assert_eq!(auth.challenge("bearer").unwrap().param("error"), Some("invalid_token"));
+
+    let negotiate = auth.challenge("Negotiate").unwrap();
+    assert_eq!(negotiate.token68.as_deref(), Some("abc123=="));
+    assert!(negotiate.params.is_empty());
+
+    assert_eq!(
+        WwwAuthenticate::from_headers(&HeaderMap::new()),
+        WwwAuthenticate::default()
+    );
+}
+
+#[test]
+#[cfg(feature = "multipart-form")]
+fn test_sanitize_relative_path() {
+    assert_eq!(
+        sanitize_relative_path("a/b.txt"),
+        Some(PathBuf::from("a").join("b.txt"))
+    );
+    assert_eq!(
+        sanitize_relative_path("../../evil.txt"),
+        Some(PathBuf::from("evil.txt"))
+    );
+    assert_eq!(
+        sanitize_relative_path("/etc/passwd"),
+        Some(PathBuf::from("etc").join("passwd"))
+    );
+    assert_eq!(
+        sanitize_relative_path("..\\dir\\.\\C:file\x07"),
+        Some(PathBuf::from("dir").join("C_file"))
+    );
+    assert_eq!(sanitize_relative_path("../.."), None);
+    assert_eq!(sanitize_relative_path(""), None);
+}
+
+#[cfg(all(test, feature = "multipart-form"))]
+fn multipart_response(content_type: &str, body: &str) -> Response {
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+    use crate::PreparedRequest;
+
+    let raw = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    );
+    let req = PreparedRequest::new(http::Method::GET, "http://example.com");
+    parse_response(BaseStream::mock(raw.into_bytes()), &req, req.url()).unwrap()
+}
+
+#[test]
+#[cfg(feature = "multipart-form")]
+fn test_unpack_multipart_traversal() {
+    let dir = std::env::temp_dir().join(format!("attohttpc-unpack-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let body = "--b\r\n\
+        Content-Disposition: form-data; name=\"x\"; filename=\"../../evil.txt\"\r\n\r\n\
+        evil\r\n\
+        --b\r\n\
+        Content-Disposition: form-data; name=\"y\"; filename=\"/etc/passwd\"\r\n\r\n\
+        root\r\n\
+        --b\r\n\
+        Content-Disposition: form-data; name=\"sub/field\"\r\n\r\n\
+        text\r\n\
+        --b\r\n\
+        Content-Disposition: form-data; filename=\"..\"\r\n\r\n\
+        unnamed\r\n\
+        --b--\r\n";
+    let paths = multipart_response("multipart/form-data; boundary=b", body)
+        .unpack_multipart_to_dir(&dir)
+        .unwrap();
+
+    assert_eq!(
+        paths,
+        vec![
+            dir.join("evil.txt"),
+            dir.join("etc").join("passwd"),
+            dir.join("sub").join("field"),
+            dir.join("part-3"),
+        ]
+    );
+    assert_eq!(fs::read_to_string(dir.join("evil.txt")).unwrap(), "evil");
+    assert_eq!(fs::read_to_string(dir.join("etc").join("passwd")).unwrap(), "root");
+    assert_eq!(fs::read_to_string(dir.join("sub").join("field")).unwrap(), "text");
+    assert_eq!(fs::read_to_string(dir.join("part-3")).unwrap(), "unnamed");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(feature = "multipart-form")]
+fn test_unpack_multipart_errors() {
+    let dir = std::env::temp_dir().join(format!("attohttpc-unpack-errors-{}", std::process::id()));
+
+    let err = multipart_response("text/plain", "hello")
+        .unpack_multipart_to_dir(&dir)
+        .unwrap_err();
+    assert!(
+        matches!(err.kind(), ErrorKind::UnexpectedContentType { actual: Some(actual), .. } if actual == "text/plain")
+    );
+
+    let err = multipart_response("multipart/mixed", "")
+        .unpack_multipart_to_dir(&dir)
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidMultipartBoundary(_)));
+
+    assert!(!dir.exists());
+}
@@ -1,14 +1,24 @@
 #[cfg(any(feature = "charsets", feature = "json"))]
 use std::io::BufReader;
-use std::io::{self, Read, Write};
+use std::io::{self, BufRead, Read, Write};
+use std::mem;
+use std::time::{Duration, Instant};
 
 use http::header::HeaderMap;
+#[cfg(any(feature = "charsets", feature = "json"))]
+use http::header::CONTENT_TYPE;
 #[cfg(feature = "json")]
 use serde::de::DeserializeOwned;
 
-use crate::error::Result;
+use crate::cache::CacheCapture;
+use crate::error::{ErrorKind, Result};
+#[cfg(feature = "har")]
+use crate::har::BodyCapture;
+use crate::parsing::body_reader::BodyReader;
 use crate::parsing::compressed_reader::CompressedReader;
+use crate::parsing::TeeReader;
 use crate::request::PreparedRequest;
+use crate::streams::BaseStream;
 
 #[cfg(feature = "charsets")]
 use {
@@ -17,8 +27,7 @@ use {
         parsing::buffers::trim_byte,
         parsing::TextReader,
     },
-    encoding_rs::Encoding,
-    http::header::CONTENT_TYPE,
+    encoding_rs::{DecoderResult, Encoding},
 };
 
 #[cfg(feature = "charsets")]
@@ -37,6 +46,105 @@ fn get_charset(headers: &HeaderMap, default_charset: Option<Charset>) -> Charset
     default_charset.unwrap_or(charsets::WINDOWS_1252)
 }
 
+#[cfg(not(feature = "charsets"))]
+fn decode_utf8_strict(buf: Vec<u8>) -> Result<String> {
+    String::from_utf8(buf).map_err(|err| {
+        ErrorKind::InvalidTextEncoding {
+            valid_up_to: err.utf8_error().valid_up_to(),
+        }
+        .into()
+    })
+}
+
+#[cfg(feature = "charsets")]
+fn decode_strict(charset: Charset, buf: &[u8]) -> Result<String> {
+    let mut decoder = charset.new_decoder();
+    let mut text = String::new();
+    let mut read = 0;
+
+    loop {
+        let additional = decoder
+            .max_utf8_buffer_length_without_replacement(buf.len() - read)
+            .unwrap_or(buf.len() - read);
+        text.reserve(additional);
+
+        let (res, n) = decoder.decode_to_string_without_replacement(&buf[read..], &mut text, true);
+        read += n;
+
+        match res {
+            DecoderResult::InputEmpty => return Ok(text),
+            DecoderResult::OutputFull => continue,
+            DecoderResult::Malformed(bad, extra) => {
+                return Err(ErrorKind::InvalidTextEncoding {
+                    valid_up_to: read - usize::from(bad) - usize::from(extra),
+                }
+                .into());
+            }
+        }
+    }
+}
+
+/// Keeps the first bytes written to it, up to a limit, and drops the rest.
+#[cfg(feature = "json")]
+struct Capture {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+#[cfg(feature = "json")]
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.limit - self.data.len());
+        self.data.extend_from_slice(&buf[..n]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Number of bytes shown on each side of the position of a JSON error.
+#[cfg(feature = "json")]
+const JSON_SNIPPET_CONTEXT: usize = 128;
+
+/// Create the error returned when the JSON in `body` cannot be parsed, with the part of the body
+/// around the error. The start of the body is used if the error is not in `body`.
+#[cfg(feature = "json")]
+fn json_decode_error(source: serde_json::Error, body: &[u8], content_type: Option<String>) -> crate::Error {
+    let line_start = match source.line() {
+        0 | 1 => Some(0),
+        line => body
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .nth(line - 2)
+            .map(|(i, _)| i + 1),
+    };
+    // The column is the position in bytes of the error on its line, starting at 1.
+    let offset = match line_start {
+        Some(start) if start + source.column() <= body.len() => start + source.column().saturating_sub(1),
+        _ => 0,
+    };
+    let start = offset.saturating_sub(JSON_SNIPPET_CONTEXT);
+    let end = body.len().min(offset + JSON_SNIPPET_CONTEXT);
+    ErrorKind::JsonDecode {
+        source,
+        snippet: String::from_utf8_lossy(&body[start..end]).into_owned(),
+        content_type,
+    }
+    .into()
+}
+
+/// Maximum number of bytes reserved up front when reading a body of known length.
+const MAX_PREALLOCATION: usize = 16 * 1024 * 1024;
+
+/// Maximum number of bytes dropped by `discard`.
+const DEFAULT_MAX_DISCARD: u64 = 64 * 1024 * 1024;
+
+/// Maximum time spent draining the body when the reader is dropped.
+const MAX_DRAIN_TIME: Duration = Duration::from_secs(1);
+
 /// The `ResponseReader` is used to read the body of a response.
 ///
 /// The `ResponseReader` implements `Read` and can be used like any other stream,
@@ -52,24 +160,118 @@ pub struct ResponseReader {
     inner: CompressedReader,
     #[cfg(feature = "charsets")]
     charset: Charset,
+    compressed_len: Option<u64>,
+    decompressed_len: Option<u64>,
+    decompressed_read: u64,
+    tolerate_truncated_body: bool,
+    truncated: bool,
+    /// Grace period used once some of a body delimited by the end of the connection was read.
+    close_read_grace: Option<Duration>,
+    grace_started: bool,
+    /// Maximum number of bytes and time spent draining the body when the reader is dropped.
+    drain_on_drop: Option<(u64, Duration)>,
+    #[cfg(feature = "json")]
+    content_type: Option<String>,
+    #[cfg(feature = "json")]
+    json_error_capture: usize,
+    #[cfg(feature = "json")]
+    reject_json_trailing_data: bool,
+    #[cfg(feature = "har")]
+    har_capture: Option<BodyCapture>,
+    cache_capture: Option<CacheCapture>,
+}
+
+/// Parse the `X-Uncompressed-Content-Length` header sent by some servers along compressed bodies.
+fn uncompressed_content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("x-uncompressed-content-length")
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.trim().parse().ok())
 }
 
 impl ResponseReader {
-    #[cfg(feature = "charsets")]
     pub(crate) fn new<B>(
         headers: &HeaderMap,
         request: &PreparedRequest<B>,
         reader: CompressedReader,
+        compressed_len: Option<u64>,
     ) -> ResponseReader {
+        let decompressed_len = if reader.is_decoding() {
+            uncompressed_content_length(headers)
+        } else {
+            compressed_len
+        };
+
+        let close_read_grace = match reader.body_reader() {
+            BodyReader::Close(_) => request.base_settings.close_read_grace,
+            _ => None,
+        };
+
         ResponseReader {
             inner: reader,
+            #[cfg(feature = "charsets")]
             charset: get_charset(headers, request.base_settings.default_charset),
+            compressed_len,
+            decompressed_len,
+            decompressed_read: 0,
+            tolerate_truncated_body: request.base_settings.tolerate_truncated_body,
+            truncated: false,
+            close_read_grace,
+            grace_started: false,
+            drain_on_drop: request
+                .base_settings
+                .drain_on_drop
+                .map(|max| (max, request.base_settings.read_timeout.min(MAX_DRAIN_TIME))),
+            #[cfg(feature = "json")]
+            content_type: headers
+                .get(CONTENT_TYPE)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned()),
+            #[cfg(feature = "json")]
+            json_error_capture: request.base_settings.json_error_capture,
+            #[cfg(feature = "json")]
+            reject_json_trailing_data: request.base_settings.reject_json_trailing_data,
+            #[cfg(feature = "har")]
+            har_capture: None,
+            cache_capture: None,
         }
     }
 
-    #[cfg(not(feature = "charsets"))]
-    pub(crate) fn new<B>(_: &HeaderMap, _: &PreparedRequest<B>, reader: CompressedReader) -> ResponseReader {
-        ResponseReader { inner: reader }
+    /// Take the connection of a response without a body, along with the bytes already received
+    /// after its head. The reader is left with a detached stream.
+    pub(crate) fn take_connection(&mut self) -> (Vec<u8>, BaseStream) {
+        self.drain_on_drop = None;
+        let reader = self.inner.body_reader_mut().buf_reader_mut();
+        let buffered = reader.buffer().to_vec();
+        reader.consume(buffered.len());
+        (buffered, mem::replace(reader.get_mut(), BaseStream::Detached))
+    }
+
+    #[cfg(feature = "har")]
+    pub(crate) fn set_har_capture(&mut self, capture: BodyCapture) {
+        self.har_capture = Some(capture);
+    }
+
+    pub(crate) fn set_cache_capture(&mut self, capture: CacheCapture) {
+        self.cache_capture = Some(capture);
+    }
+
+    /// Check if the body is decompressed while it is read.
+    pub(crate) fn is_decoding(&self) -> bool {
+        self.inner.is_decoding()
+    }
+
+    /// Get the estimated size of the response body, as a `(compressed, decompressed)` pair.
+    ///
+    /// The compressed size is the size of the body as it is received, taken from the
+    /// `Content-Length` header. It is unknown when the body uses the chunked encoding or ends with
+    /// the connection.
+    ///
+    /// The decompressed size is the size of the body returned by `Read`. It is the same as the
+    /// compressed size when the body is not compressed. Otherwise it is taken from the
+    /// `X-Uncompressed-Content-Length` header if the server sent it. In every case, it becomes
+    /// known once the end of the body is reached through `Read`.
+    pub fn size_hint(&self) -> (Option<u64>, Option<u64>) {
+        (self.compressed_len, self.decompressed_len)
     }
 
     /// Write the response to any object that implements `Write`.
@@ -77,17 +279,107 @@ impl ResponseReader {
     where
         W: Write,
     {
-        let n = io::copy(&mut self.inner, &mut writer)?;
+        let n = io::copy(&mut self, &mut writer)?;
         Ok(n)
     }
 
+    /// Create a `TeeReader` which writes the data read from this `ResponseReader` to `secondary`.
+    ///
+    /// This can be used to hash the body while writing it somewhere else.
+    pub fn tee<W>(self, secondary: W) -> TeeReader<ResponseReader, W>
+    where
+        W: Write,
+    {
+        TeeReader::new(self, secondary)
+    }
+
+    /// Number of bytes to reserve before reading the body, based on the `Content-Length` header
+    /// but capped to avoid trusting a hostile header.
+    fn capacity_hint(&self) -> usize {
+        self.inner
+            .remaining_len()
+            .map_or(0, |len| len.min(MAX_PREALLOCATION as u64) as usize)
+    }
+
     /// Read the response to a `Vec` of bytes.
     pub fn bytes(self) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
-        self.write_to(&mut buf)?;
+        self.read_into(&mut buf)?;
         Ok(buf)
     }
 
+    /// Read the response to a `bytes::Bytes` buffer.
+    ///
+    /// This method only exists when the `bytes` feature is enabled.
+    #[cfg(feature = "bytes")]
+    pub fn bytes_buf(self) -> Result<bytes::Bytes> {
+        Ok(self.bytes()?.into())
+    }
+
+    /// Read the response, appending it to the given `Vec` of bytes.
+    ///
+    /// Returns the number of bytes appended.
+    pub fn read_into(mut self, buf: &mut Vec<u8>) -> Result<u64> {
+        buf.reserve(self.capacity_hint());
+        let n = self.read_to_end(buf)?;
+        Ok(n as u64)
+    }
+
+    /// Read the rest of the response body and drop it, returning the number of bytes dropped.
+    ///
+    /// The body is not decompressed, the number of bytes is the number of bytes received. At most
+    /// 64 MiB are read, use `discard_up_to` to choose another limit.
+    pub fn discard(self) -> Result<u64> {
+        self.discard_up_to(DEFAULT_MAX_DISCARD)
+    }
+
+    /// Read at most `max` bytes of the rest of the response body and drop them, returning the
+    /// number of bytes dropped.
+    ///
+    /// The body is not decompressed, the number of bytes is the number of bytes received. If the
+    /// body is longer than `max`, the rest of the body is left unread.
+    pub fn discard_up_to(mut self, max: u64) -> Result<u64> {
+        // The limit given here replaces the one of `drain_on_drop`.
+        self.drain_on_drop = None;
+        let n = self.inner.body_reader_mut().discard(max)?;
+        Ok(n)
+    }
+
+    /// Drain the rest of the body, up to the limits set by `drain_on_drop`.
+    fn drain(&mut self, max: u64, time: Duration) -> io::Result<u64> {
+        let deadline = Instant::now() + time;
+        let reader = self.inner.body_reader_mut();
+        let mut drained = 0;
+        while drained < max && reader.has_remaining() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            reader.get_ref().set_read_timeout(Some(remaining))?;
+            // Draining in small steps lets the deadline be checked between reads.
+            let n = reader.discard((max - drained).min(64 * 1024))?;
+            if n == 0 {
+                break;
+            }
+            drained += n;
+        }
+        Ok(drained)
+    }
+
+    /// Check if the end of the response body was missing and ignored because
+    /// `tolerate_truncated_body` is enabled.
+    pub fn was_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Check if there might be data remaining in the response body.
+    ///
+    /// This returns `false` once the end of a body with a `Content-Length` or using the chunked
+    /// encoding is reached. When the body ends with the connection, this always returns `true`.
+    pub fn has_body_remaining(&self) -> bool {
+        self.inner.body_reader().has_remaining()
+    }
+
     /// Read the response to a `String`.
     ///
     /// If the `charsets` feature is enabled, it will try to decode the response using
@@ -136,6 +428,42 @@ impl ResponseReader {
         Ok(text)
     }
 
+    /// Read the response to a `String`, returning an error if the body contains invalid data.
+    ///
+    /// The charset is selected like in `text`, but decoding is strict: instead of inserting
+    /// replacement characters, an `InvalidTextEncoding` error is returned when the first invalid
+    /// sequence is found.
+    #[cfg(not(feature = "charsets"))]
+    pub fn text_strict(mut self) -> Result<String> {
+        let mut buf = Vec::new();
+        self.read_to_end(&mut buf)?;
+        decode_utf8_strict(buf)
+    }
+
+    /// Read the response to a `String`, returning an error if the body contains invalid data.
+    ///
+    /// The charset is selected like in `text`, but decoding is strict: instead of inserting
+    /// replacement characters, an `InvalidTextEncoding` error is returned when the first invalid
+    /// sequence is found.
+    #[cfg(feature = "charsets")]
+    pub fn text_strict(self) -> Result<String> {
+        let charset = self.charset;
+        self.text_with_strict(charset)
+    }
+
+    /// Read the response to a `String`, strictly decoding with the given `Charset`.
+    ///
+    /// This will ignore the encoding from the response headers and the default encoding, if any.
+    /// An `InvalidTextEncoding` error is returned when the first invalid sequence is found.
+    ///
+    /// This method only exists when the `charsets` feature is enabled.
+    #[cfg(feature = "charsets")]
+    pub fn text_with_strict(mut self, charset: Charset) -> Result<String> {
+        let mut buf = Vec::new();
+        self.read_to_end(&mut buf)?;
+        decode_strict(charset, &buf)
+    }
+
     /// Create a `TextReader` from this `ResponseReader`.
     ///
     /// If the response headers contain charset information, that charset will be used to decode the body.
@@ -159,6 +487,16 @@ impl ResponseReader {
         TextReader::new(BufReader::new(self), charset)
     }
 
+    /// Iterate over the lines of the response body, decoded like in `text_reader`.
+    ///
+    /// Lines can end with `\n` or `\r\n`, the line ending is not included in the returned strings.
+    ///
+    /// This method only exists when the `charsets` feature is enabled.
+    #[cfg(feature = "charsets")]
+    pub fn lines(self) -> io::Lines<TextReader<BufReader<ResponseReader>>> {
+        self.text_reader().lines()
+    }
+
     /// Read the response body to a String using the UTF-8 encoding.
     ///
     /// This method ignores headers and the default encoding.
@@ -167,7 +505,7 @@ impl ResponseReader {
     /// invalid data is encountered but output replacement characters instead.
     pub fn text_utf8(mut self) -> Result<String> {
         let mut buf = Vec::new();
-        self.inner.read_to_end(&mut buf)?;
+        self.read_to_end(&mut buf)?;
 
         let text = String::from_utf8(buf).unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
 
@@ -185,13 +523,14 @@ impl ResponseReader {
     /// `json_utf8`.
     #[cfg(feature = "json")]
     #[cfg(feature = "charsets")]
-    pub fn json<T>(self) -> Result<T>
+    pub fn json<T>(mut self) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let reader = BufReader::new(self.text_reader());
-        let obj = serde_json::from_reader(reader)?;
-        Ok(obj)
+        let content_type = self.content_type.take();
+        let limit = self.json_error_capture;
+        let reject_trailing = self.reject_json_trailing_data;
+        parse_json(self.text_reader(), content_type, limit, reject_trailing)
     }
 
     /// Parse the response as a JSON object and return it.
@@ -212,38 +551,406 @@ impl ResponseReader {
         self.json_utf8()
     }
 
+    /// Parse the response as a JSON object, decoding the body strictly.
+    ///
+    /// The body is decoded like in `text_strict`, so an `InvalidTextEncoding` error is returned
+    /// if it contains invalid data instead of parsing replacement characters.
+    #[cfg(feature = "json")]
+    pub fn json_strict<T>(self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let content_type = self.content_type.clone();
+        let reject_trailing = self.reject_json_trailing_data;
+        let text = self.text_strict()?;
+        parse_json(text.as_bytes(), content_type, text.len(), reject_trailing)
+    }
+
     /// Parse the response as a JSON object encoded in UTF-8.
     ///
     /// This method ignores headers and the default encoding.
     ///
     /// This method only exists when the `json` feature is enabled.
     #[cfg(feature = "json")]
-    pub fn json_utf8<T>(self) -> Result<T>
+    pub fn json_utf8<T>(mut self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let content_type = self.content_type.take();
+        let limit = self.json_error_capture;
+        let reject_trailing = self.reject_json_trailing_data;
+        parse_json(self, content_type, limit, reject_trailing)
+    }
+
+    /// Parse the response as a JSON object like `json`, returning a `TrailingData` error if the
+    /// JSON value is followed by anything but whitespace.
+    ///
+    /// The rest of the body is read to count the trailing bytes, after it is decompressed and
+    /// decoded like in `json`. This is the same as `json` when `reject_json_trailing_data` is
+    /// enabled.
+    #[cfg(feature = "json")]
+    pub fn json_strict_eof<T>(mut self) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let reader = BufReader::new(self);
-        let obj = serde_json::from_reader(reader)?;
-        Ok(obj)
+        self.reject_json_trailing_data = true;
+        self.json()
     }
 }
 
+/// Parse JSON from the reader, keeping up to `limit` bytes of it to describe the error. When
+/// `reject_trailing` is true, data following the JSON value results in a `TrailingData` error.
+#[cfg(feature = "json")]
+fn parse_json<R, T>(reader: R, content_type: Option<String>, limit: usize, reject_trailing: bool) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let capture = Capture {
+        data: Vec::new(),
+        limit,
+    };
+    let mut reader = BufReader::new(TeeReader::new(reader, capture));
+    let mut de = serde_json::Deserializer::from_reader(&mut reader);
+    let err = match T::deserialize(&mut de) {
+        Ok(value) => match de.end() {
+            Ok(()) => return Ok(value),
+            // The only syntax error of `end` is the presence of trailing data.
+            Err(err) if reject_trailing && !err.is_io() => {
+                // The deserializer keeps the first trailing byte, which it looked at.
+                let rest = io::copy(&mut reader, &mut io::sink())?;
+                return Err(ErrorKind::TrailingData {
+                    bytes: rest as usize + 1,
+                }
+                .into());
+            }
+            Err(err) => err,
+        },
+        Err(err) => err,
+    };
+
+    // Read what follows the error to show it in the snippet.
+    let _ = (&mut reader)
+        .take(JSON_SNIPPET_CONTEXT as u64)
+        .read_to_end(&mut Vec::new());
+    let (_, capture) = reader.into_inner().into_parts();
+    Err(json_decode_error(err, &capture.data, content_type))
+}
+
 impl Read for ResponseReader {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+        let n = match self.inner.read(buf) {
+            Err(err)
+                if err.kind() == io::ErrorKind::UnexpectedEof
+                    && self.tolerate_truncated_body
+                    && self.decompressed_read > 0 =>
+            {
+                warn!("response body is truncated: {}", err);
+                self.truncated = true;
+                0
+            }
+            Err(err)
+                if matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) && self.grace_started =>
+            {
+                debug!("no data received during the grace period, ending the response body");
+                0
+            }
+            res => res?,
+        };
+        if n > 0 && !self.grace_started {
+            if let (Some(grace), BodyReader::Close(reader)) = (self.close_read_grace, self.inner.body_reader()) {
+                reader.get_ref().set_read_timeout(Some(grace))?;
+                self.grace_started = true;
+            }
+        }
+        self.decompressed_read += n as u64;
+        if n == 0 && !buf.is_empty() {
+            self.decompressed_len = Some(self.decompressed_read);
+        }
+        #[cfg(feature = "har")]
+        if let Some(capture) = &mut self.har_capture {
+            if n > 0 || !buf.is_empty() {
+                capture.on_read(&buf[..n]);
+            }
+        }
+        // A truncated body is not cached.
+        if self.truncated {
+            self.cache_capture = None;
+        }
+        if let Some(capture) = &mut self.cache_capture {
+            if n > 0 || !buf.is_empty() {
+                capture.on_read(&buf[..n]);
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for ResponseReader {
+    fn drop(&mut self) {
+        if let Some((max, time)) = self.drain_on_drop {
+            if self.inner.body_reader().has_remaining() {
+                match self.drain(max, time) {
+                    Ok(n) => debug!("drained {} bytes of the response body", n),
+                    Err(err) => debug!("could not drain the response body: {}", err),
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
-#[cfg(feature = "charsets")]
 mod tests {
+    #[cfg(feature = "charsets")]
     use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+    use http::Method;
 
+    #[cfg(feature = "charsets")]
     use super::get_charset;
+    #[cfg(feature = "charsets")]
     use crate::charsets;
+    use crate::parsing::response::parse_response;
+    use crate::streams::BaseStream;
+    use crate::{ErrorKind, PreparedRequest, ResponseReader};
+
+    fn reader_for(content_type: &str, body: &[u8]) -> ResponseReader {
+        let mut buf = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            content_type,
+            body.len()
+        )
+        .into_bytes();
+        buf.extend(body);
+
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+        let (_, _, reader) = parse_response(BaseStream::mock(buf), &req, req.url()).unwrap().split();
+        reader
+    }
+
+    fn reader_for_raw(response: &[u8]) -> ResponseReader {
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+        let (_, _, reader) = parse_response(BaseStream::mock(response.to_vec()), &req, req.url())
+            .unwrap()
+            .split();
+        reader
+    }
+
+    #[test]
+    fn test_bytes_exact_length() {
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        assert_eq!(reader.capacity_hint(), 5);
+        assert_eq!(reader.bytes().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_bytes_chunked() {
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n");
+        assert_eq!(reader.capacity_hint(), 0);
+        assert_eq!(reader.bytes().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_bytes_lying_content_length() {
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000000\r\n\r\nhello");
+        assert_eq!(reader.capacity_hint(), super::MAX_PREALLOCATION);
+        assert_eq!(reader.bytes().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_read_into_appends() {
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        let mut buf = b"say ".to_vec();
+        assert_eq!(reader.read_into(&mut buf).unwrap(), 5);
+        assert_eq!(buf, b"say hello");
+    }
+
+    #[test]
+    fn test_discard_large_body() {
+        let len = 10 * 1024 * 1024;
+        let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {len}\r\n\r\n").into_bytes();
+        response.resize(response.len() + len, b'x');
+
+        let reader = reader_for_raw(&response);
+        assert!(reader.has_body_remaining());
+        assert_eq!(reader.discard().unwrap(), len as u64);
+    }
+
+    #[test]
+    fn test_discard_up_to() {
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world");
+        assert_eq!(reader.discard_up_to(5).unwrap(), 5);
+
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n");
+        assert_eq!(reader.discard_up_to(1000).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_has_body_remaining() {
+        use std::io::Read;
+
+        let mut reader =
+            reader_for_raw(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n");
+        assert!(reader.has_body_remaining());
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert!(!reader.has_body_remaining());
+
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        assert!(!reader.has_body_remaining());
+    }
+
+    #[test]
+    #[cfg(feature = "checksum")]
+    fn test_tee_hash() {
+        use sha2::{Digest, Sha256};
+
+        let body: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for chunk in body.chunks(7_000) {
+            response.extend(format!("{:x}\r\n", chunk.len()).as_bytes());
+            response.extend(chunk);
+            response.extend(b"\r\n");
+        }
+        response.extend(b"0\r\n\r\n");
+
+        let mut written = Vec::new();
+        let mut hasher = Sha256::new();
+        let n = reader_for_raw(&response)
+            .tee(&mut hasher)
+            .write_to(&mut written)
+            .unwrap();
+        assert_eq!(n, body.len() as u64);
+        assert_eq!(written, body);
+
+        let expected = Sha256::digest(reader_for_raw(&response).bytes().unwrap());
+        assert_eq!(hasher.finalize(), expected);
+    }
+
+    #[test]
+    fn test_size_hint_plain_length() {
+        use std::io::Read;
+
+        let mut reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        assert_eq!(reader.size_hint(), (Some(5), Some(5)));
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(reader.size_hint(), (Some(5), Some(5)));
+    }
+
+    #[cfg(feature = "__compress")]
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_size_hint_gzip_length() {
+        use std::io::Read;
+
+        let body = gzip(&[b'a'; 10_000]);
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        response.extend(&body);
+
+        let mut reader = reader_for_raw(&response);
+        assert_eq!(reader.size_hint(), (Some(body.len() as u64), None));
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(reader.size_hint(), (Some(body.len() as u64), Some(10_000)));
+    }
+
+    #[test]
+    #[cfg(feature = "__compress")]
+    fn test_size_hint_chunked_gzip() {
+        use std::io::Read;
+
+        let body = gzip(&[b'a'; 10_000]);
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        response.extend(format!("{:x}\r\n", body.len()).as_bytes());
+        response.extend(&body);
+        response.extend(b"\r\n0\r\n\r\n");
+
+        let mut reader = reader_for_raw(&response);
+        assert_eq!(reader.size_hint(), (None, None));
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(reader.size_hint(), (None, Some(10_000)));
+
+        let mut with_header = b"HTTP/1.1 200 OK\r\nX-Uncompressed-Content-Length: 10000\r\n".to_vec();
+        with_header.extend(&response[17..]);
+        let reader = reader_for_raw(&with_header);
+        assert_eq!(reader.size_hint(), (None, Some(10_000)));
+    }
+
+    fn assert_invalid_text(err: crate::Error, expected: usize) {
+        match err.kind() {
+            ErrorKind::InvalidTextEncoding { valid_up_to } => assert_eq!(*valid_up_to, expected),
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_text_strict_utf8_valid() {
+        let reader = reader_for("text/plain; charset=utf-8", "québec".as_bytes());
+        assert_eq!(reader.text_strict().unwrap(), "québec");
+    }
+
+    #[test]
+    fn test_text_strict_utf8_invalid() {
+        let reader = reader_for("text/plain; charset=utf-8", b"qu\xC3\x28bec");
+        assert_invalid_text(reader.text_strict().unwrap_err(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_strict_invalid() {
+        let reader = reader_for("application/json; charset=utf-8", b"{\"a\": \"\xFF\"}");
+        assert_invalid_text(reader.json_strict::<serde_json::Value>().unwrap_err(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "charsets")]
+    fn test_text_with_strict_shift_jis_valid() {
+        let reader = reader_for("text/plain", b"\x82\xA0\x82\xA2");
+        assert_eq!(reader.text_with_strict(charsets::SHIFT_JIS).unwrap(), "あい");
+    }
+
+    #[test]
+    #[cfg(feature = "charsets")]
+    fn test_text_with_strict_shift_jis_invalid() {
+        let reader = reader_for("text/plain", b"\x82\xA0\x81\x20");
+        assert_invalid_text(reader.text_with_strict(charsets::SHIFT_JIS).unwrap_err(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "charsets")]
+    fn test_lines_windows_1252_crlf() {
+        let mut body = Vec::new();
+        for i in 0..5_000 {
+            body.extend(format!("{i}: caf").as_bytes());
+            body.extend(b"\xE9\r\n");
+        }
+
+        let reader = reader_for("text/plain; charset=windows-1252", &body);
+        let lines: Vec<String> = reader.lines().collect::<std::io::Result<_>>().unwrap();
+        assert_eq!(lines.len(), 5_000);
+        assert_eq!(lines[4_999], "4999: café");
+    }
 
     #[test]
+    #[cfg(feature = "charsets")]
+    fn test_text_strict_charset_from_header() {
+        let reader = reader_for("text/plain; charset=shift_jis", b"\x82\xA0\xFF");
+        assert_invalid_text(reader.text_strict().unwrap_err(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "charsets")]
     fn test_get_charset_from_header() {
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -254,6 +961,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "charsets")]
     fn test_get_charset_from_header_lowercase() {
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -264,14 +972,179 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "charsets")]
     fn test_get_charset_from_default() {
         let headers = HeaderMap::new();
         assert_eq!(get_charset(&headers, Some(charsets::UTF_8)), charsets::UTF_8);
     }
 
     #[test]
+    #[cfg(feature = "charsets")]
     fn test_get_charset_standard() {
         let headers = HeaderMap::new();
         assert_eq!(get_charset(&headers, None), charsets::WINDOWS_1252);
     }
+
+    fn reader_with_buffer_size(response: &[u8], read_buffer_size: usize) -> ResponseReader {
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.read_buffer_size = read_buffer_size;
+        let (_, _, reader) = parse_response(BaseStream::mock(response.to_vec()), &req, req.url())
+            .unwrap()
+            .split();
+        reader
+    }
+
+    #[test]
+    fn test_buffer_size_propagated() {
+        let reader = reader_for_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        assert_eq!(reader.inner.body_reader().buffer_capacity(), 8 * 1024);
+
+        let reader = reader_with_buffer_size(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello", 2048);
+        assert_eq!(reader.inner.body_reader().buffer_capacity(), 2048);
+
+        let reader = reader_with_buffer_size(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+            64 * 1024,
+        );
+        assert_eq!(reader.inner.body_reader().buffer_capacity(), 64 * 1024);
+    }
+
+    #[test]
+    fn test_large_buffer_size_round_trip() {
+        let payload: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for chunk in payload.chunks(100_000) {
+            response.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+            response.extend_from_slice(chunk);
+            response.extend_from_slice(b"\r\n");
+        }
+        response.extend_from_slice(b"0\r\n\r\n");
+
+        let reader = reader_with_buffer_size(&response, 256 * 1024);
+        assert_eq!(reader.inner.body_reader().buffer_capacity(), 256 * 1024);
+        assert_eq!(reader.bytes().unwrap(), payload);
+    }
+
+    #[cfg(feature = "json")]
+    fn assert_json_decode_error(err: crate::Error, expected_snippet: &str, expected_content_type: &str) {
+        assert_eq!(err.body_snippet(), Some(expected_snippet));
+        assert!(!err.to_string().contains(expected_snippet));
+        match err.kind() {
+            ErrorKind::JsonDecode {
+                snippet, content_type, ..
+            } => {
+                assert_eq!(snippet, expected_snippet);
+                assert_eq!(content_type.as_deref(), Some(expected_content_type));
+            }
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_html_body() {
+        let body = "<!DOCTYPE html>\n<html><body>Service unavailable</body></html>";
+        let reader = reader_for("text/html; charset=utf-8", body.as_bytes());
+        let err = reader.json::<serde_json::Value>().unwrap_err();
+        assert_json_decode_error(err, body, "text/html; charset=utf-8");
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_trailing_garbage() {
+        let padding = " ".repeat(200);
+        let body = format!("{{\"a\": 1}}\n{padding}<garbage>");
+        let reader = reader_for("application/json", body.as_bytes());
+        let err = reader.json_utf8::<serde_json::Value>().unwrap_err();
+        assert_json_decode_error(err, &body[body.len() - 137..], "application/json");
+
+        let reader = reader_for("application/json", body.as_bytes());
+        let err = reader.json_strict::<serde_json::Value>().unwrap_err();
+        assert_json_decode_error(err, &body[body.len() - 137..], "application/json");
+    }
+
+    #[cfg(feature = "json")]
+    fn assert_trailing_data(err: crate::Error, expected: usize) {
+        match err.kind() {
+            ErrorKind::TrailingData { bytes } => assert_eq!(*bytes, expected),
+            kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_strict_eof() {
+        let reader = reader_for("application/json", b"{\"a\": 1}");
+        assert_eq!(reader.json_strict_eof::<serde_json::Value>().unwrap()["a"], 1);
+
+        let reader = reader_for("application/json", b"[1, 2] \r\n\t\n");
+        assert_eq!(reader.json_strict_eof::<Vec<u32>>().unwrap(), [1, 2]);
+
+        let reader = reader_for("application/json", b"{\"a\": 1}\n{}");
+        assert_trailing_data(reader.json_strict_eof::<serde_json::Value>().unwrap_err(), 2);
+
+        let reader = reader_for("application/json", b"42 x");
+        assert_trailing_data(reader.json_strict_eof::<u32>().unwrap_err(), 1);
+
+        // Invalid JSON is still reported as such.
+        let reader = reader_for("application/json", b"{\"a\": }");
+        let err = reader.json_strict_eof::<serde_json::Value>().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::JsonDecode { .. }));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_strict_eof_ndjson() {
+        let body = b"{\"id\": 1}\n{\"id\": 2}\n{\"id\": 3}\n";
+        let reader = reader_for("application/x-ndjson", body);
+        let err = reader.json_strict_eof::<serde_json::Value>().unwrap_err();
+        assert!(err.to_string().contains("one line at a time"), "{}", err);
+        assert_trailing_data(err, 20);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_reject_json_trailing_data_setting() {
+        let body = b"{\"a\": 1} {}";
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        for (reject, trailing) in [(false, false), (true, true)] {
+            req.base_settings.reject_json_trailing_data = reject;
+            let mut response = response.clone().into_bytes();
+            response.extend(body);
+            let resp = parse_response(BaseStream::mock(response.clone()), &req, req.url()).unwrap();
+            let err = resp.json::<serde_json::Value>().unwrap_err();
+            assert_eq!(matches!(err.kind(), ErrorKind::TrailingData { bytes: 2 }), trailing);
+
+            let resp = parse_response(BaseStream::mock(response), &req, req.url()).unwrap();
+            let err = resp.json_strict::<serde_json::Value>().unwrap_err();
+            assert_eq!(matches!(err.kind(), ErrorKind::TrailingData { bytes: 2 }), trailing);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "json", feature = "charsets"))]
+    fn test_json_strict_eof_decoded_text() {
+        // The trailing byte is decoded to the two bytes of "é" in UTF-8.
+        let reader = reader_for("application/json; charset=windows-1252", b"1 \xE9");
+        assert_trailing_data(reader.json_strict_eof::<u32>().unwrap_err(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_error_beyond_capture() {
+        let body = format!("[{}x]", "1,".repeat(1000));
+        let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+        req.base_settings.json_error_capture = 100;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let resp = parse_response(BaseStream::mock(response.into_bytes()), &req, req.url()).unwrap();
+        let err = resp.json_utf8::<serde_json::Value>().unwrap_err();
+        assert_json_decode_error(err, &body[..100], "application/json");
+    }
 }
@@ -0,0 +1,106 @@
+use std::io::{self, Read, Write};
+
+use crate::error::Result;
+
+/// `TeeReader` writes the data read from a reader to a secondary writer.
+///
+/// It can be used to compute a digest of a response body while it is being written somewhere
+/// else, without reading the body twice. The data is written to the secondary writer before it is
+/// returned by `read`, and errors from the secondary writer are returned by `read`.
+#[derive(Debug)]
+pub struct TeeReader<R, W> {
+    inner: R,
+    secondary: W,
+}
+
+impl<R, W> TeeReader<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    /// Create a new `TeeReader` writing the data read from `inner` to `secondary`.
+    pub fn new(inner: R, secondary: W) -> Self {
+        TeeReader { inner, secondary }
+    }
+
+    /// Write the rest of the data to any object that implements `Write`, flushing both writers.
+    pub fn write_to<T>(mut self, mut writer: T) -> Result<u64>
+    where
+        T: Write,
+    {
+        let n = io::copy(&mut self, &mut writer)?;
+        writer.flush()?;
+        self.secondary.flush()?;
+        Ok(n)
+    }
+
+    /// Get back the reader and the secondary writer.
+    pub fn into_parts(self) -> (R, W) {
+        (self.inner, self.secondary)
+    }
+}
+
+impl<R, W> Read for TeeReader<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.secondary.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use super::TeeReader;
+
+    /// Writer accepting at most 3 bytes per call.
+    struct ShortWriter(Vec<u8>);
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tee_short_writes() {
+        let mut reader = TeeReader::new(&b"hello world"[..], ShortWriter(Vec::new()));
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+
+        let (_, secondary) = reader.into_parts();
+        assert_eq!(data, b"hello world");
+        assert_eq!(secondary.0, b"hello world");
+    }
+
+    #[test]
+    fn test_tee_secondary_error() {
+        let reader = TeeReader::new(&b"hello world"[..], FailingWriter);
+        let err = reader.write_to(io::sink()).unwrap_err();
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(source.to_string(), "disk full");
+    }
+}
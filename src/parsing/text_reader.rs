@@ -1,16 +1,27 @@
-use std::io::{self, Read};
+use std::cmp;
+use std::io::{self, BufRead, Read};
 
 use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
 
 use crate::charsets::Charset;
 
+const BUFFER_LEN: usize = 8 * 1024;
+
 /// `TextReader` converts bytes in a specific charset to bytes in UTF-8.
 ///
 /// It can be used to convert a stream of text in a specific charset into a stream
 /// of UTF-8 encoded bytes. The `Read::read_to_string` method can be used to convert
 /// the stream of UTF-8 bytes into a `String`.
+///
+/// The decoded bytes are buffered, which means that `TextReader` also implements `BufRead`
+/// and that `BufRead::lines` can be used to iterate over the lines of the text.
 #[derive(Debug)]
-pub struct TextReader<R>(DecodeReaderBytes<R, Vec<u8>>);
+pub struct TextReader<R> {
+    inner: DecodeReaderBytes<R, Vec<u8>>,
+    buffer: Box<[u8]>,
+    pos: usize, // bytes consumed from `buffer`
+    len: usize, // bytes of decoded text in `buffer`
+}
 
 impl<R> TextReader<R>
 where
@@ -18,7 +29,31 @@ where
 {
     /// Create a new `TextReader` with the given charset.
     pub fn new(inner: R, charset: Charset) -> Self {
-        Self(DecodeReaderBytesBuilder::new().encoding(Some(charset)).build(inner))
+        Self {
+            inner: DecodeReaderBytesBuilder::new().encoding(Some(charset)).build(inner),
+            buffer: vec![0; BUFFER_LEN].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<R> BufRead for TextReader<R>
+where
+    R: Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // The decoder keeps incomplete multi-byte sequences internally until the rest of the sequence
+        // has been read, so the decoded buffer always ends on a character boundary.
+        if self.pos == self.len {
+            self.len = self.inner.read(&mut self.buffer)?;
+            self.pos = 0;
+        }
+        Ok(&self.buffer[self.pos..self.len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.len);
     }
 }
 
@@ -27,7 +62,9 @@ where
     R: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf)
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
     }
 }
 
@@ -64,3 +101,47 @@ fn test_string_reader_large_buffer_latin1() {
         assert_eq!(c, 'É');
     }
 }
+
+#[test]
+fn test_lines_large_buffer_latin1_crlf() {
+    let mut buf = Vec::new();
+    for i in 0..2_000 {
+        buf.extend(format!("line {i} ").as_bytes());
+        buf.extend(&[0xC9; 5]);
+        buf.extend(b"\r\n");
+    }
+    buf.extend(b"last");
+
+    let reader = TextReader::new(&buf[..], crate::charsets::WINDOWS_1252);
+    let lines: Vec<String> = reader.lines().collect::<io::Result<_>>().unwrap();
+
+    assert_eq!(lines.len(), 2_001);
+    for (i, line) in lines[..2_000].iter().enumerate() {
+        assert_eq!(*line, format!("line {i} ÉÉÉÉÉ"));
+    }
+    assert_eq!(lines[2_000], "last");
+}
+
+#[test]
+fn test_fill_buf_split_multibyte_sequence() {
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = cmp::min(1, cmp::min(buf.len(), self.0.len()));
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let mut reader = TextReader::new(OneByte("québec\nà\r\n".as_bytes()), crate::charsets::UTF_8);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "québec\n");
+
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "à\r\n");
+}
@@ -0,0 +1,233 @@
+//! Low-level writing of requests and reading of responses, for connections which are not managed
+//! by this crate.
+//!
+//! These functions skip everything done by `RequestBuilder` and `Session`: no URL is parsed, no
+//! header is added, redirections are not followed and bodies are not decompressed. They can be
+//! used to speak HTTP/1.1 over an already connected socket or over a custom transport.
+//!
+//! # Example
+//! ```no_run
+//! use std::io::Read;
+//! use std::net::TcpStream;
+//!
+//! use attohttpc::header::{HeaderValue, HOST};
+//! use attohttpc::raw::{self, RawRequest, ResponseLimits};
+//! use attohttpc::Method;
+//!
+//! let mut stream = TcpStream::connect("192.168.1.1:80")?;
+//!
+//! let mut request = RawRequest::new(Method::GET, "/status;v=2");
+//! request.headers.insert(HOST, HeaderValue::from_static("192.168.1.1"));
+//! raw::write_raw(&mut request, &mut stream)?;
+//!
+//! let (status, headers, mut body) = raw::read_raw_response(&stream, &Method::GET, &ResponseLimits::default())?;
+//! let mut text = String::new();
+//! body.read_to_string(&mut text)?;
+//! # Ok::<(), attohttpc::Error>(())
+//! ```
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use http::{HeaderMap, Method, StatusCode};
+
+use crate::error::{ErrorKind, Result};
+use crate::parsing::body_reader::{response_has_body, BodyReader};
+use crate::parsing::response::{parse_response_head, ResponseHead};
+use crate::request::body::{self, Body};
+use crate::request::{validate_component, write_body, write_headers};
+
+/// A request written as is by `write_raw`.
+#[derive(Debug, Clone)]
+pub struct RawRequest<B> {
+    /// Method of the request.
+    pub method: Method,
+    /// Target of the request, usually the path and the query, written as is in the request line.
+    pub target: String,
+    /// Headers of the request, including the `Host` header.
+    ///
+    /// The headers framing the body are not added automatically: `Content-Length` must be set for a
+    /// body of known length and `Transfer-Encoding: chunked` for a chunked body.
+    pub headers: HeaderMap,
+    /// Body of the request.
+    pub body: B,
+}
+
+impl RawRequest<body::Empty> {
+    /// Create a new `RawRequest` without headers nor body.
+    pub fn new(method: Method, target: impl Into<String>) -> RawRequest<body::Empty> {
+        RawRequest {
+            method,
+            target: target.into(),
+            headers: HeaderMap::new(),
+            body: body::Empty,
+        }
+    }
+}
+
+/// Limits applied by `read_raw_response` to the head of responses.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseLimits {
+    /// Maximum number of headers accepted. The default is 100.
+    pub max_headers: usize,
+    /// Whether a header with an invalid name or value is an error instead of being dropped. The
+    /// default is false.
+    pub strict_headers: bool,
+    /// Whether a header value folded over multiple lines is an error. The default is false.
+    pub reject_obs_fold: bool,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> ResponseLimits {
+        ResponseLimits {
+            max_headers: 100,
+            strict_headers: false,
+            reject_obs_fold: false,
+        }
+    }
+}
+
+/// Write the request to the writer using HTTP/1.1.
+///
+/// The request line and the headers are written as they are, after checking that they do not
+/// contain line breaks. The body is sent using the chunked encoding if its kind is
+/// `BodyKind::Chunked`.
+pub fn write_raw<B, W>(request: &mut RawRequest<B>, writer: W) -> Result
+where
+    B: Body,
+    W: Write,
+{
+    if request.target.is_empty() || request.target.contains(' ') {
+        return Err(ErrorKind::InvalidRequestComponent("request target".into()).into());
+    }
+    validate_component("request target", request.target.as_bytes())?;
+
+    let mut writer = BufWriter::new(writer);
+    write!(writer, "{} {} HTTP/1.1\r\n", request.method.as_str(), request.target)?;
+    write_headers(&mut writer, &request.headers, None)?;
+    write_body(&mut request.body, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read the head of a response from the reader, returning its status, its headers and a reader
+/// for its body.
+///
+/// The `method` is the method of the request, since responses to HEAD requests never have a body.
+/// The body reader removes the chunked encoding and stops at the end of the body, but it does not
+/// decompress it.
+pub fn read_raw_response<R>(
+    reader: R,
+    method: &Method,
+    limits: &ResponseLimits,
+) -> Result<(StatusCode, HeaderMap, impl BufRead)>
+where
+    R: Read,
+{
+    let mut reader = BufReader::new(reader);
+    let ResponseHead { status, headers, .. } = parse_response_head(&mut reader, limits)?;
+    let body = BodyReader::new(&headers, reader, response_has_body(method, status))?;
+    Ok((status, headers, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Result as IoResult, Write};
+
+    use http::header::{HeaderValue, CONTENT_LENGTH, HOST};
+    use http::{Method, StatusCode};
+    use url::Position;
+
+    use super::{read_raw_response, write_raw, RawRequest, ResponseLimits};
+    use crate::body::{Body, BodyKind, Bytes};
+    use crate::ErrorKind;
+
+    struct Streamed(&'static [u8]);
+
+    impl Body for Streamed {
+        fn kind(&mut self) -> IoResult<BodyKind> {
+            Ok(BodyKind::Chunked)
+        }
+
+        fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+            writer.write_all(self.0)
+        }
+    }
+
+    fn assert_same_as_builder<B: Body>(builder: crate::RequestBuilder<B>, body: B) {
+        let mut prepped = builder.try_prepare().unwrap();
+        let url = prepped.url().clone();
+        let mut expected = Vec::new();
+        prepped.write_request(&mut expected, &url, None).unwrap();
+
+        let mut request = RawRequest {
+            method: prepped.method().clone(),
+            target: url[Position::BeforePath..Position::AfterQuery].to_string(),
+            headers: prepped.headers().clone(),
+            body,
+        };
+        let mut actual = Vec::new();
+        write_raw(&mut request, &mut actual).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&actual), String::from_utf8_lossy(&expected));
+    }
+
+    #[test]
+    fn test_write_raw_same_as_builder() {
+        assert_same_as_builder(
+            crate::get("http://example.com/a/b?c=d")
+                .header("x-custom", "1")
+                .body(Bytes([])),
+            Bytes([]),
+        );
+        assert_same_as_builder(
+            crate::post("http://example.com/upload").bytes(&b"hello"[..]),
+            Bytes(&b"hello"[..]),
+        );
+        assert_same_as_builder(
+            crate::put("http://example.com/stream").body(Streamed(b"hello world")),
+            Streamed(b"hello world"),
+        );
+    }
+
+    #[test]
+    fn test_write_raw_exact_target() {
+        let mut request = RawRequest::new(Method::GET, "/odd;path/../%zz?x");
+        request.headers.insert(HOST, HeaderValue::from_static("gateway"));
+        let mut out = Vec::new();
+        write_raw(&mut request, &mut out).unwrap();
+        assert_eq!(out, b"GET /odd;path/../%zz?x HTTP/1.1\r\nhost: gateway\r\n\r\n");
+    }
+
+    #[test]
+    fn test_write_raw_invalid_target() {
+        for target in ["", "/a b", "/a\r\nx: y"] {
+            let mut request = RawRequest::new(Method::GET, target);
+            let err = write_raw(&mut request, Vec::new()).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::InvalidRequestComponent(_)));
+        }
+    }
+
+    #[test]
+    fn test_read_raw_response() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\nnext";
+        let (status, headers, mut body) =
+            read_raw_response(&response[..], &Method::GET, &ResponseLimits::default()).unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["transfer-encoding"], "chunked");
+        let mut text = String::new();
+        body.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "hello");
+
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+        let (_, headers, mut body) =
+            read_raw_response(&response[..], &Method::HEAD, &ResponseLimits::default()).unwrap();
+        assert_eq!(headers[CONTENT_LENGTH], "5");
+        assert_eq!(body.read(&mut [0; 8]).unwrap(), 0);
+
+        let limits = ResponseLimits {
+            max_headers: 1,
+            ..ResponseLimits::default()
+        };
+        let response = b"HTTP/1.1 200 OK\r\na: 1\r\nb: 2\r\n\r\n";
+        assert!(read_raw_response(&response[..], &Method::GET, &limits).is_err());
+    }
+}
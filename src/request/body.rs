@@ -2,6 +2,8 @@ use std::convert::TryInto;
 use std::fs;
 use std::io::{copy, Result as IoResult, Seek, SeekFrom, Write};
 
+use http::header::{HeaderMap, HeaderName};
+
 /// The kinds of request bodies currently supported by this crate.
 #[derive(Debug, Clone, Copy)]
 pub enum BodyKind {
@@ -14,6 +16,14 @@ pub enum BodyKind {
 }
 
 /// A generic rewindable request body
+///
+/// The headers describing the body, like `Content-Length` and `Content-Type`, are set from this
+/// trait when the request is prepared. A `Content-Type` header set on the request takes
+/// precedence over the type given by `content_type`.
+///
+/// A `RequestBuilder` or `PreparedRequest` is `Send` whenever its body is, so a request can be
+/// built on one thread and sent from another. All the bodies of this crate are `Send` when the
+/// data they wrap is.
 pub trait Body {
     /// Determine the kind of the request body
     fn kind(&mut self) -> IoResult<BodyKind>;
@@ -24,9 +34,32 @@ pub trait Body {
     fn write<W: Write>(&mut self, writer: W) -> IoResult<()>;
 
     /// Gets the content type this body is tied to if it has one.
+    ///
+    /// It is used as the `Content-Type` header of the request, unless the header is set.
     fn content_type(&mut self) -> IoResult<Option<String>> {
         Ok(None)
     }
+
+    /// Gets the content coding applied to the data written out by this body, if any.
+    ///
+    /// It is sent as the `Content-Encoding` header of the request, replacing the header if it is
+    /// set.
+    fn content_encoding(&mut self) -> IoResult<Option<String>> {
+        Ok(None)
+    }
+
+    /// Gets the names of the trailers sent after a chunked body, which are announced in the
+    /// `Trailer` header.
+    fn trailer_names(&mut self) -> IoResult<Vec<HeaderName>> {
+        Ok(Vec::new())
+    }
+
+    /// Gets the trailers to send after the body, once it has been written out.
+    ///
+    /// Trailers are only sent when the body uses chunked encoding.
+    fn trailers(&mut self) -> IoResult<Option<HeaderMap>> {
+        Ok(None)
+    }
 }
 
 /// An empty request body
@@ -90,24 +123,67 @@ impl Body for File {
     }
 }
 
-pub(crate) struct ChunkedWriter<W>(pub W);
+/// Size of the chunks emitted by `ChunkedWriter`, unless it is flushed earlier.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Writer using the chunked transfer encoding, which buffers small writes into larger chunks.
+pub(crate) struct ChunkedWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+}
 
 impl<W: Write> ChunkedWriter<W> {
-    pub fn close(mut self) -> IoResult<()> {
-        self.0.write_all(b"0\r\n\r\n")
+    pub fn new(inner: W) -> ChunkedWriter<W> {
+        ChunkedWriter { inner, buf: Vec::new() }
+    }
+
+    /// Write the last chunk, followed by the trailers if there are any.
+    pub fn close(mut self, trailers: Option<&HeaderMap>) -> IoResult<()> {
+        self.write_buffered_chunk()?;
+        self.inner.write_all(b"0\r\n")?;
+        for (name, value) in trailers.into_iter().flatten() {
+            write!(self.inner, "{}: ", name)?;
+            self.inner.write_all(value.as_bytes())?;
+            write!(self.inner, "\r\n")?;
+        }
+        self.inner.write_all(b"\r\n")
+    }
+
+    fn write_chunk(inner: &mut W, data: &[u8]) -> IoResult<()> {
+        // An empty chunk would terminate the body.
+        if !data.is_empty() {
+            write!(inner, "{:x}\r\n", data.len())?;
+            inner.write_all(data)?;
+            write!(inner, "\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn write_buffered_chunk(&mut self) -> IoResult<()> {
+        Self::write_chunk(&mut self.inner, &self.buf)?;
+        self.buf.clear();
+        Ok(())
     }
 }
 
 impl<W: Write> Write for ChunkedWriter<W> {
     fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
-        write!(self.0, "{:x}\r\n", buf.len())?;
-        self.0.write_all(buf)?;
-        write!(self.0, "\r\n")?;
-        Ok(buf.len())
+        if self.buf.is_empty() && buf.len() >= CHUNK_SIZE {
+            Self::write_chunk(&mut self.inner, buf)?;
+            return Ok(buf.len());
+        }
+
+        let len = buf.len().min(CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&buf[..len]);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.write_buffered_chunk()?;
+        }
+        Ok(len)
     }
 
     fn flush(&mut self) -> IoResult<()> {
-        self.0.flush()
+        self.write_buffered_chunk()?;
+        self.inner.flush()
     }
 }
 
@@ -115,7 +191,8 @@ impl<W: Write> Write for ChunkedWriter<W> {
 mod json {
     use super::*;
 
-    use std::io::BufWriter;
+    use std::fmt;
+    use std::io::{BufWriter, Error as IoError};
 
     use serde::ser::Serialize;
     use serde_json::ser::to_writer;
@@ -136,7 +213,393 @@ mod json {
             Ok(())
         }
     }
+
+    /// A request body for streaming out JSON using a custom serializer function
+    #[derive(Clone)]
+    pub struct JsonWith<B, F>(pub B, pub F);
+
+    impl<B, F, E> Body for JsonWith<B, F>
+    where
+        F: FnMut(&mut dyn Write, &B) -> Result<(), E>,
+        IoError: From<E>,
+    {
+        fn kind(&mut self) -> IoResult<BodyKind> {
+            Ok(BodyKind::Chunked)
+        }
+
+        fn write<W: Write>(&mut self, writer: W) -> IoResult<()> {
+            let mut writer = BufWriter::new(writer);
+            (self.1)(&mut writer, &self.0)?;
+            writer.flush()?;
+            Ok(())
+        }
+    }
+
+    impl<B: fmt::Debug, F> fmt::Debug for JsonWith<B, F> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("JsonWith").field(&self.0).finish()
+        }
+    }
 }
 
 #[cfg(feature = "json")]
-pub use json::Json;
+pub use json::{Json, JsonWith};
+
+#[cfg(feature = "checksum")]
+mod checksum {
+    use super::*;
+
+    use http::header::HeaderValue;
+    use sha2::{Digest, Sha256};
+
+    /// Writer computing the SHA-256 digest of the data written to the inner writer.
+    pub(crate) struct HashWriter<W> {
+        inner: W,
+        hasher: Sha256,
+    }
+
+    impl<W> HashWriter<W> {
+        pub(crate) fn new(inner: W) -> HashWriter<W> {
+            HashWriter {
+                inner,
+                hasher: Sha256::new(),
+            }
+        }
+
+        /// Get the hex-encoded digest of the data written.
+        pub(crate) fn hex_digest(self) -> String {
+            to_hex(&self.hasher.finalize())
+        }
+    }
+
+    impl<W: Write> Write for HashWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            let n = self.inner.write(buf)?;
+            self.hasher.update(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            self.inner.flush()
+        }
+    }
+
+    /// Encode the bytes in lowercase hexadecimal.
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// A request body sent using chunked encoding, followed by a trailer containing the
+    /// hex-encoded SHA-256 digest of the body
+    ///
+    /// The digest is computed while the body is streamed out, so the body does not need to be
+    /// read twice.
+    ///
+    /// This type only exists when the `checksum` feature is enabled.
+    #[derive(Debug, Clone)]
+    pub struct ChunkedWithTrailers<B> {
+        body: B,
+        trailer_name: HeaderName,
+        digest: Option<String>,
+    }
+
+    impl<B> ChunkedWithTrailers<B> {
+        /// Wrap the body, sending its digest in the trailer named `trailer_name`.
+        pub fn new(body: B, trailer_name: HeaderName) -> ChunkedWithTrailers<B> {
+            ChunkedWithTrailers {
+                body,
+                trailer_name,
+                digest: None,
+            }
+        }
+    }
+
+    impl<B: Body> Body for ChunkedWithTrailers<B> {
+        fn kind(&mut self) -> IoResult<BodyKind> {
+            Ok(BodyKind::Chunked)
+        }
+
+        fn write<W: Write>(&mut self, writer: W) -> IoResult<()> {
+            let mut writer = HashWriter::new(writer);
+            self.body.write(&mut writer)?;
+            self.digest = Some(writer.hex_digest());
+            Ok(())
+        }
+
+        fn content_type(&mut self) -> IoResult<Option<String>> {
+            self.body.content_type()
+        }
+
+        fn trailer_names(&mut self) -> IoResult<Vec<HeaderName>> {
+            Ok(vec![self.trailer_name.clone()])
+        }
+
+        fn trailers(&mut self) -> IoResult<Option<HeaderMap>> {
+            let mut trailers = HeaderMap::new();
+            if let Some(digest) = &self.digest {
+                let value = HeaderValue::from_str(digest).expect("hex digest is a valid header value");
+                trailers.insert(self.trailer_name.clone(), value);
+            }
+            Ok(Some(trailers))
+        }
+    }
+}
+
+#[cfg(feature = "checksum")]
+pub use checksum::ChunkedWithTrailers;
+#[cfg(feature = "checksum")]
+pub(crate) use checksum::HashWriter;
+
+#[cfg(feature = "__compress")]
+mod gzip {
+    use super::*;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    /// Bodies with a known length up to this size are compressed in memory, larger ones are
+    /// streamed out using chunked encoding.
+    const BUFFER_LIMIT: u64 = 1024 * 1024;
+
+    /// A request body compressed with gzip while it is written out
+    ///
+    /// Small bodies with a known length are compressed in memory so that the request keeps a
+    /// `Content-Length`, other bodies are compressed while they are streamed out using chunked
+    /// encoding. The compressed data only depends on the body and the compression level, so a
+    /// body written again after a redirection is identical. An empty body stays empty and is sent
+    /// without a `Content-Encoding`.
+    ///
+    /// This type only exists when one of the `compress` features is enabled.
+    #[derive(Debug, Clone)]
+    pub struct Gzip<B> {
+        body: B,
+        level: Compression,
+        compressed: Option<Vec<u8>>,
+    }
+
+    impl<B> Gzip<B> {
+        /// Wrap the body, compressing it with the given level between 0 and 9, or the default
+        /// level when `None`.
+        pub fn new(body: B, level: Option<u32>) -> Gzip<B> {
+            Gzip {
+                body,
+                level: level.map_or_else(Compression::default, |level| Compression::new(level.min(9))),
+                compressed: None,
+            }
+        }
+    }
+
+    impl<B: Body> Gzip<B> {
+        fn compress<W: Write>(&mut self, writer: W) -> IoResult<W> {
+            let mut encoder = GzEncoder::new(writer, self.level);
+            self.body.write(&mut encoder)?;
+            encoder.finish()
+        }
+    }
+
+    impl<B: Body> Body for Gzip<B> {
+        fn kind(&mut self) -> IoResult<BodyKind> {
+            if let Some(compressed) = &self.compressed {
+                return Ok(BodyKind::KnownLength(compressed.len() as u64));
+            }
+            match self.body.kind()? {
+                BodyKind::Empty => return Ok(BodyKind::Empty),
+                BodyKind::KnownLength(len) if len <= BUFFER_LIMIT => {}
+                _ => return Ok(BodyKind::Chunked),
+            }
+            let compressed = self.compress(Vec::new())?;
+            let len = compressed.len() as u64;
+            self.compressed = Some(compressed);
+            Ok(BodyKind::KnownLength(len))
+        }
+
+        fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+            match &self.compressed {
+                Some(compressed) => writer.write_all(compressed),
+                None if matches!(self.body.kind()?, BodyKind::Empty) => Ok(()),
+                None => self.compress(writer).map(drop),
+            }
+        }
+
+        fn content_type(&mut self) -> IoResult<Option<String>> {
+            self.body.content_type()
+        }
+
+        fn content_encoding(&mut self) -> IoResult<Option<String>> {
+            match self.body.kind()? {
+                BodyKind::Empty => Ok(None),
+                _ => Ok(Some("gzip".into())),
+            }
+        }
+
+        fn trailer_names(&mut self) -> IoResult<Vec<HeaderName>> {
+            self.body.trailer_names()
+        }
+
+        fn trailers(&mut self) -> IoResult<Option<HeaderMap>> {
+            self.body.trailers()
+        }
+    }
+}
+
+#[cfg(feature = "__compress")]
+pub use gzip::Gzip;
+
+#[cfg(test)]
+fn decode_chunked(data: &[u8]) -> Vec<u8> {
+    use std::io::{BufReader, Read};
+
+    let mut decoded = Vec::new();
+    crate::parsing::chunked_reader::ChunkedReader::new(BufReader::new(data))
+        .read_to_end(&mut decoded)
+        .unwrap();
+    decoded
+}
+
+#[test]
+fn test_chunked_writer_buffers_small_writes() {
+    let payload: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+
+    let mut out = Vec::new();
+    let mut writer = ChunkedWriter::new(&mut out);
+    for byte in &payload {
+        writer.write_all(std::slice::from_ref(byte)).unwrap();
+    }
+    writer.close(None).unwrap();
+
+    let chunk_sizes = out.windows(6).filter(|w| w == b"2000\r\n").count();
+    assert_eq!(chunk_sizes, 2);
+    assert!(out.ends_with(b"\r\n0\r\n\r\n"));
+    assert_eq!(decode_chunked(&out), payload);
+}
+
+#[test]
+fn test_chunked_writer_empty_writes() {
+    let mut out = Vec::new();
+    let mut writer = ChunkedWriter::new(&mut out);
+    writer.write_all(b"hello").unwrap();
+    assert_eq!(writer.write(b"").unwrap(), 0);
+    writer.flush().unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.write(b"").unwrap(), 0);
+    writer.write_all(b" world").unwrap();
+    writer.close(None).unwrap();
+
+    assert_eq!(out, b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n");
+    assert_eq!(decode_chunked(&out), b"hello world");
+}
+
+#[test]
+fn test_chunked_writer_large_write() {
+    let payload = vec![b'x'; 3 * CHUNK_SIZE + 10];
+
+    let mut out = Vec::new();
+    let mut writer = ChunkedWriter::new(&mut out);
+    writer.write_all(b"abc").unwrap();
+    writer.write_all(&payload).unwrap();
+    writer.close(None).unwrap();
+
+    let mut expected = b"abc".to_vec();
+    expected.extend_from_slice(&payload);
+    assert_eq!(decode_chunked(&out), expected);
+}
+
+#[test]
+fn test_chunked_writer_trailers() {
+    let mut trailers = HeaderMap::new();
+    trailers.insert("x-checksum", "abc".parse().unwrap());
+    trailers.append("x-checksum", "def".parse().unwrap());
+
+    let mut out = Vec::new();
+    let mut writer = ChunkedWriter::new(&mut out);
+    writer.write_all(b"hello").unwrap();
+    writer.close(Some(&trailers)).unwrap();
+
+    assert_eq!(out, b"5\r\nhello\r\n0\r\nx-checksum: abc\r\nx-checksum: def\r\n\r\n");
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn test_chunked_with_trailers_digest() {
+    let mut body = ChunkedWithTrailers::new(Text("hello"), HeaderName::from_static("x-content-sha256"));
+    assert_eq!(body.trailer_names().unwrap(), ["x-content-sha256"]);
+
+    let mut out = Vec::new();
+    body.write(&mut out).unwrap();
+    assert_eq!(out, b"hello");
+
+    let trailers = body.trailers().unwrap().unwrap();
+    assert_eq!(
+        trailers["x-content-sha256"],
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+}
+
+#[test]
+fn test_bodies_are_send() {
+    use crate::{PreparedRequest, RequestBuilder};
+
+    fn assert_send<T: Send>() {}
+    fn assert_request_send<B: Send>() {
+        assert_send::<RequestBuilder<B>>();
+        assert_send::<PreparedRequest<B>>();
+    }
+
+    assert_request_send::<Empty>();
+    assert_request_send::<Text<String>>();
+    assert_request_send::<Text<&'static str>>();
+    assert_request_send::<Bytes<Vec<u8>>>();
+    assert_request_send::<Bytes<&'static [u8]>>();
+    assert_request_send::<File>();
+    #[cfg(feature = "json")]
+    assert_request_send::<Json<serde_json::Value>>();
+    #[cfg(feature = "json")]
+    assert_request_send::<JsonWith<serde_json::Value, fn(&mut dyn Write, &serde_json::Value) -> IoResult<()>>>();
+    #[cfg(feature = "checksum")]
+    assert_request_send::<ChunkedWithTrailers<File>>();
+    #[cfg(feature = "multipart-form")]
+    assert_request_send::<crate::Multipart<'static>>();
+}
+
+#[cfg(feature = "__compress")]
+#[test]
+fn test_gzip_body() {
+    use std::io::Read;
+
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(data)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        decompressed
+    }
+
+    let small = vec![b'a'; 1000];
+    let mut body = Gzip::new(Bytes(&small), None);
+    let len = match body.kind().unwrap() {
+        BodyKind::KnownLength(len) => len,
+        kind => panic!("{:?}", kind),
+    };
+    let mut out = Vec::new();
+    body.write(&mut out).unwrap();
+    assert_eq!(out.len() as u64, len);
+    assert_eq!(decompress(&out), small);
+    assert_eq!(body.content_encoding().unwrap().as_deref(), Some("gzip"));
+
+    let large: Vec<u8> = (0..2 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let mut body = Gzip::new(Bytes(&large), Some(1));
+    assert!(matches!(body.kind().unwrap(), BodyKind::Chunked));
+    let mut first = Vec::new();
+    body.write(&mut first).unwrap();
+    let mut second = Vec::new();
+    body.write(&mut second).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(decompress(&first), large);
+
+    let mut body = Gzip::new(Empty, Some(9));
+    assert!(matches!(body.kind().unwrap(), BodyKind::Empty));
+    let mut out = Vec::new();
+    body.write(&mut out).unwrap();
+    assert!(out.is_empty());
+    assert_eq!(body.content_encoding().unwrap(), None);
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+
+#[derive(Debug)]
+struct State {
+    in_flight: Mutex<usize>,
+    available: Condvar,
+}
+
+/// Counting semaphore limiting the number of requests sent at the same time, shared by the
+/// requests created from a `Session`.
+#[derive(Clone)]
+pub struct RequestLimiter {
+    state: Arc<State>,
+    max: usize,
+}
+
+impl RequestLimiter {
+    pub fn new(max: usize) -> RequestLimiter {
+        RequestLimiter {
+            state: Arc::new(State {
+                in_flight: Mutex::new(0),
+                available: Condvar::new(),
+            }),
+            max,
+        }
+    }
+
+    /// Wait until fewer than `max` requests are in flight, failing with a `TimedOut` error if the
+    /// deadline is reached first.
+    pub fn acquire(&self, deadline: Option<Instant>) -> Result<Permit> {
+        let mut in_flight = self.state.in_flight.lock().unwrap_or_else(PoisonError::into_inner);
+        while *in_flight >= self.max {
+            in_flight = match deadline {
+                None => self
+                    .state
+                    .available
+                    .wait(in_flight)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let timeout = match deadline.checked_duration_since(Instant::now()) {
+                        Some(timeout) => timeout,
+                        None => return Err(timed_out()),
+                    };
+                    self.state
+                        .available
+                        .wait_timeout(in_flight, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+        *in_flight += 1;
+        Ok(Permit {
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+impl fmt::Debug for RequestLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestLimiter").field("max", &self.max).finish()
+    }
+}
+
+/// Permission to send a request, given back to the `RequestLimiter` when dropped.
+#[derive(Debug)]
+pub struct Permit {
+    state: Arc<State>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut in_flight = self.state.in_flight.lock().unwrap_or_else(PoisonError::into_inner);
+        *in_flight -= 1;
+        self.state.available.notify_one();
+    }
+}
+
+fn timed_out() -> crate::Error {
+    io::Error::from(io::ErrorKind::TimedOut).into()
+}
+
+/// Check if the host matches the pattern, either exactly or as a subdomain.
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    match host.len().checked_sub(pattern.len()) {
+        Some(0) => host.eq_ignore_ascii_case(pattern),
+        Some(n) => host.as_bytes()[n - 1] == b'.' && host[n..].eq_ignore_ascii_case(pattern),
+        None => false,
+    }
+}
+
+/// Limit on the rate of requests sent to the hosts matching a pattern, shared by the requests
+/// created from a `Session`.
+///
+/// This is a token bucket holding a single token, so requests are spaced by at least `interval`.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    pattern: String,
+    interval: Duration,
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+/// Longest interval between two requests, so that the time of the next request cannot overflow.
+const MAX_INTERVAL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+impl RateLimit {
+    /// Panics if `requests_per_second` is not a positive number.
+    pub fn new(pattern: &str, requests_per_second: f64) -> RateLimit {
+        assert!(
+            requests_per_second > 0.0 && requests_per_second.is_finite(),
+            "requests_per_second must be a positive number"
+        );
+        // The interval overflows when the rate is tiny, it is clamped instead.
+        let interval = Duration::try_from_secs_f64(1.0 / requests_per_second).unwrap_or(MAX_INTERVAL);
+        RateLimit {
+            pattern: pattern.trim_start_matches('.').to_lowercase(),
+            interval: interval.min(MAX_INTERVAL),
+            next: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        host_matches(host, &self.pattern)
+    }
+
+    /// Forget the requests sent so far. The requests created before keep the previous state.
+    pub fn reset(&mut self) {
+        self.next = Arc::new(Mutex::new(None));
+    }
+
+    /// Wait until a request can be sent, failing with a `TimedOut` error without waiting if that
+    /// is after the deadline.
+    pub fn wait(&self, deadline: Option<Instant>) -> Result {
+        let now = Instant::now();
+        let start = {
+            let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+            let start = next.map_or(now, |next| next.max(now));
+            if deadline.is_some_and(|deadline| start > deadline) {
+                return Err(timed_out());
+            }
+            *next = Some(start + self.interval);
+            start
+        };
+        if start > now {
+            debug!("rate limit of {} reached, waiting {:?}", self.pattern, start - now);
+            thread::sleep(start - now);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{host_matches, RateLimit, RequestLimiter};
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_acquire_release() {
+        let limiter = RequestLimiter::new(2);
+        let first = limiter.acquire(None).unwrap();
+        let _second = limiter.acquire(None).unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(50);
+        match limiter.acquire(Some(deadline)).unwrap_err().kind() {
+            ErrorKind::Io(err) => assert_eq!(err.kind(), std::io::ErrorKind::TimedOut),
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+        assert!(Instant::now() >= deadline);
+
+        drop(first);
+        let _third = limiter
+            .acquire(Some(Instant::now() + Duration::from_millis(50)))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("API.example.com", "example.com"));
+        assert!(host_matches("api.example.com.", "example.com"));
+        assert!(!host_matches("badexample.com", "example.com"));
+        assert!(!host_matches("example.com", "api.example.com"));
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let limit = RateLimit::new("example.com", 20.0);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limit.wait(None).unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        assert!(limit.wait(Some(deadline)).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_reset() {
+        let mut limit = RateLimit::new("example.com", 1.0);
+        let previous = limit.clone();
+        limit.wait(None).unwrap();
+
+        limit.reset();
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert!(limit.wait(Some(deadline)).is_ok());
+        assert!(previous.wait(Some(deadline)).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_tiny_rate() {
+        let limit = RateLimit::new("example.com", f64::MIN_POSITIVE);
+        limit.wait(None).unwrap();
+        let deadline = Instant::now() + Duration::from_millis(10);
+        assert!(limit.wait(Some(deadline)).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_invalid_rate() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(std::panic::catch_unwind(|| RateLimit::new("example.com", rate)).is_err());
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+
+/// Login and password of a `machine` or `default` entry of a netrc file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Credentials {
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Credentials {
+    /// Get the value of the `Authorization` header for basic authentication with these
+    /// credentials, if there is a login.
+    pub fn authorization(&self) -> Option<String> {
+        let login = self.login.as_ref()?;
+        let auth = format!("{}:{}", login, self.password.as_deref().unwrap_or(""));
+        Some(format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(auth.as_bytes())
+        ))
+    }
+}
+
+/// Entries of a netrc file, as used by curl and ftp to store the credentials of hosts.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Netrc {
+    machines: Vec<(String, Credentials)>,
+    default: Option<Credentials>,
+}
+
+/// Split the file into tokens, skipping comments and the bodies of macro definitions, which end
+/// with an empty line.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut in_macdef = false;
+    for line in text.lines() {
+        if in_macdef {
+            in_macdef = !line.trim().is_empty();
+            continue;
+        }
+        for token in line.split_whitespace() {
+            if token.starts_with('#') {
+                break;
+            } else if token == "macdef" {
+                in_macdef = true;
+                break;
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+impl Netrc {
+    /// Parse the content of a netrc file. Unknown tokens are ignored.
+    pub fn parse(text: &str) -> Netrc {
+        let mut netrc = Netrc::default();
+        // The entry being parsed, `None` for the default entry.
+        let mut current: Option<(Option<String>, Credentials)> = None;
+        let mut tokens = tokenize(text).into_iter();
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "machine" | "default" => {
+                    netrc.push(current.take());
+                    let machine = match token {
+                        "machine" => Some(tokens.next().unwrap_or_default().to_lowercase()),
+                        _ => None,
+                    };
+                    current = Some((machine, Credentials::default()));
+                }
+                "login" | "password" => {
+                    let value = tokens.next().map(String::from);
+                    if let Some((_, credentials)) = &mut current {
+                        match token {
+                            "login" => credentials.login = value,
+                            _ => credentials.password = value,
+                        }
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                _ => {}
+            }
+        }
+        netrc.push(current);
+        netrc
+    }
+
+    fn push(&mut self, entry: Option<(Option<String>, Credentials)>) {
+        match entry {
+            Some((Some(machine), credentials)) => self.machines.push((machine, credentials)),
+            // Like curl, the first default entry is used.
+            Some((None, credentials)) if self.default.is_none() => self.default = Some(credentials),
+            _ => {}
+        }
+    }
+
+    /// Read and parse the netrc file at the given path. A missing file gives an empty `Netrc`.
+    pub fn load(path: &Path) -> io::Result<Netrc> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Netrc::parse(&text)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Netrc::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Get the path of the netrc file of the user, `~/.netrc`, or `%USERPROFILE%\_netrc` on
+    /// Windows.
+    pub fn default_path() -> Option<PathBuf> {
+        #[cfg(windows)]
+        let (home, name) = ("USERPROFILE", "_netrc");
+        #[cfg(not(windows))]
+        let (home, name) = ("HOME", ".netrc");
+        env::var_os(home).map(|home| Path::new(&home).join(name))
+    }
+
+    /// Find the credentials of the first entry for the host, or of the default entry.
+    pub fn find(&self, host: &str) -> Option<&Credentials> {
+        self.find_machine(host).or(self.default.as_ref())
+    }
+
+    /// Find the credentials of the first entry for the host, ignoring the default entry.
+    pub fn find_machine(&self, host: &str) -> Option<&Credentials> {
+        self.machines
+            .iter()
+            .find(|(machine, _)| machine.eq_ignore_ascii_case(host))
+            .map(|(_, credentials)| credentials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Credentials, Netrc};
+
+    fn credentials(login: &str, password: &str) -> Credentials {
+        Credentials {
+            login: Some(login.into()),
+            password: Some(password.into()),
+        }
+    }
+
+    #[test]
+    fn test_parse_machines() {
+        let netrc = Netrc::parse(
+            "machine example.com login alice password secret\n\
+             # comment\n\
+             machine API.example.org\n  login bob\n  password hunter2 # trailing comment\n\
+             machine example.com login eve password other\n",
+        );
+        assert_eq!(netrc.find("example.com"), Some(&credentials("alice", "secret")));
+        assert_eq!(netrc.find("api.example.org"), Some(&credentials("bob", "hunter2")));
+        assert_eq!(netrc.find("example.net"), None);
+    }
+
+    #[test]
+    fn test_parse_default() {
+        let netrc = Netrc::parse(
+            "default login anonymous password guest\n\
+             machine example.com login alice account ignored password secret\n",
+        );
+        assert_eq!(netrc.find("example.com"), Some(&credentials("alice", "secret")));
+        assert_eq!(netrc.find("example.net"), Some(&credentials("anonymous", "guest")));
+        assert_eq!(netrc.find_machine("example.net"), None);
+    }
+
+    #[test]
+    fn test_parse_macdef() {
+        let netrc = Netrc::parse(
+            "machine example.com login alice password secret\n\
+             macdef init\n\
+             machine evil.com login mallory password x\n\
+             cd /pub\n\
+             \n\
+             machine example.org login bob password hunter2\n",
+        );
+        assert_eq!(netrc.find("example.com"), Some(&credentials("alice", "secret")));
+        assert_eq!(netrc.find("evil.com"), None);
+        assert_eq!(netrc.find("example.org"), Some(&credentials("bob", "hunter2")));
+    }
+
+    #[test]
+    fn test_authorization() {
+        assert_eq!(
+            credentials("alice", "secret").authorization().as_deref(),
+            Some("Basic YWxpY2U6c2VjcmV0")
+        );
+        let no_password = Credentials {
+            login: Some("alice".into()),
+            password: None,
+        };
+        assert_eq!(no_password.authorization().as_deref(), Some("Basic YWxpY2U6"));
+        assert_eq!(Credentials::default().authorization(), None);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let netrc = Netrc::load(Path::new("/this/file/does/not/exist/.netrc")).unwrap();
+        assert_eq!(netrc, Netrc::default());
+    }
+}
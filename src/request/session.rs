@@ -1,23 +1,59 @@
 use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use http::header::{HeaderValue, IntoHeaderName};
 use http::Method;
 
+use crate::cache::{CacheConfig, ResponseCache};
 #[cfg(feature = "charsets")]
 use crate::charsets::Charset;
 use crate::error::{Error, Result};
+#[cfg(feature = "har")]
+use crate::har::HarRecorder;
+use crate::into_url::IntoUrl;
+use crate::parsing::WwwAuthenticate;
+use crate::request::limiter::{RateLimit, RequestLimiter};
+#[cfg(feature = "__compress")]
+use crate::request::parse_accept_encoding;
 use crate::request::proxy::ProxySettings;
-use crate::request::{header_append, header_insert, BaseSettings, RequestBuilder};
-use crate::tls::Certificate;
+use crate::request::{
+    header_append, header_insert, header_insert_cased, BaseSettings, RequestBuilder, Signer, MIN_BUFFER_SIZE,
+};
+use crate::skip_debug::SkipDebug;
+#[cfg(feature = "testing")]
+use crate::testing::MockTransport;
+use crate::tls::{parse_pem_certificates, Certificate, TlsCache, TlsVersion};
 
 /// `Session` is a type that can carry settings over multiple requests. The settings applied to the
 /// `Session` are applied to every request created from this `Session`.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct Session {
     base_settings: BaseSettings,
 }
 
+impl fmt::Debug for Session {
+    /// Summarize the settings of the `Session`. Header values are not printed, since they can
+    /// contain credentials.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let settings = &self.base_settings;
+        let rate_limits: Vec<&str> = settings.rate_limits.iter().map(RateLimit::pattern).collect();
+        f.debug_struct("Session")
+            .field("headers", &settings.headers.len())
+            .field("connect_timeout", &settings.connect_timeout)
+            .field("read_timeout", &settings.read_timeout)
+            .field("timeout", &settings.timeout)
+            .field("follow_redirects", &settings.follow_redirects)
+            .field("max_redirections", &settings.max_redirections)
+            .field("root_certificates", &settings.root_certificates.0.len())
+            .field("rate_limits", &rate_limits)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Session {
     /// Create a new `Session` with default settings.
     pub fn new() -> Session {
@@ -26,10 +62,28 @@ impl Session {
         }
     }
 
+    /// Drop the state shared by the requests created from this `Session`, keeping its settings.
+    ///
+    /// This resets the rate limits set with `rate_limit`, as if no request had been sent yet, and
+    /// drops the cached TLS configuration, which is built again by the next request. Requests
+    /// created before keep the previous state. The limit on concurrent requests is kept, since the
+    /// requests in flight still count against it. The response cache set with `cache` is emptied,
+    /// for the requests created before as well. Long-lived programs can call this periodically to
+    /// release memory.
+    pub fn reset(&mut self) {
+        for limit in &mut self.base_settings.rate_limits {
+            limit.reset();
+        }
+        if let Some(cache) = &self.base_settings.response_cache {
+            cache.clear();
+        }
+        self.base_settings.tls_cache = TlsCache::default();
+    }
+
     /// Create a new `RequestBuilder` with the GET method and this Session's settings applied on it.
     pub fn get<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::GET, base_url, self.base_settings.clone())
     }
@@ -37,7 +91,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the POST method and this Session's settings applied on it.
     pub fn post<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::POST, base_url, self.base_settings.clone())
     }
@@ -45,7 +99,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the PUT method and this Session's settings applied on it.
     pub fn put<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::PUT, base_url, self.base_settings.clone())
     }
@@ -53,7 +107,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the DELETE method and this Session's settings applied on it.
     pub fn delete<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::DELETE, base_url, self.base_settings.clone())
     }
@@ -61,7 +115,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the HEAD method and this Session's settings applied on it.
     pub fn head<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::HEAD, base_url, self.base_settings.clone())
     }
@@ -69,7 +123,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the OPTIONS method and this Session's settings applied on it.
     pub fn options<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::OPTIONS, base_url, self.base_settings.clone())
     }
@@ -77,7 +131,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the PATCH method and this Session's settings applied on it.
     pub fn patch<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::PATCH, base_url, self.base_settings.clone())
     }
@@ -85,7 +139,7 @@ impl Session {
     /// Create a new `RequestBuilder` with the TRACE method and this Session's settings applied on it.
     pub fn trace<U>(&self, base_url: U) -> RequestBuilder
     where
-        U: AsRef<str>,
+        U: IntoUrl,
     {
         RequestBuilder::with_settings(Method::TRACE, base_url, self.base_settings.clone())
     }
@@ -152,6 +206,45 @@ impl Session {
         Ok(())
     }
 
+    /// Modify a header for this `Request`, remembering the spelling of its name.
+    ///
+    /// The spelling is used when requests are sent with `preserve_header_case` enabled,
+    /// otherwise this is the same as `header`.
+    ///
+    /// # Panics
+    /// This method will panic if the name or the value is invalid.
+    pub fn header_cased<V>(&mut self, name: &str, value: V)
+    where
+        V: TryInto<HeaderValue>,
+        Error: From<V::Error>,
+    {
+        self.try_header_cased(name, value).expect("invalid header");
+    }
+
+    /// Modify a header for this `Request`, remembering the spelling of its name.
+    ///
+    /// The spelling is used when requests are sent with `preserve_header_case` enabled,
+    /// otherwise this is the same as `try_header`.
+    pub fn try_header_cased<V>(&mut self, name: &str, value: V) -> Result<()>
+    where
+        V: TryInto<HeaderValue>,
+        Error: From<V::Error>,
+    {
+        header_insert_cased(&mut self.base_settings, name, value)?;
+        Ok(())
+    }
+
+    /// Sets if the case of header names should be preserved when sending requests.
+    ///
+    /// Header names are normally sent in lowercase. When enabled, the names set using
+    /// `header_cased` are sent with their original spelling and the other names are sent with
+    /// the first letter of every word capitalized, like `Content-Length`.
+    ///
+    /// The default is false.
+    pub fn preserve_header_case(&mut self, preserve_header_case: bool) {
+        self.base_settings.preserve_header_case = preserve_header_case;
+    }
+
     /// Set the maximum number of headers accepted in responses to this request.
     ///
     /// The default is 100.
@@ -159,6 +252,96 @@ impl Session {
         self.base_settings.max_headers = max_headers;
     }
 
+    /// Set whether a response with an invalid header name or value makes the request fail.
+    ///
+    /// When disabled, invalid headers are dropped and counted in `Response::dropped_header_count`.
+    ///
+    /// The default is false.
+    pub fn strict_response_headers(&mut self, strict: bool) {
+        self.base_settings.strict_response_headers = strict;
+    }
+
+    /// Set whether a response with a header value folded over multiple lines makes the request
+    /// fail, which RFC 7230 allows since line folding is obsolete.
+    ///
+    /// When disabled, the lines of a folded value are joined with a single space.
+    ///
+    /// The default is false.
+    pub fn reject_obs_fold(&mut self, reject: bool) {
+        self.base_settings.reject_obs_fold = reject;
+    }
+
+    /// Set whether a response body which fails with an unexpected end of file, like a compressed
+    /// body cut short, is read as if it had ended normally, as long as some of it was read.
+    ///
+    /// This works around servers closing the connection before the end of the body. The
+    /// truncation is reported by `Response::was_truncated`.
+    ///
+    /// The default is false.
+    pub fn tolerate_truncated_body(&mut self, tolerate: bool) {
+        self.base_settings.tolerate_truncated_body = tolerate;
+    }
+
+    /// Set whether a chunked response body is complete when the connection is closed right after
+    /// the last chunk, without the empty line which should end the body.
+    ///
+    /// Some embedded servers omit this line, and browsers accept their responses. The body is never
+    /// considered complete when the connection is closed in the middle of a chunk, or when the
+    /// response announces trailer fields with the `Trailer` header.
+    ///
+    /// The default is true.
+    pub fn lenient_chunked_termination(&mut self, lenient: bool) {
+        self.base_settings.lenient_chunked_termination = lenient;
+    }
+
+    /// Set whether the hop-by-hop headers of responses are kept.
+    ///
+    /// By default, the headers which only concern the connection, like `Connection`,
+    /// `Keep-Alive`, `Transfer-Encoding`, `Trailer` and `Upgrade`, are removed from the headers of
+    /// responses along with the headers named in the `Connection` header. This makes it safe to
+    /// forward the headers of a response.
+    ///
+    /// The default is false.
+    pub fn keep_hop_headers(&mut self, keep: bool) {
+        self.base_settings.keep_hop_headers = keep;
+    }
+
+    /// Add a content type that the responses are expected to have.
+    ///
+    /// When at least one type is expected, sending a request fails with an
+    /// `UnexpectedContentType` error if the `Content-Type` of its response does not match any of
+    /// them, without reading the body. Only the type and subtype are compared, ignoring case and
+    /// parameters. A type starting with `+`, like `+json`, matches the types with this structured
+    /// suffix, like `application/problem+json`.
+    ///
+    /// Responses with a 4xx or 5xx status code are not checked, so that their body can still be
+    /// read to find out what went wrong.
+    pub fn expect_content_type(&mut self, content_type: impl AsRef<str>) {
+        self.base_settings
+            .expected_content_types
+            .push(content_type.as_ref().trim().to_ascii_lowercase());
+    }
+
+    /// Set whether a response without a `Content-Type` header fails the check done when content
+    /// types are expected with `expect_content_type`.
+    ///
+    /// The default is false, such responses are accepted.
+    pub fn require_content_type(&mut self, require: bool) {
+        self.base_settings.require_content_type = require;
+    }
+
+    /// Set the capacity of the buffers used to read the response and to write the request.
+    ///
+    /// Larger buffers can speed up large transfers on fast networks, smaller ones reduce memory
+    /// usage. Sizes below 1 KiB are raised to 1 KiB. The length of the lines of the response head
+    /// is limited independently of the buffer size.
+    ///
+    /// The default is 8 KiB for both.
+    pub fn buffer_size(&mut self, read: usize, write: usize) {
+        self.base_settings.read_buffer_size = read.max(MIN_BUFFER_SIZE);
+        self.base_settings.write_buffer_size = write.max(MIN_BUFFER_SIZE);
+    }
+
     /// Set the maximum number of redirections this `Request` can perform.
     ///
     /// The default is 5.
@@ -173,9 +356,25 @@ impl Session {
         self.base_settings.follow_redirects = follow_redirects;
     }
 
+    /// Sets if the address family, IPv4 or IPv6, which last connected successfully to a host
+    /// should be tried first when connecting to that host again.
+    ///
+    /// This avoids paying the connection delay of a broken address family on every request.
+    /// The address families are remembered for the whole process.
+    ///
+    /// This value defaults to true.
+    pub fn remember_address_family(&mut self, remember_address_family: bool) {
+        self.base_settings.remember_address_family = remember_address_family;
+    }
+
     /// Sets a connect timeout for this request.
     ///
     /// The default is 30 seconds.
+    ///
+    /// It also bounds the DNS resolution of the host, but only when it is shorter than 30 seconds,
+    /// taking the overall timeout into account. Longer resolutions are left to the system resolver,
+    /// which usually gives up on its own within that time. When the resolution times out, it keeps
+    /// running on a background thread until the system resolver gives up.
     pub fn connect_timeout(&mut self, duration: Duration) {
         self.base_settings.connect_timeout = duration;
     }
@@ -187,6 +386,41 @@ impl Session {
         self.base_settings.read_timeout = duration;
     }
 
+    /// Sets a read timeout for the response body, which replaces the read timeout once the status
+    /// line and headers of the response are received.
+    ///
+    /// This lets the read timeout cover a server which takes a long time to respond, like a
+    /// long-polling endpoint, while a body which stalls is detected quickly.
+    ///
+    /// The default is to use the read timeout for the body too.
+    pub fn response_body_read_timeout(&mut self, duration: Duration) {
+        self.base_settings.response_body_read_timeout = Some(duration);
+    }
+
+    /// Set how long to wait for more data when reading a response body which ends with the
+    /// connection, because it has neither a `Content-Length` nor a chunked encoding.
+    ///
+    /// Once some of the body was read, the body ends when no data arrives for this duration, instead
+    /// of failing at the read timeout. This works around servers which keep the connection open
+    /// after sending such a body.
+    ///
+    /// The default is `None`, the body only ends when the connection is closed.
+    pub fn close_read_grace(&mut self, grace: Option<Duration>) {
+        self.base_settings.close_read_grace = grace;
+    }
+
+    /// Read and drop the rest of the response body, up to `max_bytes`, when the response is dropped
+    /// before its body was read entirely.
+    ///
+    /// Some servers log errors or penalize clients when the connection is closed before they sent
+    /// the whole response. Draining is also limited in time, to the read timeout but at most one
+    /// second, after which the connection is closed anyway.
+    ///
+    /// The default is to close the connection immediately.
+    pub fn drain_on_drop(&mut self, max_bytes: u64) {
+        self.base_settings.drain_on_drop = Some(max_bytes);
+    }
+
     /// Sets a timeout for the whole request.
     ///
     /// Applies after a TCP connection is established. Defaults to no timeout.
@@ -194,9 +428,72 @@ impl Session {
         self.base_settings.timeout = Some(duration);
     }
 
+    /// Set the longest connect timeout of the requests created from this `Session`.
+    ///
+    /// A longer `connect_timeout`, set on the `Session` or on a request, is reduced to this
+    /// duration when the request is prepared. Defaults to no maximum.
+    pub fn max_connect_timeout(&mut self, duration: Duration) {
+        self.base_settings.max_connect_timeout = Some(duration);
+    }
+
+    /// Set the longest read timeout of the requests created from this `Session`.
+    ///
+    /// A longer `read_timeout`, set on the `Session` or on a request, is reduced to this duration
+    /// when the request is prepared. Defaults to no maximum.
+    pub fn max_read_timeout(&mut self, duration: Duration) {
+        self.base_settings.max_read_timeout = Some(duration);
+    }
+
+    /// Set the longest timeout for the whole of the requests created from this `Session`.
+    ///
+    /// A longer `timeout`, set on the `Session` or on a request, is reduced to this duration when
+    /// the request is prepared, and requests without a timeout get this one. This guarantees that
+    /// no request outlives the maximum, whatever the code building it does. Defaults to no maximum.
+    pub fn max_timeout(&mut self, duration: Duration) {
+        self.base_settings.max_timeout = Some(duration);
+    }
+
+    /// Limit the number of requests created from this `Session` which can be sent at the same time.
+    ///
+    /// Sending a request waits until fewer than `max` requests are in progress. The wait counts
+    /// against the `timeout` of the request, if there is one. A request stops counting once its
+    /// `Response` is returned, reading the response body is not limited.
+    ///
+    /// Only the requests created after this call are limited. Defaults to no limit.
+    ///
+    /// # Panics
+    /// This method will panic if `max` is 0.
+    pub fn max_concurrent_requests(&mut self, max: usize) {
+        assert!(max > 0, "max_concurrent_requests must be greater than 0");
+        self.base_settings.request_limiter = Some(RequestLimiter::new(max));
+    }
+
+    /// Limit the rate of requests created from this `Session` and sent to the hosts matching
+    /// `host_pattern`.
+    ///
+    /// The pattern matches the host itself and its subdomains, `example.com` matches
+    /// `api.example.com` for instance. All the matching hosts share the same limit. Requests are
+    /// spaced evenly, sending a request waits until enough time has passed since the previous one.
+    /// The wait counts against the `timeout` of the request, an error is returned right away if
+    /// the request could not be sent in time.
+    ///
+    /// Calling this method again with the same pattern replaces the limit. Only the requests
+    /// created after this call are limited.
+    ///
+    /// # Panics
+    /// This method will panic if `requests_per_second` is not a positive number.
+    pub fn rate_limit(&mut self, host_pattern: impl AsRef<str>, requests_per_second: f64) {
+        let limit = RateLimit::new(host_pattern.as_ref(), requests_per_second);
+        self.base_settings
+            .rate_limits
+            .retain(|l| l.pattern() != limit.pattern());
+        self.base_settings.rate_limits.push(limit);
+    }
+
     /// Sets the proxy settigns for this request.
     ///
     /// If left untouched, the defaults are to use system proxy settings found in environment variables.
+    /// They are read once per process, see [`ProxySettings::reload_from_env`].
     pub fn proxy_settings(&mut self, settings: ProxySettings) {
         self.base_settings.proxy_settings = settings;
     }
@@ -210,15 +507,117 @@ impl Session {
         self.base_settings.default_charset = default_charset;
     }
 
+    /// Set the maximum number of bytes of the body kept while parsing a response as JSON, to show
+    /// the part of the body around the error if the parsing fails.
+    ///
+    /// The default is 64 KiB.
+    #[cfg(feature = "json")]
+    pub fn json_error_capture(&mut self, len: usize) {
+        self.base_settings.json_error_capture = len;
+    }
+
+    /// Sets if parsing a response as JSON fails with a `TrailingData` error when the JSON value is
+    /// followed by anything but whitespace, as `json_strict_eof` does.
+    ///
+    /// The default is false, in which case a generic `JsonDecode` error is returned instead.
+    #[cfg(feature = "json")]
+    pub fn reject_json_trailing_data(&mut self, reject: bool) {
+        self.base_settings.reject_json_trailing_data = reject;
+    }
+
+    /// Record the requests created from this `Session` and their responses in the given `HarRecorder`.
+    ///
+    /// Only the requests created after this call are recorded.
+    ///
+    /// This method only exists when the `har` feature is enabled.
+    #[cfg(feature = "har")]
+    pub fn record_to(&mut self, recorder: HarRecorder) {
+        self.base_settings.har_recorder = Some(recorder);
+    }
+
+    /// Keep the successful responses to the GET requests created from this `Session` in memory, and
+    /// revalidate them with the server instead of downloading them again.
+    ///
+    /// Responses with an `ETag` or a `Last-Modified` header are cached once their body is read to
+    /// the end, unless they have a `Cache-Control: no-store` header. The next GET requests to the
+    /// same URL are sent with the `If-None-Match` and `If-Modified-Since` headers, and when the
+    /// server answers `304 Not Modified`, the cached response is returned instead, with
+    /// `Response::from_cache` returning true. The freshness of the responses is never guessed,
+    /// every request is sent to the server. Requests which set one of these headers themselves do
+    /// not use the cache.
+    ///
+    /// The responses are cached by URL. Responses with a `Vary` header are not cached, and requests
+    /// with an `Authorization` header do not use the cache, so that the responses of a user are
+    /// never returned to another.
+    ///
+    /// Calling this method again replaces the cache with an empty one.
+    pub fn cache(&mut self, config: CacheConfig) {
+        self.base_settings.response_cache = Some(ResponseCache::new(config));
+    }
+
+    /// Answer the requests created from this `Session` with the canned responses of a
+    /// `MockTransport`, instead of connecting to the servers.
+    ///
+    /// This method only exists when the `testing` feature is enabled.
+    #[cfg(feature = "testing")]
+    pub fn mock(&mut self, transport: MockTransport) {
+        self.base_settings.mock = Some(transport);
+    }
+
     /// Sets if this `Request` will announce that it accepts compression.
     ///
     /// This value defaults to true. Note that this only lets the browser know that this `Request` supports
     /// compression, the server might choose not to compress the content.
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "__compress")]
     pub fn allow_compression(&mut self, allow_compression: bool) {
         self.base_settings.allow_compression = allow_compression;
     }
 
+    /// Sets the content encodings this `Request` will announce in the `Accept-Encoding` header.
+    ///
+    /// Only the encodings listed here will be decoded in the response. The default is to
+    /// announce and decode every supported encoding, `gzip` and `deflate`.
+    ///
+    /// # Panics
+    /// This method will panic if one of the encodings is not supported.
+    #[cfg(feature = "__compress")]
+    pub fn accept_encoding<I, S>(&mut self, encodings: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.try_accept_encoding(encodings)
+            .expect("unsupported content encoding");
+    }
+
+    /// Sets the content encodings this `Request` will announce in the `Accept-Encoding` header.
+    ///
+    /// Only the encodings listed here will be decoded in the response. The default is to
+    /// announce and decode every supported encoding, `gzip` and `deflate`.
+    #[cfg(feature = "__compress")]
+    pub fn try_accept_encoding<I, S>(&mut self, encodings: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.base_settings.accept_encoding = parse_accept_encoding(encodings)?;
+        Ok(())
+    }
+
+    /// Sets the maximum ratio between the size of a decoded response body and its compressed size.
+    ///
+    /// A small compressed body can expand to gigabytes, exhausting the memory of a client which
+    /// reads it entirely. Reading a body which expands more than this ratio fails with
+    /// `InvalidResponseKind::DecompressionBomb`. The check only starts after the first MiB of
+    /// decoded data, small bodies which compress very well are not refused. This is independent of
+    /// any limit on the size of the body.
+    ///
+    /// The default is 1000. `None` disables the check.
+    #[cfg(feature = "__compress")]
+    pub fn max_decompression_ratio(&mut self, ratio: Option<u32>) {
+        self.base_settings.max_decompression_ratio = ratio;
+    }
+
     /// Sets if this `Request` will accept invalid TLS certificates.
     ///
     /// Accepting invalid certificates implies that invalid hostnames are accepted
@@ -232,6 +631,7 @@ impl Session {
     /// to the list of trusted root CAs by your system.
     pub fn danger_accept_invalid_certs(&mut self, accept_invalid_certs: bool) {
         self.base_settings.accept_invalid_certs = accept_invalid_certs;
+        self.base_settings.tls_cache = TlsCache::default();
     }
 
     /// Sets if this `Request` will accept an invalid hostname in a TLS certificate.
@@ -243,10 +643,141 @@ impl Session {
     /// the hostname.
     pub fn danger_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) {
         self.base_settings.accept_invalid_hostnames = accept_invalid_hostnames;
+        self.base_settings.tls_cache = TlsCache::default();
     }
 
     /// Adds a root certificate that will be trusted.
+    ///
+    /// A certificate which was already added is ignored.
     pub fn add_root_certificate(&mut self, cert: Certificate) {
-        self.base_settings.root_certificates.0.push(cert);
+        self.base_settings.add_root_certificate(cert);
+    }
+
+    /// Adds all the root certificates of a PEM bundle file, which will be trusted.
+    ///
+    /// See [`parse_pem_certificates`](crate::parse_pem_certificates) for the parsing rules.
+    pub fn add_root_certificates_pem_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let pem = fs::read(path)?;
+        for cert in parse_pem_certificates(&pem)? {
+            self.base_settings.add_root_certificate(cert);
+        }
+        Ok(())
+    }
+
+    /// Get the number of root certificates added to this `Session`, without duplicates.
+    ///
+    /// This does not include the root certificates of the system or of the TLS backend.
+    pub fn root_certificate_count(&self) -> usize {
+        self.base_settings.root_certificates.0.len()
+    }
+
+    /// Sets the minimum TLS version requests will accept.
+    ///
+    /// By default, the minimum version of the TLS backend is used.
+    pub fn min_tls_version(&mut self, version: TlsVersion) {
+        self.base_settings.min_tls_version = Some(version);
+        self.base_settings.tls_cache = TlsCache::default();
+    }
+
+    /// Sets the maximum TLS version requests will accept.
+    ///
+    /// By default, the maximum version of the TLS backend is used.
+    pub fn max_tls_version(&mut self, version: TlsVersion) {
+        self.base_settings.max_tls_version = Some(version);
+        self.base_settings.tls_cache = TlsCache::default();
+    }
+
+    /// Sets the protocols offered using ALPN during the TLS handshake.
+    ///
+    /// This only affects the handshake, requests are always sent using HTTP/1.1.
+    pub fn alpn_protocols(&mut self, protocols: &[&str]) {
+        self.base_settings.alpn_protocols = protocols.iter().map(|protocol| protocol.to_string()).collect();
+        self.base_settings.tls_cache = TlsCache::default();
+    }
+
+    /// Set whether the credentials of the host are looked up in the netrc file of the user, like
+    /// curl does, when no `Authorization` header is set.
+    ///
+    /// The file is `~/.netrc`, or `%USERPROFILE%\_netrc` on Windows, unless another file is given
+    /// with `netrc_file`. The login and password of the first `machine` entry matching the host,
+    /// or of the `default` entry, are sent using basic authentication. A missing file is ignored.
+    /// After a redirection, only the `machine` entry of the host is used, and only if the redirection
+    /// stays on the origin of the request.
+    ///
+    /// The default is false.
+    ///
+    /// This method only exists when the `basic-auth` feature is enabled.
+    #[cfg(feature = "basic-auth")]
+    pub fn netrc(&mut self, enabled: bool) {
+        self.base_settings.netrc = enabled;
+    }
+
+    /// Look up the credentials of the host in the given netrc file, instead of the netrc file of
+    /// the user. This enables `netrc`.
+    ///
+    /// This method only exists when the `basic-auth` feature is enabled.
+    #[cfg(feature = "basic-auth")]
+    pub fn netrc_file(&mut self, path: impl Into<std::path::PathBuf>) {
+        self.base_settings.netrc = true;
+        self.base_settings.netrc_file = Some(path.into());
+    }
+
+    /// Sets a function called when the server answers with `401 Unauthorized`, to refresh an
+    /// expired token for instance.
+    ///
+    /// The function receives the challenges of the `WWW-Authenticate` headers of the response. If
+    /// it returns a value, a request is sent again once to the same URL with that value as its
+    /// `Authorization` header. If it returns `None` or if the second attempt also fails with a
+    /// 401, the 401 response is returned. The function is not called for a 401 response from
+    /// another origin than the one of the request, after a redirection.
+    pub fn on_auth_challenge<F>(&mut self, handler: F)
+    where
+        F: Fn(&WwwAuthenticate) -> Option<HeaderValue> + Send + Sync + 'static,
+    {
+        self.base_settings.auth_challenge_handler = Some(SkipDebug(Arc::new(handler)));
     }
+
+    /// Sets the `Signer` used to sign requests after their headers are finalized.
+    ///
+    /// See the [Signer trait](crate::Signer) for details.
+    pub fn sign_with<S>(&mut self, signer: S)
+    where
+        S: Signer + 'static,
+    {
+        self.base_settings.signer = Some(SkipDebug(Arc::new(signer)));
+    }
+}
+
+#[test]
+fn test_session_debug() {
+    let mut session = Session::new();
+    session.header("Authorization", "Bearer secret");
+    session.header("X-Trace", "1");
+    session.rate_limit("example.com", 10.0);
+
+    let debug = format!("{session:?}");
+    assert!(
+        debug.starts_with("Session { headers: 2, connect_timeout: 30s,"),
+        "{}",
+        debug
+    );
+    assert!(debug.contains("rate_limits: [\"example.com\"]"), "{}", debug);
+    assert!(debug.ends_with(", .. }"), "{}", debug);
+    assert!(!debug.contains("secret"), "{}", debug);
+}
+
+#[test]
+fn test_session_reset() {
+    let mut session = Session::new();
+    session.header("X-Trace", "1");
+    session.rate_limit("example.com", 1.0);
+    session.base_settings.rate_limits[0].wait(None).unwrap();
+    let tls_cache = session.base_settings.tls_cache.clone();
+
+    session.reset();
+    assert_eq!(session.base_settings.headers["x-trace"], "1");
+    assert_eq!(session.base_settings.rate_limits.len(), 1);
+    let deadline = std::time::Instant::now() + Duration::from_millis(100);
+    assert!(session.base_settings.rate_limits[0].wait(Some(deadline)).is_ok());
+    assert!(!tls_cache.same_as(&session.base_settings.tls_cache));
 }
@@ -1,52 +1,215 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+#[cfg(feature = "basic-auth")]
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
-use http::HeaderMap;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 
+use crate::cache::ResponseCache;
 #[cfg(feature = "charsets")]
 use crate::charsets::Charset;
+#[cfg(feature = "har")]
+use crate::har::HarRecorder;
+#[cfg(feature = "__compress")]
+use crate::parsing::compressed_reader::SUPPORTED_ENCODINGS;
+use crate::parsing::WwwAuthenticate;
+use crate::raw::ResponseLimits;
+use crate::request::limiter::{RateLimit, RequestLimiter};
 use crate::request::proxy::ProxySettings;
+use crate::request::Signer;
 use crate::skip_debug::SkipDebug;
-use crate::tls::Certificate;
+#[cfg(feature = "testing")]
+use crate::testing::MockTransport;
+use crate::tls::{certificate_der, Certificate, TlsCache, TlsVersion};
+
+/// Default capacity of the buffers used to read responses and write requests.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+/// Smallest capacity accepted for the buffers used to read responses and write requests.
+pub const MIN_BUFFER_SIZE: usize = 1024;
+
+/// Function called with the challenges of a 401 response, which returns the `Authorization`
+/// header to retry the request with.
+pub type AuthChallengeHandler = dyn Fn(&WwwAuthenticate) -> Option<HeaderValue> + Send + Sync;
 
 #[derive(Clone, Debug)]
 pub struct BaseSettings {
     pub headers: HeaderMap,
+    pub header_names: HashMap<HeaderName, String>,
+    pub preserve_header_case: bool,
     pub max_headers: usize,
+    pub strict_response_headers: bool,
+    pub reject_obs_fold: bool,
+    pub tolerate_truncated_body: bool,
+    pub lenient_chunked_termination: bool,
+    pub keep_hop_headers: bool,
+    pub expected_content_types: Vec<String>,
+    pub require_content_type: bool,
+    pub read_buffer_size: usize,
+    pub write_buffer_size: usize,
     pub max_redirections: u32,
     pub follow_redirects: bool,
     pub connect_timeout: Duration,
+    pub remember_address_family: bool,
+    pub preresolved: Option<Vec<SocketAddr>>,
     pub read_timeout: Duration,
+    pub response_body_read_timeout: Option<Duration>,
+    pub close_read_grace: Option<Duration>,
+    pub drain_on_drop: Option<u64>,
     pub timeout: Option<Duration>,
+    pub max_connect_timeout: Option<Duration>,
+    pub max_read_timeout: Option<Duration>,
+    pub max_timeout: Option<Duration>,
     pub proxy_settings: ProxySettings,
     pub accept_invalid_certs: bool,
     pub accept_invalid_hostnames: bool,
     pub root_certificates: SkipDebug<Vec<Certificate>>,
+    pub min_tls_version: Option<TlsVersion>,
+    pub max_tls_version: Option<TlsVersion>,
+    pub alpn_protocols: Vec<String>,
+    pub tls_server_name: Option<String>,
+    pub tls_cache: TlsCache,
+    pub signer: Option<SkipDebug<Arc<dyn Signer>>>,
+    pub auth_challenge_handler: Option<SkipDebug<Arc<AuthChallengeHandler>>>,
+    pub request_limiter: Option<RequestLimiter>,
+    pub rate_limits: Vec<RateLimit>,
+    pub no_compression: bool,
+    pub absolute_form: bool,
+    pub te_trailers: bool,
+    /// `Content-Type` header of the session, which the type of the body of a request replaces.
+    pub inherited_content_type: Option<HeaderValue>,
 
     #[cfg(feature = "charsets")]
     pub default_charset: Option<Charset>,
-    #[cfg(feature = "flate2")]
+    #[cfg(feature = "json")]
+    pub json_error_capture: usize,
+    #[cfg(feature = "json")]
+    pub reject_json_trailing_data: bool,
+    #[cfg(feature = "basic-auth")]
+    pub netrc: bool,
+    #[cfg(feature = "basic-auth")]
+    pub netrc_file: Option<PathBuf>,
+    #[cfg(feature = "__compress")]
     pub allow_compression: bool,
+    #[cfg(feature = "__compress")]
+    pub accept_encoding: Vec<&'static str>,
+    #[cfg(feature = "__compress")]
+    pub max_decompression_ratio: Option<u32>,
+    pub response_cache: Option<ResponseCache>,
+    #[cfg(feature = "har")]
+    pub har_recorder: Option<HarRecorder>,
+    #[cfg(feature = "testing")]
+    pub mock: Option<MockTransport>,
+}
+
+impl BaseSettings {
+    /// Add a root certificate, unless a certificate with the same DER encoding was already added.
+    ///
+    /// The certificates are kept in the order they were first added.
+    pub(crate) fn add_root_certificate(&mut self, cert: Certificate) {
+        if let Some(der) = certificate_der(&cert) {
+            let certs = &self.root_certificates.0;
+            if certs.iter().any(|other| certificate_der(other).as_ref() == Some(&der)) {
+                return;
+            }
+        }
+        self.root_certificates.0.push(cert);
+        self.tls_cache = TlsCache::default();
+    }
+
+    /// Clamp the timeouts to their maximum values, the maximum timeout is also the default one.
+    pub(crate) fn apply_timeout_caps(&mut self) {
+        if let Some(max) = self.max_connect_timeout {
+            self.connect_timeout = self.connect_timeout.min(max);
+        }
+        if let Some(max) = self.max_read_timeout {
+            self.read_timeout = self.read_timeout.min(max);
+            self.response_body_read_timeout = self.response_body_read_timeout.map(|timeout| timeout.min(max));
+        }
+        if let Some(max) = self.max_timeout {
+            self.timeout = Some(self.timeout.map_or(max, |timeout| timeout.min(max)));
+        }
+    }
+
+    /// Limits applied to the head of the responses.
+    pub(crate) fn response_limits(&self) -> ResponseLimits {
+        ResponseLimits {
+            max_headers: self.max_headers,
+            strict_headers: self.strict_response_headers,
+            reject_obs_fold: self.reject_obs_fold,
+        }
+    }
 }
 
 impl Default for BaseSettings {
     fn default() -> Self {
         BaseSettings {
             headers: HeaderMap::new(),
+            header_names: HashMap::new(),
+            preserve_header_case: false,
             max_headers: 100,
+            strict_response_headers: false,
+            reject_obs_fold: false,
+            tolerate_truncated_body: false,
+            lenient_chunked_termination: true,
+            keep_hop_headers: false,
+            expected_content_types: Vec::new(),
+            require_content_type: false,
+            read_buffer_size: DEFAULT_BUFFER_SIZE,
+            write_buffer_size: DEFAULT_BUFFER_SIZE,
             max_redirections: 5,
             follow_redirects: true,
             connect_timeout: Duration::from_secs(30),
+            remember_address_family: true,
+            preresolved: None,
             read_timeout: Duration::from_secs(30),
+            response_body_read_timeout: None,
+            close_read_grace: None,
+            drain_on_drop: None,
             timeout: None,
-            proxy_settings: ProxySettings::from_env(),
+            max_connect_timeout: None,
+            max_read_timeout: None,
+            max_timeout: None,
+            proxy_settings: ProxySettings::cached_from_env(),
             accept_invalid_certs: false,
             accept_invalid_hostnames: false,
             root_certificates: SkipDebug(Vec::new()),
+            min_tls_version: None,
+            max_tls_version: None,
+            alpn_protocols: Vec::new(),
+            tls_server_name: None,
+            tls_cache: TlsCache::default(),
+            signer: None,
+            auth_challenge_handler: None,
+            request_limiter: None,
+            rate_limits: Vec::new(),
+            no_compression: false,
+            absolute_form: false,
+            te_trailers: false,
+            inherited_content_type: None,
 
             #[cfg(feature = "charsets")]
             default_charset: None,
-            #[cfg(feature = "flate2")]
+            #[cfg(feature = "json")]
+            json_error_capture: 64 * 1024,
+            #[cfg(feature = "json")]
+            reject_json_trailing_data: false,
+            #[cfg(feature = "basic-auth")]
+            netrc: false,
+            #[cfg(feature = "basic-auth")]
+            netrc_file: None,
+            #[cfg(feature = "__compress")]
             allow_compression: true,
+            #[cfg(feature = "__compress")]
+            accept_encoding: SUPPORTED_ENCODINGS.to_vec(),
+            #[cfg(feature = "__compress")]
+            max_decompression_ratio: Some(1000),
+            response_cache: None,
+            #[cfg(feature = "har")]
+            har_recorder: None,
+            #[cfg(feature = "testing")]
+            mock: None,
         }
     }
 }
@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+
+use http::{HeaderMap, Method};
+use url::Url;
+
+use crate::error::Result;
+
+/// A hook used to sign requests right before they are sent.
+///
+/// The signer is called when the request is prepared, after every other header, including `Host`,
+/// has been set. It receives the method, the URL and the headers of the request, as well as a
+/// function which streams the body of the request into the given writer. The body can be written
+/// as many times as needed, it will still be sent intact afterwards.
+///
+/// The signer is called again before a request is sent to another URL or with other headers, after
+/// a redirection or an authentication challenge. It receives the headers it set the previous time,
+/// so it should replace them rather than append to them.
+///
+/// Closures with the same signature as `Signer::sign` implement this trait.
+pub trait Signer: Send + Sync {
+    /// Sign the request, usually by adding headers to it.
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &mut HeaderMap,
+        body: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<()>;
+}
+
+impl<F> Signer for F
+where
+    F: Fn(&Method, &Url, &mut HeaderMap, &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> Result<()> + Send + Sync,
+{
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &mut HeaderMap,
+        body: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<()> {
+        self(method, url, headers, body)
+    }
+}
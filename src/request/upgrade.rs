@@ -0,0 +1,137 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use http::header::CONNECTION;
+use http::StatusCode;
+
+use crate::error::{ErrorKind, Result};
+use crate::parsing::Response;
+use crate::request::body::Body;
+use crate::request::{header_insert, PreparedRequest};
+use crate::streams::BaseStream;
+
+/// A connection switched to another protocol by a `101 Switching Protocols` response, returned by
+/// `PreparedRequest::send_upgrade`.
+///
+/// Reading starts with the bytes sent by the server right after the response, which were received
+/// along with it.
+pub struct UpgradedStream {
+    buffered: Vec<u8>,
+    pos: usize,
+    stream: BaseStream,
+}
+
+impl UpgradedStream {
+    /// Sets the read timeout of the connection. It starts as the read timeout of the request.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+}
+
+impl Read for UpgradedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.buffered.len() {
+            let n = (self.buffered.len() - self.pos).min(buf.len());
+            buf[..n].copy_from_slice(&self.buffered[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
+        self.stream.read(buf)
+    }
+}
+
+impl Write for UpgradedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl fmt::Debug for UpgradedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpgradedStream")
+            .field("buffered", &(self.buffered.len() - self.pos))
+            .finish()
+    }
+}
+
+impl<B: Body> PreparedRequest<B> {
+    /// Send this request to switch the connection to another protocol, like WebSocket, using the
+    /// `Upgrade` mechanism of HTTP/1.1.
+    ///
+    /// The `Upgrade` header and the headers required by the protocol, like `Sec-WebSocket-Key`,
+    /// must be set on the request, the `Connection: upgrade` header is set by this method. If the
+    /// server answers with `101 Switching Protocols`, the response is returned along with the
+    /// connection, on which the new protocol is spoken.
+    ///
+    /// Redirections are not followed, any other response fails with `ErrorKind::UpgradeRefused`.
+    /// The timeout of the request does not apply to the upgraded connection, its read timeout does.
+    pub fn send_upgrade(&mut self) -> Result<(Response, UpgradedStream)> {
+        let headers = self.base_settings.headers.clone();
+        let res =
+            header_insert(&mut self.base_settings.headers, CONNECTION, "upgrade").and_then(|_| self.send_hops(true));
+        self.base_settings.headers = headers;
+
+        let mut resp = res?;
+        if resp.status() != StatusCode::SWITCHING_PROTOCOLS {
+            let (status, headers) = resp.into_head();
+            return Err(ErrorKind::UpgradeRefused { status, headers }.into());
+        }
+
+        let (buffered, mut stream) = resp.take_connection();
+        stream.disarm_timeout();
+        Ok((
+            resp,
+            UpgradedStream {
+                buffered,
+                pos: 0,
+                stream,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use http::Method;
+
+    use super::UpgradedStream;
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+    use crate::PreparedRequest;
+
+    #[test]
+    fn test_take_connection_keeps_buffered_bytes() {
+        let raw = b"HTTP/1.1 101 Switching Protocols\r\n\
+            Upgrade: websocket\r\n\
+            Connection: Upgrade\r\n\r\n\
+            \x81\x05hello"
+            .to_vec();
+        let req = PreparedRequest::new(Method::GET, "http://localhost/chat");
+        let mut resp = parse_response(BaseStream::mock(raw), &req, req.url()).unwrap();
+        let (buffered, stream) = resp.take_connection();
+        assert_eq!(buffered, b"\x81\x05hello");
+
+        let mut upgraded = UpgradedStream {
+            buffered,
+            pos: 0,
+            stream,
+        };
+        let mut first = [0; 3];
+        upgraded.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"\x81\x05h");
+        let mut rest = Vec::new();
+        upgraded.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"ello");
+        upgraded.write_all(b"frame").unwrap();
+
+        // The response has no body, and its connection is gone.
+        assert_eq!(resp.text().unwrap(), "");
+    }
+}
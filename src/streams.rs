@@ -1,25 +1,19 @@
-#[cfg(test)]
-use std::io::Cursor;
-use std::io::{self, Read, Write};
-#[cfg(not(windows))]
-use std::net::Shutdown;
-use std::net::TcpStream;
-#[cfg(windows)]
-use std::os::{
-    raw::c_int,
-    windows::{io::AsRawSocket, raw::SOCKET},
-};
+use std::cell::Cell;
+use std::io::{self, Cursor, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
 use std::sync::mpsc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use http::header::{CONNECTION, CONTENT_LENGTH, USER_AGENT};
 use url::{Host, Url};
 
 use crate::happy;
 use crate::parsing::buffers::BufReaderWrite;
-use crate::parsing::response::parse_response_head;
+use crate::parsing::response::{parse_response_head, ResponseHead};
+use crate::parsing::ConnectionInfo;
 use crate::request::BaseSettings;
-use crate::tls::{TlsHandshaker, TlsStream};
+use crate::tls::TlsStream;
 use crate::{ErrorKind, Result};
 
 pub struct ConnectInfo<'a> {
@@ -27,8 +21,19 @@ pub struct ConnectInfo<'a> {
     pub proxy: Option<&'a Url>,
     pub base_settings: &'a BaseSettings,
     pub deadline: Option<Instant>,
+    /// Addresses to connect to instead of resolving the host.
+    pub preresolved: Option<&'a [SocketAddr]>,
+    /// Name to use in the TLS handshake with the server instead of its host.
+    pub tls_server_name: Option<&'a str>,
+    /// Time spent in the TLS handshake, set once connected.
+    pub handshake_time: Cell<Option<Duration>>,
+    /// Addresses of the TCP connection, set once connected.
+    pub connection: Cell<Option<ConnectionInfo>>,
 }
 
+/// Schemes of the URLs which requests can be sent to.
+pub const SUPPORTED_SCHEMES: &[&str] = &["http", "https"];
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum BaseStream {
@@ -43,7 +48,9 @@ pub enum BaseStream {
     Tunnel {
         stream: Box<TlsStream<BufReaderWrite<BaseStream>>>,
     },
-    #[cfg(test)]
+    /// The connection was handed over to an `UpgradedStream`.
+    Detached,
+    /// Data kept in memory, like a response served from the cache or a mock response.
     Mock(Cursor<Vec<u8>>),
 }
 
@@ -64,20 +71,17 @@ impl BaseStream {
         }?;
 
         if let Some(proxy_url) = info.proxy {
-            if info.url.scheme() == "https" {
-                return BaseStream::initiate_tunnel(stream, proxy_url, info.url, info.base_settings);
+            if info.url.scheme() == "https" && !info.base_settings.proxy_settings.https_via_absolute_form() {
+                return BaseStream::initiate_tunnel(stream, proxy_url, info);
             }
         }
 
         Ok(stream)
     }
 
-    fn initiate_tunnel(
-        mut stream: BaseStream,
-        proxy_url: &Url,
-        remote_url: &Url,
-        base_settings: &BaseSettings,
-    ) -> Result<BaseStream> {
+    fn initiate_tunnel(mut stream: BaseStream, proxy_url: &Url, info: &ConnectInfo) -> Result<BaseStream> {
+        let remote_url = info.url;
+        let base_settings = info.base_settings;
         let remote_host = remote_url.host_str().ok_or(ErrorKind::InvalidUrlHost)?;
         let remote_port = remote_url.port_or_known_default().ok_or(ErrorKind::InvalidUrlPort)?;
         let proxy_host = proxy_url.host_str().ok_or(ErrorKind::InvalidUrlHost)?;
@@ -88,30 +92,56 @@ impl BaseStream {
             remote_host, remote_port, proxy_host, proxy_port,
         );
 
-        write!(stream, "CONNECT {remote_host}:{remote_port} HTTP/1.1\r\n")?;
-        write!(stream, "Host: {proxy_host}:{proxy_port}\r\n")?;
-        write!(stream, "Connection: close\r\n")?;
-        write!(stream, "\r\n")?;
+        let connect_headers = base_settings.proxy_settings.connect_headers();
 
-        let mut stream = BufReaderWrite::new(stream);
-        let (status, _) = parse_response_head(&mut stream, base_settings.max_headers)?;
+        let mut head = Vec::new();
+        write!(head, "CONNECT {remote_host}:{remote_port} HTTP/1.1\r\n")?;
+        // The Host header of a CONNECT request is the authority of the tunnel, not the proxy.
+        write!(head, "Host: {remote_host}:{remote_port}\r\n")?;
+        if let Some(user_agent) = base_settings.headers.get(USER_AGENT) {
+            if !connect_headers.contains_key(USER_AGENT) {
+                write!(head, "User-Agent: ")?;
+                head.extend_from_slice(user_agent.as_bytes());
+                write!(head, "\r\n")?;
+            }
+        }
+        if !connect_headers.contains_key(CONNECTION) {
+            write!(head, "Connection: close\r\n")?;
+        }
+        for (key, value) in connect_headers {
+            write!(head, "{}: ", key.as_str())?;
+            head.extend_from_slice(value.as_bytes());
+            write!(head, "\r\n")?;
+        }
+        write!(head, "\r\n")?;
+        stream.write_all(&head)?;
+
+        let mut stream = BufReaderWrite::with_capacity(base_settings.read_buffer_size, stream);
+        let ResponseHead { status, headers, .. } = parse_response_head(&mut stream, &base_settings.response_limits())?;
 
         debug!("tunnel response status code is {}", status);
 
         if !status.is_success() {
             // Error initializaing tunnel, get status code and up to 10 KiB of data from the body.
+            // The Content-Length is honored since the proxy might keep the connection open.
+            let limit = headers
+                .get(CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
+                .map_or(10 * 1024, |len| len.min(10 * 1024));
             let mut buf = Vec::with_capacity(2048);
-            stream.take(10 * 1024).read_to_end(&mut buf)?;
+            stream.take(limit).read_to_end(&mut buf)?;
             let err = ErrorKind::ConnectError {
                 status_code: status,
+                headers,
                 body: buf,
             };
             return Err(err.into());
         }
 
-        let mut handshaker = TlsHandshaker::new();
-        apply_base_settings(&mut handshaker, base_settings);
-        let stream = handshaker.handshake(remote_host, stream)?;
+        let start = Instant::now();
+        let handshaker = base_settings.tls_cache.handshaker(base_settings)?;
+        let stream = handshaker.handshake(info.tls_server_name.unwrap_or(remote_host), stream)?;
+        info.handshake_time.set(Some(start.elapsed()));
 
         Ok(BaseStream::Tunnel {
             stream: Box::new(stream),
@@ -119,15 +149,38 @@ impl BaseStream {
     }
 
     fn connect_tcp(host: &Host<&str>, port: u16, info: &ConnectInfo) -> Result<(TcpStream, Option<mpsc::Sender<()>>)> {
-        let stream = happy::connect(host, port, info.base_settings.connect_timeout, info.deadline)?;
+        let settings = info.base_settings;
+        let resolved;
+        let addrs = match info.preresolved {
+            Some(addrs) => addrs,
+            None => {
+                resolved = happy::resolve(host, port, settings.connect_timeout, info.deadline)?;
+                &resolved[..]
+            }
+        };
+        let stream = happy::connect(
+            &host.to_string(),
+            addrs,
+            settings.connect_timeout,
+            info.deadline,
+            settings.remember_address_family,
+        )?;
         stream.set_read_timeout(Some(info.base_settings.read_timeout))?;
+        // The addresses are only informative, the request is not failed when they are unavailable.
+        if let (Ok(peer), Ok(local)) = (stream.peer_addr(), stream.local_addr()) {
+            info.connection.set(Some(ConnectionInfo {
+                peer,
+                local,
+                proxied: info.proxy.is_some(),
+            }));
+        }
         let timeout = info
             .deadline
             .map(|deadline| -> Result<mpsc::Sender<()>> {
-                #[cfg(not(windows))]
+                // The timeout thread owns a duplicate of the socket, so it never uses a handle which
+                // might have been closed and reused. Shutting down is safe while another thread is
+                // reading from or writing to the socket, unlike closing it.
                 let stream = stream.try_clone()?;
-                #[cfg(windows)]
-                let socket = stream.as_raw_socket();
 
                 let (tx, rx) = mpsc::channel();
                 thread::spawn(move || {
@@ -138,19 +191,7 @@ impl BaseStream {
 
                     if shutdown {
                         drop(rx);
-
-                        #[cfg(not(windows))]
                         let _ = stream.shutdown(Shutdown::Both);
-
-                        #[cfg(windows)]
-                        extern "system" {
-                            fn closesocket(socket: SOCKET) -> c_int;
-                        }
-
-                        #[cfg(windows)]
-                        unsafe {
-                            closesocket(socket);
-                        }
                     }
                 });
                 Ok(tx)
@@ -161,13 +202,38 @@ impl BaseStream {
 
     fn connect_tls(host: &Host<&str>, port: u16, info: &ConnectInfo) -> Result<BaseStream> {
         let (stream, timeout) = BaseStream::connect_tcp(host, port, info)?;
-        let mut handshaker = TlsHandshaker::new();
-        apply_base_settings(&mut handshaker, info.base_settings);
-        let stream = handshaker.handshake(&host.to_string(), stream)?;
+        let start = Instant::now();
+        let handshaker = info.base_settings.tls_cache.handshaker(info.base_settings)?;
+        // When connecting to a proxy, the host is the one of the proxy.
+        let server_name = match (info.proxy, info.tls_server_name) {
+            (None, Some(name)) => name.to_owned(),
+            _ => host.to_string(),
+        };
+        let stream = handshaker.handshake(&server_name, stream)?;
+        info.handshake_time.set(Some(start.elapsed()));
         Ok(BaseStream::Tls { stream, timeout })
     }
 
-    #[cfg(test)]
+    /// Set the read timeout of the underlying socket.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            BaseStream::Plain { stream, .. } => stream.set_read_timeout(timeout),
+            BaseStream::Tls { stream, .. } => stream.get_ref().set_read_timeout(timeout),
+            BaseStream::Tunnel { stream } => stream.get_ref().get_ref().set_read_timeout(timeout),
+            BaseStream::Detached | BaseStream::Mock(_) => Ok(()),
+        }
+    }
+
+    /// Stop the timeout thread from shutting the connection down at the deadline of the request.
+    pub fn disarm_timeout(&mut self) {
+        match self {
+            BaseStream::Plain { timeout, .. } | BaseStream::Tls { timeout, .. } => *timeout = None,
+            BaseStream::Tunnel { stream } => stream.get_mut().get_mut().disarm_timeout(),
+            BaseStream::Detached | BaseStream::Mock(_) => {}
+        }
+    }
+
+    /// Create a stream reading the given data, and dropping the data written to it.
     pub fn mock(bytes: Vec<u8>) -> BaseStream {
         BaseStream::Mock(Cursor::new(bytes))
     }
@@ -180,7 +246,7 @@ impl Read for BaseStream {
             BaseStream::Plain { stream, timeout } => read_timeout(stream, buf, timeout),
             BaseStream::Tls { stream, timeout } => read_timeout(stream, buf, timeout),
             BaseStream::Tunnel { stream } => stream.read(buf),
-            #[cfg(test)]
+            BaseStream::Detached => Ok(0),
             BaseStream::Mock(s) => s.read(buf),
         }
     }
@@ -193,8 +259,8 @@ impl Write for BaseStream {
             BaseStream::Plain { stream, .. } => stream.write(buf),
             BaseStream::Tls { stream, .. } => stream.write(buf),
             BaseStream::Tunnel { stream } => stream.write(buf),
-            #[cfg(test)]
-            _ => Ok(0),
+            BaseStream::Detached => Err(io::ErrorKind::NotConnected.into()),
+            BaseStream::Mock(_) => Ok(buf.len()),
         }
     }
 
@@ -204,42 +270,70 @@ impl Write for BaseStream {
             BaseStream::Plain { stream, .. } => stream.flush(),
             BaseStream::Tls { stream, .. } => stream.flush(),
             BaseStream::Tunnel { stream } => stream.flush(),
-            #[cfg(test)]
-            _ => Ok(()),
+            BaseStream::Detached => Ok(()),
+            BaseStream::Mock(_) => Ok(()),
         }
     }
 }
 
+/// The error of reads on a socket shut down by another thread on Windows, which has no `ErrorKind`.
+const WSAESHUTDOWN: i32 = 10058;
+
 fn read_timeout(stream: &mut impl Read, buf: &mut [u8], timeout: &Option<mpsc::Sender<()>>) -> io::Result<usize> {
-    match stream.read(buf) {
-        Ok(0) => {
-            #[cfg(unix)]
-            if let Some(timeout) = timeout {
-                // On Unix we get a 0 read when the connection is shutdown by the timeout thread.
-                if !buf.is_empty() && timeout.send(()).is_err() {
-                    return Err(io::ErrorKind::TimedOut.into());
-                }
-            }
-            Ok(0)
-        }
-        Ok(read) => Ok(read),
-        Err(err) => {
-            #[cfg(windows)]
-            if let Some(timeout) = timeout {
-                // On Windows we get a ConnectionAborted when the connection is shutdown by the timeout thread.
-                if err.kind() == io::ErrorKind::ConnectionAborted && timeout.send(()).is_err() {
-                    return Err(io::ErrorKind::TimedOut.into());
-                }
+    let res = stream.read(buf);
+    if let Some(timeout) = timeout {
+        // When the connection is shutdown by the timeout thread, we get a 0 read on Unix and a 0
+        // read or an error such as ConnectionAborted or WSAESHUTDOWN on Windows. The channel is
+        // closed once that happened. Sending on the channel stops the timeout thread, so errors
+        // which cannot come from the shutdown, like the expiry of the read timeout, must keep it
+        // running.
+        let maybe_shutdown = match &res {
+            Ok(read) => *read == 0 && !buf.is_empty(),
+            Err(err) => {
+                matches!(
+                    err.kind(),
+                    io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::NotConnected
+                        | io::ErrorKind::BrokenPipe
+                ) || (cfg!(windows) && err.raw_os_error() == Some(WSAESHUTDOWN))
             }
-            Err(err)
+        };
+        if maybe_shutdown && timeout.send(()).is_err() {
+            return Err(io::ErrorKind::TimedOut.into());
         }
     }
+    res
 }
 
-fn apply_base_settings(handshaker: &mut TlsHandshaker, base_settings: &BaseSettings) {
-    handshaker.danger_accept_invalid_certs(base_settings.accept_invalid_certs);
-    handshaker.danger_accept_invalid_hostnames(base_settings.accept_invalid_hostnames);
-    for cert in &base_settings.root_certificates.0 {
-        handshaker.add_root_certificate(cert.clone());
+#[test]
+fn test_read_timeout_keeps_deadline() {
+    struct Failing(io::ErrorKind);
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(self.0.into())
+        }
     }
+
+    let (tx, rx) = mpsc::channel();
+    let timeout = Some(tx);
+    for kind in [
+        io::ErrorKind::WouldBlock,
+        io::ErrorKind::TimedOut,
+        io::ErrorKind::Interrupted,
+    ] {
+        let err = read_timeout(&mut Failing(kind), &mut [0; 8], &timeout).unwrap_err();
+        assert_eq!(err.kind(), kind);
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+    }
+
+    // The end of the body stops the timeout thread.
+    read_timeout(&mut &b""[..], &mut [0; 8], &timeout).unwrap();
+    assert_eq!(rx.try_recv(), Ok(()));
+
+    // Once the timeout thread shut the connection down, the read fails with a timeout.
+    drop(rx);
+    let err = read_timeout(&mut Failing(io::ErrorKind::ConnectionAborted), &mut [0; 8], &timeout).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
 }
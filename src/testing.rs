@@ -0,0 +1,231 @@
+//! Utilities to test code using attohttpc without a network.
+//!
+//! A `MockTransport` installed on a `Session` with `Session::mock`, or on a single request with
+//! `RequestBuilder::mock`, answers the requests with canned responses instead of connecting to the
+//! servers. The canned responses are parsed like real ones, so decompression, charsets and
+//! redirections behave as they would with a server.
+//!
+//! # Example
+//! ```
+//! use attohttpc::testing::{MockResponse, MockTransport};
+//! use attohttpc::Method;
+//!
+//! let transport = MockTransport::new().on(
+//!     Method::GET,
+//!     "http://api.example.com/users/*",
+//!     MockResponse::new(200).header("Content-Type", "application/json").body(r#"{"name":"alice"}"#),
+//! );
+//! let mut session = attohttpc::Session::new();
+//! session.mock(transport.clone());
+//!
+//! let text = session.get("http://api.example.com/users/1").send()?.text()?;
+//! assert_eq!(text, r#"{"name":"alice"}"#);
+//! assert_eq!(transport.requests().len(), 1);
+//! # Ok::<(), attohttpc::Error>(())
+//! ```
+//!
+//! This module only exists when the `testing` feature is enabled.
+
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use http::{HeaderMap, Method, StatusCode};
+use url::Url;
+
+use crate::streams::BaseStream;
+
+/// A canned response returned by a `MockTransport`.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// Create a response with the given status code and an empty body.
+    ///
+    /// # Panics
+    /// Panics if the status code is not between 100 and 999.
+    pub fn new(status: u16) -> MockResponse {
+        MockResponse {
+            status: StatusCode::from_u16(status).expect("invalid status code"),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Add a header to the response.
+    ///
+    /// A `Content-Length` header is added automatically unless the response sets it, or sets
+    /// `Transfer-Encoding`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> MockResponse {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the body of the response, as it is sent by the server.
+    ///
+    /// If the response has a `Content-Encoding` header, the body must be encoded accordingly.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> MockResponse {
+        self.body = body.into();
+        self
+    }
+
+    /// Serialize the response like a server would send it.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status.as_str(),
+            self.status.canonical_reason().unwrap_or("")
+        )
+        .into_bytes();
+        let has_framing = self.headers.iter().any(|(name, _)| {
+            name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("transfer-encoding")
+        });
+        for (name, value) in &self.headers {
+            bytes.extend(format!("{name}: {value}\r\n").as_bytes());
+        }
+        if !has_framing {
+            bytes.extend(format!("Content-Length: {}\r\n", self.body.len()).as_bytes());
+        }
+        bytes.extend(b"\r\n");
+        bytes.extend(&self.body);
+        bytes
+    }
+}
+
+/// A request received by a `MockTransport`.
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// Method of the request.
+    pub method: Method,
+    /// URL of the request.
+    pub url: Url,
+    /// Headers of the request, as they would be sent.
+    pub headers: HeaderMap,
+}
+
+#[derive(Debug, Default)]
+struct Routes {
+    routes: Vec<(Method, String, MockResponse)>,
+    requests: Vec<MockRequest>,
+}
+
+/// Answers requests with canned responses, chosen by method and URL, instead of connecting to the
+/// servers.
+///
+/// Clones of a `MockTransport` share the same responses and received requests. Requests which do
+/// not match any response fail with a `ConnectionRefused` I/O error.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    inner: Arc<Mutex<Routes>>,
+}
+
+/// Check if the URL matches the pattern, in which `*` matches any sequence of characters.
+fn url_matches(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let mut rest = match url.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (idx, part) in parts.iter().enumerate() {
+        if idx == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+impl MockTransport {
+    /// Create a new `MockTransport` without any response.
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Answer the requests with the given method and a URL matching `url_pattern` with `response`.
+    ///
+    /// The pattern is compared to the whole URL, as serialized by the `url` crate, and `*` matches
+    /// any sequence of characters. When several patterns match, the first one added is used.
+    pub fn on(self, method: Method, url_pattern: impl Into<String>, response: MockResponse) -> MockTransport {
+        self.lock().routes.push((method, url_pattern.into(), response));
+        self
+    }
+
+    /// Get the requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Routes> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record the request and get a stream reading the response to send back.
+    pub(crate) fn connect(&self, method: &Method, url: &Url, headers: &HeaderMap) -> io::Result<BaseStream> {
+        let mut inner = self.lock();
+        inner.requests.push(MockRequest {
+            method: method.clone(),
+            url: url.clone(),
+            headers: headers.clone(),
+        });
+
+        let response = inner
+            .routes
+            .iter()
+            .find(|(m, pattern, _)| m == method && url_matches(pattern, url.as_str()))
+            .map(|(_, _, response)| response.to_bytes());
+        match response {
+            Some(response) => Ok(BaseStream::mock(response)),
+            None => Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("no mock response for {method} {url}"),
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockTransport")
+            .field("routes", &self.lock().routes.len())
+            .finish()
+    }
+}
+
+#[test]
+fn test_url_matches() {
+    assert!(url_matches("http://example.com/", "http://example.com/"));
+    assert!(!url_matches("http://example.com/", "http://example.com/a"));
+    assert!(url_matches("http://example.com/*", "http://example.com/a/b?c"));
+    assert!(url_matches("http://*.example.com/*/x", "http://api.example.com/a/b/x"));
+    assert!(!url_matches("http://*.example.com/*/x", "http://api.example.org/a/b/x"));
+    assert!(url_matches("*", "https://anything/"));
+    assert!(url_matches("*a*a", "aa"));
+    assert!(!url_matches("*a*a", "a"));
+}
+
+#[test]
+fn test_mock_response_bytes() {
+    let response = MockResponse::new(404).header("X-A", "1").body("missing");
+    assert_eq!(
+        response.to_bytes(),
+        b"HTTP/1.1 404 Not Found\r\nX-A: 1\r\nContent-Length: 7\r\n\r\nmissing"
+    );
+
+    let response = MockResponse::new(200)
+        .header("Transfer-Encoding", "chunked")
+        .body("0\r\n\r\n");
+    assert_eq!(
+        response.to_bytes(),
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n"
+    );
+}
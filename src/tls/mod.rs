@@ -1,3 +1,11 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use url::Url;
+
+use crate::request::BaseSettings;
+use crate::{ErrorKind, Result};
+
 #[cfg(feature = "tls-native")]
 mod native_tls_impl;
 
@@ -15,3 +23,164 @@ pub use rustls_impl::*;
 
 #[cfg(all(not(feature = "tls-native"), not(feature = "__rustls")))]
 pub use no_tls_impl::*;
+
+/// Whether one of the TLS backends was enabled by features.
+pub const TLS_ENABLED: bool = cfg!(any(feature = "tls-native", feature = "__rustls"));
+
+/// Fail early if the URL requires TLS but no TLS backend was enabled.
+pub fn ensure_enabled_for(url: &Url) -> Result<()> {
+    if !TLS_ENABLED && url.scheme() == "https" {
+        return Err(ErrorKind::TlsDisabled {
+            url: Some(url.to_string()),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Check that the name can be sent as the server name of the TLS handshake, to verify the
+/// certificate of the server.
+pub fn check_server_name(name: &str) -> Result<()> {
+    #[cfg(all(feature = "__rustls", not(feature = "tls-native")))]
+    use std::convert::TryFrom;
+
+    #[cfg(all(feature = "__rustls", not(feature = "tls-native")))]
+    let valid = rustls::pki_types::ServerName::try_from(name).is_ok();
+    #[cfg(not(all(feature = "__rustls", not(feature = "tls-native"))))]
+    let valid = !name.is_empty() && url::Host::parse(name).is_ok();
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ErrorKind::InvalidDNSName(name.to_owned()).into())
+    }
+}
+
+const PEM_BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+const PEM_END: &[u8] = b"-----END CERTIFICATE-----";
+
+/// Parse all the certificates contained in a PEM bundle.
+///
+/// Sections other than certificates are ignored. The error identifies the index of the first
+/// certificate of the bundle which could not be parsed.
+pub fn parse_pem_certificates(pem: &[u8]) -> Result<Vec<Certificate>> {
+    let invalid = |index, reason: &str| ErrorKind::InvalidPemCertificate {
+        index,
+        reason: reason.to_owned(),
+    };
+
+    let mut certs = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = find(rest, PEM_BEGIN) {
+        let index = certs.len();
+        let section = &rest[start..];
+        let end = find(section, PEM_END).ok_or_else(|| invalid(index, "missing END CERTIFICATE line"))? + PEM_END.len();
+        certs.push(parse_pem_certificate(&section[..end]).map_err(|reason| invalid(index, &reason))?);
+        rest = &section[end..];
+    }
+
+    if certs.is_empty() {
+        return Err(invalid(0, "no certificate found").into());
+    }
+    Ok(certs)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// A version of the TLS protocol.
+///
+/// Not every backend supports every version, `rustls` only supports TLS 1.2 and TLS 1.3.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TlsVersion {
+    /// TLS 1.0
+    Tls1_0,
+    /// TLS 1.1
+    Tls1_1,
+    /// TLS 1.2
+    Tls1_2,
+    /// TLS 1.3
+    Tls1_3,
+}
+
+/// Holds the `TlsHandshaker` built from the TLS settings of a `BaseSettings`.
+///
+/// Clones share the same handshaker, which lets every request made from a `Session` reuse the
+/// same TLS configuration and resume previous TLS sessions. The cache must be replaced by a new
+/// one whenever the TLS settings change.
+#[derive(Clone, Default)]
+pub struct TlsCache(Arc<Mutex<Option<Arc<TlsHandshaker>>>>);
+
+impl TlsCache {
+    pub fn handshaker(&self, base_settings: &BaseSettings) -> Result<Arc<TlsHandshaker>> {
+        let mut inner = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        match &*inner {
+            Some(handshaker) => Ok(Arc::clone(handshaker)),
+            None => {
+                let handshaker = Arc::new(TlsHandshaker::new(base_settings)?);
+                *inner = Some(Arc::clone(&handshaker));
+                Ok(handshaker)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl TlsCache {
+    /// Whether both caches are the same, shared by clones.
+    pub fn same_as(&self, other: &TlsCache) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for TlsCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsCache").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_pem_certificates;
+    use crate::ErrorKind;
+
+    const CERT: &[u8] = include_bytes!("../../tests/tools/cert.pem");
+
+    fn invalid_index(pem: &[u8]) -> usize {
+        match parse_pem_certificates(pem).err().unwrap().into_kind() {
+            ErrorKind::InvalidPemCertificate { index, .. } => index,
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_parse_pem_certificate() {
+        assert_eq!(parse_pem_certificates(CERT).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_pem_bundle() {
+        let bundle = [CERT, b"\n", CERT].concat();
+        assert_eq!(parse_pem_certificates(&bundle).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_pem_garbage() {
+        assert_eq!(invalid_index(b"garbage"), 0);
+        assert_eq!(invalid_index(b""), 0);
+    }
+
+    #[test]
+    fn test_parse_pem_missing_end() {
+        let bundle = [CERT, b"-----BEGIN CERTIFICATE-----\nMIIB\n"].concat();
+        assert_eq!(invalid_index(&bundle), 1);
+    }
+
+    #[cfg(any(feature = "tls-native", feature = "__rustls"))]
+    #[test]
+    fn test_parse_pem_invalid_second_certificate() {
+        let bundle = [CERT, b"-----BEGIN CERTIFICATE-----\n!!!!\n-----END CERTIFICATE-----\n"].concat();
+        assert_eq!(invalid_index(&bundle), 1);
+    }
+}
@@ -2,41 +2,53 @@ use std::fmt;
 use std::io;
 use std::io::prelude::*;
 
-use native_tls::HandshakeError;
+use native_tls::{HandshakeError, Protocol};
 
+use crate::request::BaseSettings;
+use crate::tls::TlsVersion;
 use crate::Result;
 
 pub type Certificate = native_tls::Certificate;
 
+pub fn parse_pem_certificate(pem: &[u8]) -> std::result::Result<Certificate, String> {
+    Certificate::from_pem(pem).map_err(|e| e.to_string())
+}
+
+/// Get the DER encoding of the certificate, used to find duplicates.
+pub fn certificate_der(cert: &Certificate) -> Option<Vec<u8>> {
+    cert.to_der().ok()
+}
+
 pub struct TlsHandshaker {
-    inner: native_tls::TlsConnectorBuilder,
+    connector: native_tls::TlsConnector,
 }
 
 impl TlsHandshaker {
-    pub fn new() -> TlsHandshaker {
-        TlsHandshaker {
-            inner: native_tls::TlsConnector::builder(),
+    pub fn new(base_settings: &BaseSettings) -> Result<TlsHandshaker> {
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.danger_accept_invalid_certs(base_settings.accept_invalid_certs);
+        builder.danger_accept_invalid_hostnames(base_settings.accept_invalid_hostnames);
+        for cert in &base_settings.root_certificates.0 {
+            builder.add_root_certificate(cert.clone());
         }
-    }
-
-    pub fn danger_accept_invalid_certs(&mut self, accept_invalid_certs: bool) {
-        self.inner.danger_accept_invalid_certs(accept_invalid_certs);
-    }
-
-    pub fn danger_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) {
-        self.inner.danger_accept_invalid_hostnames(accept_invalid_hostnames);
-    }
-
-    pub fn add_root_certificate(&mut self, cert: Certificate) {
-        self.inner.add_root_certificate(cert);
+        if let Some(min) = base_settings.min_tls_version {
+            builder.min_protocol_version(Some(protocol(min)));
+        }
+        if let Some(max) = base_settings.max_tls_version {
+            builder.max_protocol_version(Some(protocol(max)));
+        }
+        let alpn_protocols: Vec<&str> = base_settings.alpn_protocols.iter().map(String::as_str).collect();
+        builder.request_alpns(&alpn_protocols);
+        Ok(TlsHandshaker {
+            connector: builder.build()?,
+        })
     }
 
     pub fn handshake<S>(&self, domain: &str, stream: S) -> Result<TlsStream<S>>
     where
         S: Read + Write,
     {
-        let connector = self.inner.build()?;
-        let stream = match connector.connect(domain, stream) {
+        let stream = match self.connector.connect(domain, stream) {
             Ok(stream) => stream,
             Err(HandshakeError::Failure(err)) => return Err(err.into()),
             Err(HandshakeError::WouldBlock(mut stream)) => loop {
@@ -51,6 +63,15 @@ impl TlsHandshaker {
     }
 }
 
+fn protocol(version: TlsVersion) -> Protocol {
+    match version {
+        TlsVersion::Tls1_0 => Protocol::Tlsv10,
+        TlsVersion::Tls1_1 => Protocol::Tlsv11,
+        TlsVersion::Tls1_2 => Protocol::Tlsv12,
+        TlsVersion::Tls1_3 => Protocol::Tlsv13,
+    }
+}
+
 pub struct TlsStream<S>
 where
     S: Read + Write,
@@ -58,6 +79,19 @@ where
     inner: native_tls::TlsStream<S>,
 }
 
+impl<S> TlsStream<S>
+where
+    S: Read + Write,
+{
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut()
+    }
+}
+
 impl<S> Read for TlsStream<S>
 where
     S: Read + Write,
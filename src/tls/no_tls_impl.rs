@@ -3,28 +3,33 @@ use std::io;
 use std::io::prelude::*;
 use std::marker::PhantomData;
 
+use crate::request::BaseSettings;
 use crate::{ErrorKind, Result};
 
 pub type Certificate = ();
 
+pub fn parse_pem_certificate(_pem: &[u8]) -> std::result::Result<Certificate, String> {
+    Ok(())
+}
+
+/// Get the DER encoding of the certificate, used to find duplicates. Certificates are not kept
+/// without TLS, they are all the same.
+pub fn certificate_der(_cert: &Certificate) -> Option<Vec<u8>> {
+    Some(Vec::new())
+}
+
 pub struct TlsHandshaker {}
 
 impl TlsHandshaker {
-    pub fn new() -> TlsHandshaker {
-        TlsHandshaker {}
+    pub fn new(_base_settings: &BaseSettings) -> Result<TlsHandshaker> {
+        Ok(TlsHandshaker {})
     }
 
-    pub fn danger_accept_invalid_certs(&mut self, _accept_invalid_certs: bool) {}
-
-    pub fn danger_accept_invalid_hostnames(&mut self, _accept_invalid_hostnames: bool) {}
-
-    pub fn add_root_certificate(&mut self, _cert: Certificate) {}
-
     pub fn handshake<S>(&self, _domain: &str, _stream: S) -> Result<TlsStream<S>>
     where
         S: Read + Write,
     {
-        Err(ErrorKind::TlsDisabled.into())
+        Err(ErrorKind::TlsDisabled { url: None }.into())
     }
 }
 
@@ -35,6 +40,19 @@ where
     dummy: PhantomData<S>,
 }
 
+impl<S> TlsStream<S>
+where
+    S: Read + Write,
+{
+    pub fn get_ref(&self) -> &S {
+        unreachable!("TLS streams cannot be created when TLS is disabled")
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        unreachable!("TLS streams cannot be created when TLS is disabled")
+    }
+}
+
 impl<S> Read for TlsStream<S>
 where
     S: Read + Write,
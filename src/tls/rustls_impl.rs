@@ -7,107 +7,112 @@ use std::sync::Arc;
 use rustls::{
     client::{
         danger::{DangerousClientConfigBuilder, HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
-        WebPkiServerVerifier,
+        Resumption, WebPkiServerVerifier,
     },
-    pki_types::{CertificateDer, ServerName, UnixTime},
+    pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+    version::{TLS12, TLS13},
     ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme, StreamOwned,
+    SupportedProtocolVersion,
 };
 #[cfg(feature = "tls-rustls-native-roots")]
 use rustls_native_certs::load_native_certs;
 #[cfg(feature = "tls-rustls-webpki-roots")]
 use webpki_roots::TLS_SERVER_ROOTS;
 
+use crate::request::BaseSettings;
+use crate::tls::TlsVersion;
 use crate::{Error, ErrorKind, Result};
 
 pub type Certificate = CertificateDer<'static>;
 
+pub fn parse_pem_certificate(pem: &[u8]) -> std::result::Result<Certificate, String> {
+    Certificate::from_pem_slice(pem).map_err(|e| e.to_string())
+}
+
+/// Get the DER encoding of the certificate, used to find duplicates.
+pub fn certificate_der(cert: &Certificate) -> Option<Vec<u8>> {
+    Some(cert.to_vec())
+}
+
 pub struct TlsHandshaker {
-    inner: Option<Arc<ClientConfig>>,
-    accept_invalid_certs: bool,
-    accept_invalid_hostnames: bool,
-    additional_certs: Vec<Certificate>,
+    config: Arc<ClientConfig>,
 }
 
 impl TlsHandshaker {
-    pub fn new() -> TlsHandshaker {
-        TlsHandshaker {
-            inner: None,
-            accept_invalid_hostnames: false,
-            accept_invalid_certs: false,
-            additional_certs: Vec::new(),
+    pub fn new(base_settings: &BaseSettings) -> Result<TlsHandshaker> {
+        let mut root_store = RootCertStore::empty();
+
+        #[cfg(feature = "tls-rustls-webpki-roots")]
+        root_store.extend(TLS_SERVER_ROOTS.iter().cloned());
+
+        #[cfg(feature = "tls-rustls-native-roots")]
+        for cert in load_native_certs()? {
+            // Inspired by https://github.com/seanmonstar/reqwest/blob/231b18f83572836c674404b33cb1ca8b35ca3e36/src/async_impl/client.rs#L363-L365
+            // Native certificate stores often include certificates with invalid formats,
+            // but we don't want those invalid entries to invalidate the entire process of
+            // loading native root certificates
+            if let Err(e) = root_store.add(cert) {
+                warn!("Could not load native root certificate: {}", e);
+            }
         }
-    }
-
-    pub fn danger_accept_invalid_certs(&mut self, accept_invalid_certs: bool) {
-        self.accept_invalid_certs = accept_invalid_certs;
-        self.inner = None;
-    }
 
-    pub fn danger_accept_invalid_hostnames(&mut self, accept_invalid_hostnames: bool) {
-        self.accept_invalid_hostnames = accept_invalid_hostnames;
-        self.inner = None;
-    }
-
-    pub fn add_root_certificate(&mut self, cert: Certificate) {
-        self.additional_certs.push(cert);
-        self.inner = None;
-    }
-
-    fn client_config(&mut self) -> Result<Arc<ClientConfig>> {
-        match &self.inner {
-            Some(inner) => Ok(Arc::clone(inner)),
-            None => {
-                let mut root_store = RootCertStore::empty();
-
-                #[cfg(feature = "tls-rustls-webpki-roots")]
-                root_store.extend(TLS_SERVER_ROOTS.iter().cloned());
-
-                #[cfg(feature = "tls-rustls-native-roots")]
-                for cert in load_native_certs()? {
-                    // Inspired by https://github.com/seanmonstar/reqwest/blob/231b18f83572836c674404b33cb1ca8b35ca3e36/src/async_impl/client.rs#L363-L365
-                    // Native certificate stores often include certificates with invalid formats,
-                    // but we don't want those invalid entries to invalidate the entire process of
-                    // loading native root certificates
-                    if let Err(e) = root_store.add(cert) {
-                        warn!("Could not load native root certificate: {}", e);
-                    }
-                }
-
-                for cert in self.additional_certs.iter().cloned() {
-                    root_store.add(cert)?;
-                }
-
-                let config = DangerousClientConfigBuilder {
-                    cfg: ClientConfig::builder(),
-                }
-                .with_custom_certificate_verifier(Arc::new(CustomCertVerifier {
-                    upstream: WebPkiServerVerifier::builder(root_store.into()).build()?,
-                    accept_invalid_certs: self.accept_invalid_certs,
-                    accept_invalid_hostnames: self.accept_invalid_hostnames,
-                }))
-                .with_no_client_auth()
-                .into();
+        for cert in base_settings.root_certificates.0.iter().cloned() {
+            root_store.add(cert)?;
+        }
 
-                self.inner = Some(Arc::clone(&config));
+        let versions: Vec<&'static SupportedProtocolVersion> =
+            [(TlsVersion::Tls1_2, &TLS12), (TlsVersion::Tls1_3, &TLS13)]
+                .iter()
+                .filter(|(version, _)| base_settings.min_tls_version.is_none_or(|min| *version >= min))
+                .filter(|(version, _)| base_settings.max_tls_version.is_none_or(|max| *version <= max))
+                .map(|(_, supported)| *supported)
+                .collect();
+        if versions.is_empty() {
+            return Err(rustls::Error::General("no supported TLS version in the configured range".into()).into());
+        }
 
-                Ok(config)
-            }
+        let mut config = DangerousClientConfigBuilder {
+            cfg: ClientConfig::builder_with_protocol_versions(&versions),
         }
+        .with_custom_certificate_verifier(Arc::new(CustomCertVerifier {
+            upstream: WebPkiServerVerifier::builder(root_store.into()).build()?,
+            accept_invalid_certs: base_settings.accept_invalid_certs,
+            accept_invalid_hostnames: base_settings.accept_invalid_hostnames,
+        }))
+        .with_no_client_auth();
+
+        // The config is shared by every connection made with the same settings, so sessions
+        // stored here allow later handshakes to the same host to be resumed.
+        config.resumption = Resumption::in_memory_sessions(256);
+        config.alpn_protocols = base_settings
+            .alpn_protocols
+            .iter()
+            .map(|protocol| protocol.as_bytes().to_vec())
+            .collect();
+
+        Ok(TlsHandshaker {
+            config: Arc::new(config),
+        })
     }
 
-    pub fn handshake<S>(&mut self, domain: &str, mut stream: S) -> Result<TlsStream<S>>
+    pub fn handshake<S>(&self, domain: &str, mut stream: S) -> Result<TlsStream<S>>
     where
         S: Read + Write,
     {
         let domain = ServerName::try_from(domain)
             .map_err(|_| Error(Box::new(ErrorKind::InvalidDNSName(domain.to_owned()))))?
             .to_owned();
-        let config = self.client_config()?;
-        let mut session = ClientConnection::new(config, domain)?;
+        let mut session = ClientConnection::new(Arc::clone(&self.config), domain)?;
 
         while let Err(err) = session.complete_io(&mut stream) {
             if err.kind() != io::ErrorKind::WouldBlock || !session.is_handshaking() {
-                return Err(err.into());
+                // Handshake failures are reported by rustls as I/O errors wrapping a rustls::Error.
+                return Err(
+                    match err.get_ref().and_then(|inner| inner.downcast_ref::<rustls::Error>()) {
+                        Some(tls_err) => tls_err.clone().into(),
+                        None => err.into(),
+                    },
+                );
             }
         }
 
@@ -128,6 +133,14 @@ impl<S> TlsStream<S>
 where
     S: Read + Write,
 {
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut()
+    }
+
     fn handle_close_notify(&mut self, res: io::Result<usize>) -> io::Result<usize> {
         match res {
             Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => {
@@ -254,3 +267,91 @@ impl ServerCertVerifier for CustomCertVerifier {
         self.upstream.supported_verify_schemes()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::prelude::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+
+    use rustls::{HandshakeKind, ServerConfig, ServerConnection, StreamOwned};
+
+    use super::TlsHandshaker;
+    use crate::request::BaseSettings;
+    use crate::tls::TlsVersion;
+    use crate::ErrorKind;
+
+    fn start_server(connections: usize) -> u16 {
+        let certs = rustls_pemfile::certs(&mut &include_bytes!("../../tests/tools/cert.pem")[..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = rustls_pemfile::private_key(&mut &include_bytes!("../../tests/tools/key.pem")[..])
+            .unwrap()
+            .unwrap();
+        let config = Arc::new(
+            ServerConfig::builder()
+                .with_no_client_auth()
+                .with_single_cert(certs, key)
+                .unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let conn = ServerConnection::new(Arc::clone(&config)).unwrap();
+                let mut stream = StreamOwned::new(conn, stream.unwrap());
+                stream.write_all(b"hello").unwrap();
+                stream.conn.send_close_notify();
+                stream.conn.complete_io(&mut stream.sock).unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_session_resumed_with_shared_cache() {
+        let port = start_server(2);
+        let base_settings = BaseSettings {
+            accept_invalid_certs: true,
+            ..BaseSettings::default()
+        };
+
+        let mut kinds = Vec::new();
+        for _ in 0..2 {
+            let handshaker = base_settings.tls_cache.handshaker(&base_settings).unwrap();
+            let sock = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let mut stream = handshaker.handshake("localhost", sock).unwrap();
+
+            let mut body = String::new();
+            stream.read_to_string(&mut body).unwrap();
+            assert_eq!(body, "hello");
+            kinds.push(stream.inner.conn.handshake_kind());
+        }
+
+        assert_eq!(kinds, [Some(HandshakeKind::Full), Some(HandshakeKind::Resumed)]);
+    }
+
+    #[test]
+    fn test_empty_tls_version_range() {
+        let base_settings = BaseSettings {
+            min_tls_version: Some(TlsVersion::Tls1_3),
+            max_tls_version: Some(TlsVersion::Tls1_2),
+            ..BaseSettings::default()
+        };
+
+        let err = TlsHandshaker::new(&base_settings).err().unwrap();
+        assert!(matches!(err.kind(), ErrorKind::Tls(_)));
+    }
+
+    #[test]
+    fn test_tls_cache_shared_by_clones() {
+        let base_settings = BaseSettings::default();
+        let cloned = base_settings.clone();
+
+        let first = base_settings.tls_cache.handshaker(&base_settings).unwrap();
+        let second = cloned.tls_cache.handshaker(&cloned).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
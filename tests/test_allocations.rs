@@ -0,0 +1,55 @@
+//! Counts the allocations made while parsing response heads. This replaces the global allocator of
+//! the test binary, so it is ignored by default: run it with
+//! `cargo test --features __bench --test test_allocations -- --ignored`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use attohttpc::bench;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations(responses: &[&[u8]]) -> usize {
+    let start = ALLOCATIONS.load(Ordering::SeqCst);
+    bench::parse_response_heads(responses, 100).unwrap();
+    ALLOCATIONS.load(Ordering::SeqCst) - start
+}
+
+#[test]
+#[ignore]
+fn test_parse_response_heads_reuse_buffers() {
+    let redirect: &[u8] = b"HTTP/1.1 302 Found\r\n\
+        Location: /next\r\n\
+        Content-Length: 0\r\n\
+        Cache-Control: no-cache\r\n\
+        Content-Type: text/plain\r\n\
+        Date: Fri, 16 Oct 2026 12:00:00 GMT\r\n\
+        Server: test\r\n\
+        \r\n";
+
+    let first = count_allocations(&[redirect]);
+    let five = count_allocations(&[redirect; 5]);
+
+    // The next hops only allocate the reader buffer, the reason phrase and the header values.
+    assert_eq!(five - first, 4 * (2 + 6), "first hop: {first}, five hops: {five}");
+}
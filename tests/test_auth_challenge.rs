@@ -0,0 +1,111 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use attohttpc::header::HeaderValue;
+use attohttpc::StatusCode;
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// Answers 401 until the request has the `Bearer fresh` token, then echoes the request body.
+/// `/redirect` redirects to `127.0.0.1`.
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let route = warp::header::optional::<String>("authorization")
+        .and(warp::body::bytes())
+        .map(|auth: Option<String>, body: hyper::body::Bytes| {
+            let resp = http::Response::builder();
+            if auth.as_deref() == Some("Bearer fresh") {
+                resp.body(body.to_vec())
+            } else {
+                resp.status(401)
+                    .header("WWW-Authenticate", r#"Bearer realm="api", error="invalid_token""#)
+                    .body(Vec::new())
+            }
+        });
+
+    let port = local_addr.port();
+    let redirect = warp::path("redirect").map(move || {
+        http::Response::builder()
+            .header("Location", format!("http://127.0.0.1:{port}/"))
+            .status(http::StatusCode::FOUND)
+            .body(Vec::new())
+    });
+
+    let server = warp::serve(redirect.or(route)).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_auth_challenge_refreshes_token() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let mut session = attohttpc::Session::new();
+    let counter = Arc::clone(&calls);
+    session.on_auth_challenge(move |auth| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        let challenge = auth.challenge("bearer")?;
+        assert_eq!(challenge.realm(), Some("api"));
+        assert_eq!(challenge.param("error"), Some("invalid_token"));
+        Some(HeaderValue::from_static("Bearer fresh"))
+    });
+
+    let resp = session
+        .post(format!("http://localhost:{port}"))
+        .bearer_auth("expired")
+        .text("payload")
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text()?, "payload");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_auth_challenge_retries_once() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let counter = Arc::clone(&calls);
+    let resp = attohttpc::get(format!("http://localhost:{port}"))
+        .on_auth_challenge(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Some(HeaderValue::from_static("Bearer still-wrong"))
+        })
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let resp = attohttpc::get(format!("http://localhost:{port}"))
+        .on_auth_challenge(|_| None)
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_auth_challenge_not_retried_on_other_host() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let counter = Arc::clone(&calls);
+    let resp = attohttpc::get(format!("http://localhost:{port}/redirect"))
+        .on_auth_challenge(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Some(HeaderValue::from_static("Bearer fresh"))
+        })
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    Ok(())
+}
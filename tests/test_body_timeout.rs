@@ -0,0 +1,82 @@
+use std::convert::Infallible;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use attohttpc::ErrorKind;
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// Waits 2 seconds before sending the headers, then sends 3 chunks of the body 1 second apart.
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let poll = warp::path("poll").and_then(|| async {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let stream = futures_util::stream::unfold(0, |sent| async move {
+            if sent == 3 {
+                return None;
+            }
+            if sent > 0 {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Some((Ok::<_, Infallible>("chunk"), sent + 1))
+        });
+        Ok::<_, Infallible>(http::Response::new(hyper::Body::wrap_stream(stream)))
+    });
+
+    let server = warp::serve(poll).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_body_timeout_shorter_than_read_timeout() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    // The headers arrive within the read timeout, but the body stalls longer than its timeout.
+    let resp = attohttpc::get(format!("http://localhost:{port}/poll"))
+        .read_timeout(Duration::from_secs(5))
+        .response_body_read_timeout(Duration::from_millis(300))
+        .send()?;
+    assert!(resp.is_success());
+
+    let mut body = Vec::new();
+    let err = resp.split().2.read_to_end(&mut body).unwrap_err();
+    assert!(is_timeout(&err), "{:?}", err);
+    assert_eq!(body, b"chunk");
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/poll"))
+        .read_timeout(Duration::from_secs(5))
+        .response_body_read_timeout(Duration::from_secs(3))
+        .send()?;
+    assert_eq!(resp.text()?, "chunkchunkchunk");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_body_timeout_longer_than_read_timeout() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    // The body timeout only starts once the headers are received.
+    let err = attohttpc::get(format!("http://localhost:{port}/poll"))
+        .read_timeout(Duration::from_millis(500))
+        .response_body_read_timeout(Duration::from_secs(10))
+        .send()
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::Io(err) => assert!(is_timeout(err), "{:?}", err),
+        kind => panic!("unexpected error {:?}", kind),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use attohttpc::{CacheConfig, Session};
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// State of the server: the version of the document, changed by the tests, and the status codes
+/// sent on the wire.
+#[derive(Default)]
+struct State {
+    version: AtomicUsize,
+    statuses: Mutex<Vec<u16>>,
+}
+
+async fn make_server() -> Result<(u16, Arc<State>), anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+    let state = Arc::new(State::default());
+
+    let route_state = state.clone();
+    let config = warp::path("config")
+        .and(warp::header::optional::<String>("if-none-match"))
+        .map(move |if_none_match: Option<String>| {
+            let version = route_state.version.load(Ordering::SeqCst);
+            let etag = format!("\"v{version}\"");
+            let builder = http::Response::builder().header("ETag", &etag);
+            let resp = if if_none_match.as_deref() == Some(etag.as_str()) {
+                builder.status(http::StatusCode::NOT_MODIFIED).body(String::new())
+            } else {
+                builder.body(format!("config version {version}"))
+            };
+            let resp = resp.unwrap();
+            route_state.statuses.lock().unwrap().push(resp.status().as_u16());
+            resp
+        });
+
+    // The validators do not depend on the credentials or the language, only the body does.
+    let route_state = state.clone();
+    let private = warp::path("private")
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("if-none-match"))
+        .map(move |authorization: Option<String>, if_none_match: Option<String>| {
+            let builder = http::Response::builder().header("ETag", "\"v0\"");
+            let resp = if if_none_match.as_deref() == Some("\"v0\"") {
+                builder.status(http::StatusCode::NOT_MODIFIED).body(String::new())
+            } else {
+                builder.body(format!("data of {}", authorization.unwrap_or_default()))
+            };
+            let resp = resp.unwrap();
+            route_state.statuses.lock().unwrap().push(resp.status().as_u16());
+            resp
+        });
+    let route_state = state.clone();
+    let localized = warp::path("localized")
+        .and(warp::header::optional::<String>("accept-language"))
+        .and(warp::header::optional::<String>("if-none-match"))
+        .map(move |language: Option<String>, if_none_match: Option<String>| {
+            let builder = http::Response::builder()
+                .header("ETag", "\"v0\"")
+                .header("Vary", "Accept-Language");
+            let resp = if if_none_match.as_deref() == Some("\"v0\"") {
+                builder.status(http::StatusCode::NOT_MODIFIED).body(String::new())
+            } else {
+                builder.body(format!("text in {}", language.unwrap_or_default()))
+            };
+            let resp = resp.unwrap();
+            route_state.statuses.lock().unwrap().push(resp.status().as_u16());
+            resp
+        });
+
+    let server = warp::serve(config.or(private).or(localized)).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok((local_addr.port(), state))
+}
+
+fn statuses(state: &State) -> Vec<u16> {
+    state.statuses.lock().unwrap().clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_revalidation() -> Result<(), anyhow::Error> {
+    let (port, state) = make_server().await?;
+    let url = format!("http://localhost:{port}/config");
+    let mut session = Session::new();
+    session.cache(CacheConfig {
+        max_entries: 10,
+        max_body_bytes: 1024,
+    });
+
+    let resp = session.get(&url).send()?;
+    assert!(!resp.from_cache());
+    assert_eq!(resp.text()?, "config version 0");
+
+    let resp = session.get(&url).send()?;
+    assert!(resp.from_cache());
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["etag"], "\"v0\"");
+    assert_eq!(resp.text()?, "config version 0");
+    assert_eq!(statuses(&state), [200, 304]);
+
+    state.version.store(1, Ordering::SeqCst);
+    let resp = session.get(&url).send()?;
+    assert!(!resp.from_cache());
+    assert_eq!(resp.text()?, "config version 1");
+
+    let resp = session.get(&url).send()?;
+    assert!(resp.from_cache());
+    assert_eq!(resp.text()?, "config version 1");
+    assert_eq!(statuses(&state), [200, 304, 200, 304]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_skips_large_and_unread_bodies() -> Result<(), anyhow::Error> {
+    let (port, state) = make_server().await?;
+    let url = format!("http://localhost:{port}/config");
+
+    let mut session = Session::new();
+    session.cache(CacheConfig {
+        max_entries: 10,
+        max_body_bytes: 4,
+    });
+    session.get(&url).send()?.text()?;
+    let resp = session.get(&url).send()?;
+    assert!(!resp.from_cache());
+
+    let mut session = Session::new();
+    session.cache(CacheConfig {
+        max_entries: 10,
+        max_body_bytes: 1024,
+    });
+    drop(session.get(&url).send()?);
+    let resp = session.get(&url).send()?;
+    assert!(!resp.from_cache());
+    resp.text()?;
+
+    // A request setting the validators itself gets the 304 response.
+    let resp = session.get(&url).header("If-None-Match", "\"v0\"").send()?;
+    assert_eq!(resp.status(), 304);
+    assert!(!resp.from_cache());
+
+    session.reset();
+    let resp = session.get(&url).send()?;
+    assert!(!resp.from_cache());
+    assert_eq!(statuses(&state), [200, 200, 200, 200, 304, 200]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_skips_authorized_requests() -> Result<(), anyhow::Error> {
+    let (port, state) = make_server().await?;
+    let url = format!("http://localhost:{port}/private");
+    let mut session = Session::new();
+    session.cache(CacheConfig {
+        max_entries: 10,
+        max_body_bytes: 1024,
+    });
+
+    let resp = session.get(&url).bearer_auth("alice").send()?;
+    assert_eq!(resp.text()?, "data of Bearer alice");
+
+    let resp = session.get(&url).bearer_auth("bob").send()?;
+    assert!(!resp.from_cache());
+    assert_eq!(resp.text()?, "data of Bearer bob");
+    assert_eq!(statuses(&state), [200, 200]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_skips_vary() -> Result<(), anyhow::Error> {
+    let (port, state) = make_server().await?;
+    let url = format!("http://localhost:{port}/localized");
+    let mut session = Session::new();
+    session.cache(CacheConfig {
+        max_entries: 10,
+        max_body_bytes: 1024,
+    });
+
+    let resp = session.get(&url).header("Accept-Language", "en").send()?;
+    assert_eq!(resp.text()?, "text in en");
+
+    let resp = session.get(&url).header("Accept-Language", "fr").send()?;
+    assert!(!resp.from_cache());
+    assert_eq!(resp.text()?, "text in fr");
+    assert_eq!(statuses(&state), [200, 200]);
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use attohttpc::charsets;
+
+/// Answers one request with a `text/plain` body which does not name its charset.
+fn start_server(body: &[u8]) -> (u16, std::thread::JoinHandle<Vec<Vec<String>>>) {
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    raw::start_raw_server([response])
+}
+
+#[test]
+fn test_default_charset_from_label() {
+    // "こんにちは" in Shift-JIS.
+    let (port, thread) = start_server(b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd");
+
+    let charset = charsets::from_label(" Shift_JIS ").unwrap();
+    let resp = attohttpc::get(format!("http://localhost:{port}"))
+        .default_charset(Some(charset))
+        .send()
+        .unwrap();
+    assert_eq!(resp.text().unwrap(), "こんにちは");
+
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_text_with_from_label() {
+    let (port, thread) = start_server(b"qu\xe9bec");
+
+    let resp = attohttpc::get(format!("http://localhost:{port}")).send().unwrap();
+    assert_eq!(
+        resp.text_with(charsets::from_label("LATIN1").unwrap()).unwrap(),
+        "québec"
+    );
+
+    thread.join().unwrap();
+}
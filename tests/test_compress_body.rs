@@ -0,0 +1,119 @@
+#![cfg(feature = "compress")]
+
+use std::io::Read;
+use std::net::SocketAddr;
+
+use flate2::read::GzDecoder;
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// Decompresses the body of the request and answers with the framing headers, followed by the
+/// decompressed body.
+fn decompress(
+    encoding: Option<String>,
+    length: Option<String>,
+    chunked: Option<String>,
+    body: hyper::body::Bytes,
+) -> Vec<u8> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&body[..]).read_to_end(&mut decompressed).unwrap();
+    let mut resp = format!(
+        "{} {} {}\n",
+        encoding.unwrap_or_default(),
+        length.as_deref() == Some(body.len().to_string().as_str()),
+        chunked.unwrap_or_default(),
+    )
+    .into_bytes();
+    resp.extend(decompressed);
+    resp
+}
+
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let upload = warp::path("upload")
+        .and(warp::header::optional::<String>("content-encoding"))
+        .and(warp::header::optional::<String>("content-length"))
+        .and(warp::header::optional::<String>("transfer-encoding"))
+        .and(warp::body::bytes())
+        .map(decompress);
+    let redirect = warp::path("redirect").map(|| {
+        http::Response::builder()
+            .header("Location", "/upload")
+            .status(http::StatusCode::TEMPORARY_REDIRECT)
+            .body("")
+    });
+
+    let server = warp::serve(upload.or(redirect)).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| b"0123456789abcdef"[(i / 3 + i % 7) % 16]).collect()
+}
+
+fn split(resp: Vec<u8>) -> (String, Vec<u8>) {
+    let idx = resp.iter().position(|&b| b == b'\n').unwrap();
+    (
+        String::from_utf8(resp[..idx].to_vec()).unwrap(),
+        resp[idx + 1..].to_vec(),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compress_body_buffered() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let body = payload(100_000);
+
+    let resp = attohttpc::post(format!("http://localhost:{port}/upload"))
+        .bytes(&body)
+        .compress_body(None)
+        .send()?;
+    let (framing, received) = split(resp.bytes()?);
+
+    assert_eq!(framing, "gzip true ");
+    assert_eq!(received, body);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compress_body_streaming() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let body = payload(3 * 1024 * 1024);
+
+    let resp = attohttpc::post(format!("http://localhost:{port}/upload"))
+        .bytes(&body)
+        .compress_body(Some(6))
+        .send()?;
+    let (framing, received) = split(resp.bytes()?);
+
+    assert_eq!(framing, "gzip false chunked");
+    assert_eq!(received, body);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compress_body_redirect() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    for len in [1000, 2 * 1024 * 1024] {
+        let body = payload(len);
+        let resp = attohttpc::post(format!("http://localhost:{port}/redirect"))
+            .bytes(&body)
+            .compress_body(None)
+            .send()?;
+        let (framing, received) = split(resp.bytes()?);
+
+        assert!(framing.starts_with("gzip "));
+        assert_eq!(received, body);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+#[derive(Default)]
+struct Counters {
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+/// Serves requests which take `delay` to answer, recording how many of them overlap.
+async fn make_server(delay: Duration, counters: Arc<Counters>) -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let route = warp::any().then(move || {
+        let counters = Arc::clone(&counters);
+        async move {
+            let in_flight = counters.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            counters.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(delay).await;
+            counters.in_flight.fetch_sub(1, Ordering::SeqCst);
+            "ok"
+        }
+    });
+
+    let server = warp::serve(route).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_concurrent_requests() -> Result<(), anyhow::Error> {
+    let counters = Arc::new(Counters::default());
+    let port = make_server(Duration::from_millis(100), Arc::clone(&counters)).await?;
+
+    let mut session = attohttpc::Session::new();
+    session.max_concurrent_requests(2);
+    let session = Arc::new(session);
+
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let session = Arc::clone(&session);
+            thread::spawn(move || -> attohttpc::Result<String> {
+                session.get(format!("http://localhost:{port}")).send()?.text()
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap()?, "ok");
+    }
+
+    assert_eq!(counters.max_in_flight.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_concurrent_requests_timeout() -> Result<(), anyhow::Error> {
+    let counters = Arc::new(Counters::default());
+    let port = make_server(Duration::from_millis(500), Arc::clone(&counters)).await?;
+
+    let mut session = attohttpc::Session::new();
+    session.max_concurrent_requests(1);
+    let session = Arc::new(session);
+
+    let slow = {
+        let session = Arc::clone(&session);
+        thread::spawn(move || session.get(format!("http://localhost:{port}")).send())
+    };
+    while counters.in_flight.load(Ordering::SeqCst) == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let err = session
+        .get(format!("http://localhost:{port}"))
+        .timeout(Duration::from_millis(100))
+        .send()
+        .unwrap_err();
+    match err.kind() {
+        attohttpc::ErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+        kind => panic!("unexpected error: {:?}", kind),
+    }
+
+    assert!(slow.join().unwrap()?.is_success());
+    assert_eq!(counters.max_in_flight.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rate_limit() -> Result<(), anyhow::Error> {
+    let counters = Arc::new(Counters::default());
+    let port = make_server(Duration::ZERO, Arc::clone(&counters)).await?;
+
+    let mut session = attohttpc::Session::new();
+    session.rate_limit("localhost", 5.0);
+    session.rate_limit("example.com", 0.1);
+
+    let start = Instant::now();
+    for _ in 0..10 {
+        assert_eq!(session.get(format!("http://localhost:{port}")).send()?.text()?, "ok");
+    }
+    assert!(start.elapsed() >= Duration::from_millis(1800));
+
+    Ok(())
+}
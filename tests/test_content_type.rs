@@ -0,0 +1,72 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use attohttpc::ErrorKind;
+
+/// Answers one request with the given `Content-Type`, or none, and status code.
+fn start_server(content_type: Option<&str>, status: &str) -> u16 {
+    let content_type = content_type.map_or(String::new(), |ct| format!("Content-Type: {ct}\r\n"));
+    let response = format!("HTTP/1.1 {status}\r\n{content_type}Content-Length: 2\r\nConnection: close\r\n\r\n{{}}");
+    raw::start_raw_server([response]).0
+}
+
+fn get(content_type: Option<&'static str>, status: &'static str) -> attohttpc::RequestBuilder {
+    let port = start_server(content_type, status);
+    attohttpc::get(format!("http://localhost:{port}"))
+        .expect_content_type("application/json")
+        .expect_content_type("+json")
+}
+
+#[test]
+fn test_expected_content_type() -> attohttpc::Result<()> {
+    let resp = get(Some("application/json; charset=utf-8"), "200 OK").send()?;
+    assert_eq!(resp.text()?, "{}");
+
+    let resp = get(Some("APPLICATION/JSON"), "200 OK").send()?;
+    assert_eq!(resp.text()?, "{}");
+
+    Ok(())
+}
+
+#[test]
+fn test_expected_content_type_suffix() -> attohttpc::Result<()> {
+    let resp = get(Some("application/problem+json"), "200 OK").send()?;
+    assert_eq!(resp.text()?, "{}");
+
+    Ok(())
+}
+
+#[test]
+fn test_unexpected_content_type() {
+    let err = get(Some("text/html; charset=utf-8"), "200 OK").send().unwrap_err();
+    match err.kind() {
+        ErrorKind::UnexpectedContentType { expected, actual } => {
+            assert_eq!(expected, &["application/json", "+json"]);
+            assert_eq!(actual.as_deref(), Some("text/html; charset=utf-8"));
+        }
+        kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_unexpected_content_type_error_status() -> attohttpc::Result<()> {
+    let resp = get(Some("text/html"), "503 Service Unavailable").send()?;
+    assert_eq!(resp.status(), attohttpc::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.text()?, "{}");
+
+    Ok(())
+}
+
+#[test]
+fn test_missing_content_type() -> attohttpc::Result<()> {
+    let resp = get(None, "200 OK").send()?;
+    assert_eq!(resp.text()?, "{}");
+
+    let err = get(None, "200 OK").require_content_type(true).send().unwrap_err();
+    match err.kind() {
+        ErrorKind::UnexpectedContentType { actual, .. } => assert_eq!(*actual, None),
+        kind => panic!("unexpected error: {:?}", kind),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use attohttpc::header::HeaderName;
+use attohttpc::Method;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let cors = warp::cors()
+        .allow_origin("https://example.com")
+        .allow_methods(vec!["GET", "PUT"])
+        .allow_headers(vec!["x-token", "content-type"])
+        .allow_credentials(true)
+        .max_age(600);
+    let route = warp::any().map(|| "ok").with(cors);
+
+    let server = warp::serve(route).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cors_preflight_allowed() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/api"))
+        .cors_preflight(
+            "https://example.com",
+            Method::PUT,
+            &[
+                HeaderName::from_static("x-token"),
+                HeaderName::from_static("content-type"),
+            ],
+        )
+        .send()?;
+    assert!(resp.is_success());
+
+    let cors = resp.cors_allowed();
+    assert!(cors.allows_origin("https://example.com"));
+    assert!(!cors.allows_origin("https://example.org"));
+    assert!(cors.allow_credentials);
+    assert!(cors.allows_method(&Method::PUT));
+    assert!(!cors.allows_method(&Method::DELETE));
+    assert!(cors.allows_header(&HeaderName::from_static("x-token")));
+    assert!(!cors.allows_header(&HeaderName::from_static("x-other")));
+    assert_eq!(cors.max_age, Some(Duration::from_secs(600)));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cors_preflight_rejected() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/api"))
+        .cors_preflight("https://example.com", Method::DELETE, &[])
+        .send()?;
+    assert!(!resp.is_success());
+    assert!(!resp.cors_allowed().allows_origin("https://example.com"));
+
+    Ok(())
+}
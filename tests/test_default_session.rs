@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use attohttpc::header::{HeaderValue, USER_AGENT};
+
+#[test]
+fn test_default_session() {
+    assert!(attohttpc::default_session().is_none());
+    let req = attohttpc::get("http://example.com").prepare();
+    assert!(req.headers()[USER_AGENT].to_str().unwrap().starts_with("attohttpc/"));
+
+    let mut session = attohttpc::Session::new();
+    session.header(USER_AGENT, "my-tool/1.0");
+    session.header("X-Team", "core");
+    session.timeout(Duration::from_secs(10));
+    assert!(attohttpc::set_default_session(session).is_ok());
+    assert!(attohttpc::set_default_session(attohttpc::Session::new()).is_err());
+    assert!(attohttpc::default_session().is_some());
+
+    for req in [
+        attohttpc::get("http://example.com").prepare(),
+        attohttpc::post("http://example.com").prepare(),
+        attohttpc::delete("http://example.com").prepare(),
+    ] {
+        assert_eq!(req.headers()[USER_AGENT], "my-tool/1.0");
+        assert_eq!(req.headers()["x-team"], "core");
+    }
+
+    // The settings of the request take precedence.
+    let req = attohttpc::get("http://example.com")
+        .header(USER_AGENT, "other/2.0")
+        .prepare();
+    assert_eq!(req.headers()[USER_AGENT], HeaderValue::from_static("other/2.0"));
+    assert_eq!(req.headers()["x-team"], "core");
+}
@@ -0,0 +1,123 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn serve_once(response: &[u8]) -> u16 {
+    raw::start_raw_server([response]).0
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("attohttpc-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_save_to_file() {
+    let port = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let dir = temp_dir("save");
+    let path = dir.join("out.txt");
+    fs::write(&path, "previous content").unwrap();
+
+    let n = attohttpc::get(format!("http://localhost:{port}"))
+        .send()
+        .unwrap()
+        .save_to_file(&path)
+        .unwrap();
+
+    assert_eq!(n, 5);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+    assert!(!dir.join("out.txt.part").exists());
+}
+
+#[test]
+fn test_save_to_file_error_keeps_destination() {
+    let port = serve_once(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel");
+    let dir = temp_dir("save-error");
+    let path = dir.join("out.txt");
+    fs::write(&path, "previous content").unwrap();
+
+    let res = attohttpc::get(format!("http://localhost:{port}"))
+        .send()
+        .unwrap()
+        .save_to_file(&path);
+
+    assert!(res.is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "previous content");
+    assert!(!dir.join("out.txt.part").exists());
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn test_save_to_file_verified() {
+    const HELLO_SHA256: &str = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+
+    let port = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let dir = temp_dir("verified");
+    let path = dir.join("out.txt");
+
+    attohttpc::get(format!("http://localhost:{port}"))
+        .send()
+        .unwrap()
+        .save_to_file_verified(&path, HELLO_SHA256)
+        .unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+    assert!(!dir.join("out.txt.part").exists());
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn test_save_to_file_verified_mismatch() {
+    let port = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let dir = temp_dir("mismatch");
+    let path = dir.join("out.txt");
+
+    let err = attohttpc::get(format!("http://localhost:{port}"))
+        .send()
+        .unwrap()
+        .save_to_file_verified(&path, "00")
+        .unwrap_err();
+
+    match err.kind() {
+        attohttpc::ErrorKind::ChecksumMismatch { expected, .. } => assert_eq!(expected, "00"),
+        kind => panic!("unexpected error: {:?}", kind),
+    }
+    assert!(!path.exists());
+    assert!(!dir.join("out.txt.part").exists());
+}
+
+#[test]
+fn test_save_to_dir_content_disposition() {
+    let port = serve_once(
+        b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nContent-Disposition: attachment; filename=\"../report.txt\"\r\n\r\nhello",
+    );
+    let dir = temp_dir("dir-disposition");
+
+    let path = attohttpc::get(format!("http://localhost:{port}/download"))
+        .send()
+        .unwrap()
+        .save_to_dir(&dir)
+        .unwrap();
+
+    assert_eq!(path, dir.join("report.txt"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+}
+
+#[test]
+fn test_save_to_dir_url_fallback() {
+    let port = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let dir = temp_dir("dir-url");
+
+    let path = attohttpc::get(format!("http://localhost:{port}/files/data%20set.csv"))
+        .send()
+        .unwrap()
+        .save_to_dir(&dir)
+        .unwrap();
+
+    assert_eq!(path, dir.join("data set.csv"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+}
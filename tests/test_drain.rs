@@ -0,0 +1,84 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+const CHUNK: &[u8] = &[b'x'; 64 * 1024];
+
+/// Counts the bytes of the body taken by the server, reported once the body is dropped, either
+/// because it was sent entirely or because the connection was closed.
+struct Counter {
+    sent: usize,
+    total: usize,
+    report: Sender<(usize, usize)>,
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        let _ = self.report.send((self.sent, self.total));
+    }
+}
+
+async fn make_server() -> Result<(u16, Receiver<(usize, usize)>), anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+    let (send, recv) = channel();
+
+    let body = warp::path!("body" / usize).map(move |chunks: usize| {
+        let counter = Counter {
+            sent: 0,
+            total: chunks * CHUNK.len(),
+            report: send.clone(),
+        };
+        let stream = futures_util::stream::unfold(counter, |mut counter| async move {
+            if counter.sent == counter.total {
+                return None;
+            }
+            counter.sent += CHUNK.len();
+            Some((Ok::<_, Infallible>(CHUNK), counter))
+        });
+        http::Response::new(hyper::Body::wrap_stream(stream))
+    });
+
+    let server = warp::serve(body).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok((local_addr.port(), recv))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_drain_on_drop_completes_body() -> Result<(), anyhow::Error> {
+    let (port, recv) = make_server().await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/body/64"))
+        .drain_on_drop(16 * 1024 * 1024)
+        .send()?;
+    assert!(resp.is_success());
+    drop(resp);
+
+    let (sent, total) = recv.recv_timeout(Duration::from_secs(5))?;
+    assert_eq!(sent, total);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_drop_without_drain_closes_connection() -> Result<(), anyhow::Error> {
+    let (port, recv) = make_server().await?;
+
+    // The body is much larger than what the socket buffers can hold.
+    let resp = attohttpc::get(format!("http://localhost:{port}/body/512"))
+        .drain_on_drop(1024)
+        .send()?;
+    drop(resp);
+
+    let (sent, total) = recv.recv_timeout(Duration::from_secs(5))?;
+    assert!(sent < total);
+
+    Ok(())
+}
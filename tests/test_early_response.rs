@@ -0,0 +1,57 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+const BODY_LEN: usize = 50 * 1024 * 1024;
+
+#[test]
+fn test_early_response_during_upload() -> Result<(), anyhow::Error> {
+    let (port, server) = raw::start_raw_server([
+        b"HTTP/1.1 413 Payload Too Large\r\nContent-Length: 8\r\nConnection: close\r\n\r\ntoo big!",
+    ]);
+
+    let resp = attohttpc::post(format!("http://localhost:{port}/upload"))
+        .bytes(vec![b'x'; BODY_LEN])
+        .send()?;
+    server.join().unwrap();
+
+    assert_eq!(resp.status(), attohttpc::StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(resp.upload_interrupted());
+    assert_eq!(resp.text()?, "too big!");
+
+    Ok(())
+}
+
+#[test]
+fn test_connection_closed_during_upload() {
+    let (port, server) = raw::start_raw_server([b""]);
+
+    let err = attohttpc::post(format!("http://localhost:{port}/upload"))
+        .bytes(vec![b'x'; BODY_LEN])
+        .send()
+        .unwrap_err();
+    server.join().unwrap();
+
+    match err.kind() {
+        attohttpc::ErrorKind::Io(err) => assert!(matches!(
+            err.kind(),
+            std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset
+        )),
+        kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_complete_upload() -> Result<(), anyhow::Error> {
+    let (port, server) =
+        raw::start_raw_server([b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"]);
+
+    let resp = attohttpc::post(format!("http://localhost:{port}/upload"))
+        .bytes(b"small")
+        .send()?;
+    server.join().unwrap();
+
+    assert!(!resp.upload_interrupted());
+    assert_eq!(resp.text()?, "ok");
+
+    Ok(())
+}
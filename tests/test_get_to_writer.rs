@@ -0,0 +1,150 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use attohttpc::header::{HeaderMap, HeaderValue};
+use attohttpc::{ErrorKind, GetOptions, StatusCode};
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let file = warp::path("file").map(|| {
+        http::Response::builder()
+            .header("Content-Type", "text/plain")
+            .body("hello world")
+    });
+    let moved = warp::path("moved").map(|| warp::redirect(warp::http::Uri::from_static("/file")));
+    let missing = warp::path("missing").map(|| http::Response::builder().status(404).body("not found"));
+    let echo = warp::path("echo")
+        .and(warp::header::optional::<String>("x-token"))
+        .and(warp::header::<String>("user-agent"))
+        .map(|token: Option<String>, agent: String| format!("{} {}", token.unwrap_or_default(), agent));
+    let stream = warp::path("stream").map(|| {
+        let chunks = (0..10).map(|_| Ok::<_, Infallible>("0123456789"));
+        http::Response::new(hyper::Body::wrap_stream(futures_util::stream::iter(chunks)))
+    });
+    let slow = warp::path("slow").and_then(|| async {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        Ok::<_, Infallible>("late")
+    });
+
+    let routes = file.or(moved).or(missing).or(echo).or(stream).or(slow);
+    let server = warp::serve(routes).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_to_writer_follows_redirects() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let mut body = Vec::new();
+    let summary = attohttpc::get_to_writer(
+        format!("http://localhost:{port}/moved"),
+        &mut body,
+        GetOptions::default(),
+    )?;
+
+    assert_eq!(body, b"hello world");
+    assert_eq!(summary.status, StatusCode::OK);
+    assert_eq!(summary.bytes_written, 11);
+    assert_eq!(summary.final_url.as_str(), format!("http://localhost:{port}/file"));
+    assert_eq!(summary.content_type.as_deref(), Some("text/plain"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_to_writer_error_status() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let url = format!("http://localhost:{port}/missing");
+
+    let mut body = Vec::new();
+    let err = attohttpc::get_to_writer(&url, &mut body, GetOptions::default()).unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+    assert!(body.is_empty());
+
+    let options = GetOptions {
+        error_for_status: false,
+        ..Default::default()
+    };
+    let summary = attohttpc::get_to_writer(&url, &mut body, options)?;
+    assert_eq!(summary.status, StatusCode::NOT_FOUND);
+    assert_eq!(body, b"not found");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_to_writer_max_size() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let options = |max_size| GetOptions {
+        max_size: Some(max_size),
+        ..Default::default()
+    };
+
+    // The Content-Length is checked before writing anything.
+    let mut body = Vec::new();
+    let err = attohttpc::get_to_writer(format!("http://localhost:{port}/file"), &mut body, options(10)).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::ResponseTooLarge { max_size: 10 }));
+    assert!(body.is_empty());
+
+    let summary = attohttpc::get_to_writer(format!("http://localhost:{port}/file"), &mut body, options(11))?;
+    assert_eq!(summary.bytes_written, 11);
+
+    // A chunked body is cut at the maximum size.
+    let mut body = Vec::new();
+    let err = attohttpc::get_to_writer(format!("http://localhost:{port}/stream"), &mut body, options(50)).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::ResponseTooLarge { max_size: 50 }));
+    assert_eq!(body.len(), 50);
+
+    let mut body = Vec::new();
+    let summary = attohttpc::get_to_writer(format!("http://localhost:{port}/stream"), &mut body, options(100))?;
+    assert_eq!(summary.bytes_written, 100);
+    assert_eq!(body.len(), 100);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_to_writer_headers() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-token", HeaderValue::from_static("secret"));
+    headers.insert("user-agent", HeaderValue::from_static("downloader"));
+    let options = GetOptions {
+        headers,
+        ..Default::default()
+    };
+
+    let mut body = Vec::new();
+    attohttpc::get_to_writer(format!("http://localhost:{port}/echo"), &mut body, options)?;
+    assert_eq!(body, b"secret downloader");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_to_writer_timeout() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let options = GetOptions {
+        timeout: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+
+    let mut body = Vec::new();
+    let err = attohttpc::get_to_writer(format!("http://localhost:{port}/slow"), &mut body, options).unwrap_err();
+    match err.kind() {
+        ErrorKind::Io(err) => assert_eq!(err.kind(), std::io::ErrorKind::TimedOut),
+        kind => panic!("unexpected error {:?}", kind),
+    }
+
+    Ok(())
+}
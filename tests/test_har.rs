@@ -0,0 +1,168 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use std::net::SocketAddr;
+
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let hello = warp::path("hello").map(|| "hello world");
+    let echo = warp::path("echo")
+        .and(warp::body::bytes())
+        .map(|body: hyper::body::Bytes| body.to_vec());
+
+    let cookie = warp::path("cookie").map(|| warp::reply::with_header("ok", "set-cookie", "session=secret"));
+
+    let server = warp::serve(hello.or(echo).or(cookie)).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+fn assert_timings(entry: &serde_json::Value) {
+    let timings = &entry["timings"];
+    for name in ["blocked", "dns", "connect", "ssl", "send", "wait", "receive"] {
+        assert!(timings[name].as_f64().unwrap() >= -1.0, "{}", name);
+    }
+    assert!(entry["time"].as_f64().unwrap() >= 0.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_har_two_requests() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let recorder = attohttpc::HarRecorder::new().capture_bodies(5);
+    let mut session = attohttpc::Session::new();
+    session.record_to(recorder.clone());
+
+    let text = session
+        .get(format!("http://localhost:{port}/hello"))
+        .param("q", "a b")
+        .send()?
+        .text()?;
+    assert_eq!(text, "hello world");
+    let resp = session
+        .post(format!("http://localhost:{port}/echo"))
+        .text("ping")
+        .send()?;
+    assert_eq!(resp.text()?, "ping");
+    assert_eq!(recorder.len(), 2);
+
+    let mut output = Vec::new();
+    recorder.write_to(&mut output)?;
+    let har: serde_json::Value = serde_json::from_slice(&output)?;
+
+    let log = &har["log"];
+    assert_eq!(log["version"], "1.2");
+    assert_eq!(log["creator"]["name"], "attohttpc");
+    let entries = log["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let first = &entries[0];
+    assert!(first["startedDateTime"].as_str().unwrap().ends_with('Z'));
+    assert_timings(first);
+    assert_eq!(first["cache"], serde_json::json!({}));
+    let request = &first["request"];
+    assert_eq!(request["method"], "GET");
+    assert_eq!(request["url"], format!("http://localhost:{port}/hello?q=a+b"));
+    assert_eq!(request["httpVersion"], "HTTP/1.1");
+    assert_eq!(request["cookies"], serde_json::json!([]));
+    assert_eq!(
+        request["queryString"],
+        serde_json::json!([{"name": "q", "value": "a b"}])
+    );
+    assert!(request["headers"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!({"name": "host", "value": format!("localhost:{port}")})));
+    assert_eq!(request["headersSize"], -1);
+    assert_eq!(request["bodySize"], 0);
+    let response = &first["response"];
+    assert_eq!(response["status"], 200);
+    assert_eq!(response["statusText"], "OK");
+    assert_eq!(response["httpVersion"], "HTTP/1.1");
+    assert_eq!(response["redirectURL"], "");
+    assert_eq!(response["bodySize"], 11);
+    assert_eq!(response["content"]["size"], 11);
+    assert_eq!(response["content"]["mimeType"], "text/plain; charset=utf-8");
+    assert_eq!(response["content"]["text"], "hello");
+    assert_eq!(response["content"]["comment"], "truncated to 5 bytes");
+
+    let second = &entries[1];
+    assert_timings(second);
+    assert_eq!(second["request"]["method"], "POST");
+    assert_eq!(second["request"]["bodySize"], 4);
+    assert_eq!(second["response"]["content"]["size"], 4);
+    assert_eq!(second["response"]["content"]["text"], "ping");
+    assert!(second["response"]["content"].get("comment").is_none());
+
+    Ok(())
+}
+
+fn header_value(headers: &serde_json::Value, name: &str) -> String {
+    let header = headers.as_array().unwrap().iter().find(|header| header["name"] == name);
+    header.unwrap()["value"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_har_redacts_credentials() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    for include_credentials in [false, true] {
+        let recorder = attohttpc::HarRecorder::new().include_credentials(include_credentials);
+        let mut session = attohttpc::Session::new();
+        session.record_to(recorder.clone());
+        session
+            .get(format!("http://localhost:{port}/cookie"))
+            .bearer_auth("token")
+            .header("Cookie", "session=old")
+            .send()?
+            .text()?;
+
+        let entry = &recorder.to_json()["log"]["entries"][0];
+        let request_headers = &entry["request"]["headers"];
+        let response_headers = &entry["response"]["headers"];
+        if include_credentials {
+            assert_eq!(header_value(request_headers, "authorization"), "Bearer token");
+            assert_eq!(header_value(request_headers, "cookie"), "session=old");
+            assert_eq!(header_value(response_headers, "set-cookie"), "session=secret");
+        } else {
+            assert_eq!(header_value(request_headers, "authorization"), "[redacted]");
+            assert_eq!(header_value(request_headers, "cookie"), "[redacted]");
+            assert_eq!(header_value(response_headers, "set-cookie"), "[redacted]");
+        }
+        assert_eq!(header_value(request_headers, "host"), format!("localhost:{port}"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_har_status_text() -> Result<(), anyhow::Error> {
+    let (port, server) = raw::start_raw_server([
+        "HTTP/1.1 200 Fine Thanks\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        "HTTP/1.1 404 \r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ]);
+
+    let recorder = attohttpc::HarRecorder::new();
+    let mut session = attohttpc::Session::new();
+    session.record_to(recorder.clone());
+    session.get(format!("http://localhost:{port}/a")).send()?.text()?;
+    session.get(format!("http://localhost:{port}/b")).send()?.text()?;
+    server.join().unwrap();
+
+    let mut output = Vec::new();
+    recorder.write_to(&mut output)?;
+    let har: serde_json::Value = serde_json::from_slice(&output)?;
+    let entries = har["log"]["entries"].as_array().unwrap();
+    assert_eq!(entries[0]["response"]["statusText"], "Fine Thanks");
+    // Without a reason phrase, the canonical one is recorded.
+    assert_eq!(entries[1]["response"]["statusText"], "Not Found");
+
+    Ok(())
+}
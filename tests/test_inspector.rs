@@ -0,0 +1,46 @@
+use std::net::SocketAddr;
+
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let echo = warp::path::full()
+        .and(warp::header::optional::<String>("x-trace-id"))
+        .map(|path: warp::path::FullPath, trace: Option<String>| {
+            format!("{} {}", path.as_str(), trace.unwrap_or_default())
+        });
+
+    let server = warp::serve(echo).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_inspector_rewrites_host() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let body = tokio::task::spawn_blocking(move || {
+        let mut req = attohttpc::get("http://unreachable.invalid/api/items");
+
+        // Redirect the request to the local server, like a middleware for a mock would do.
+        let mut inspector = req.inspect();
+        let url = inspector.url_mut();
+        url.set_host(Some("127.0.0.1")).unwrap();
+        url.set_port(Some(port)).unwrap();
+        inspector
+            .headers_mut()
+            .insert("x-trace-id", attohttpc::header::HeaderValue::from_static("trace-1"));
+
+        req.send()?.text()
+    })
+    .await??;
+
+    assert_eq!(body, "/api/items trace-1");
+
+    Ok(())
+}
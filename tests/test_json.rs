@@ -0,0 +1,89 @@
+use std::net::SocketAddr;
+
+use serde_json::{json, Map, Value};
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+async fn make_echo_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let echo = warp::body::bytes().map(|body: hyper::body::Bytes| body.to_vec());
+
+    let server = warp::serve(echo).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+fn uppercase_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k.to_uppercase(), uppercase_keys(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(uppercase_keys).collect()),
+        value => value,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_json_compact() -> Result<(), anyhow::Error> {
+    let port = make_echo_server().await?;
+
+    let body = attohttpc::post(format!("http://localhost:{port}"))
+        .json(&json!({"hello": [1, 2]}))?
+        .send()?
+        .bytes()?;
+
+    assert_eq!(body, br#"{"hello":[1,2]}"#);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_json_pretty() -> Result<(), anyhow::Error> {
+    let port = make_echo_server().await?;
+
+    let body = attohttpc::post(format!("http://localhost:{port}"))
+        .json_pretty(&json!({"hello": 1}))?
+        .send()?
+        .bytes()?;
+
+    assert_eq!(body, b"{\n  \"hello\": 1\n}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_json_with_custom_serializer() -> Result<(), anyhow::Error> {
+    let port = make_echo_server().await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{port}"))
+        .json_with(&json!({"hello": {"world": 1}}), |value| {
+            serde_json::to_vec(&uppercase_keys(value.clone()))
+        })?
+        .send()?;
+
+    assert_eq!(resp.bytes()?, br#"{"HELLO":{"WORLD":1}}"#);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_json_streaming_with_pretty() -> Result<(), anyhow::Error> {
+    let port = make_echo_server().await?;
+
+    let body = attohttpc::post(format!("http://localhost:{port}"))
+        .json_streaming_with(json!({"hello": 1}), |writer, value| {
+            serde_json::to_writer_pretty(writer, value)
+        })
+        .send()?
+        .bytes()?;
+
+    assert_eq!(body, b"{\n  \"hello\": 1\n}");
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
 use std::net::SocketAddr;
 use std::sync::mpsc::{sync_channel, Receiver};
 use std::thread;
@@ -100,3 +100,183 @@ fn test_multipart_default() -> attohttpc::Result<()> {
 
     Ok(())
 }
+
+type Entry = (String, Option<String>, Vec<u8>);
+
+/// Sends back the name, content type and data of every entry of the received form.
+fn start_collecting_server() -> (u16, Receiver<Vec<Entry>>) {
+    let (send, recv) = sync_channel(1);
+    let rt = Builder::new_multi_thread().enable_io().enable_time().build().unwrap();
+    let filter = warp::path("multipart")
+        .and(warp::header::<Mime>("content-type"))
+        .and(warp::body::bytes())
+        .map(move |ct: Mime, bytes| {
+            let boundary = ct.get_param("boundary").unwrap().to_string();
+            let mut form = Multipart::with_body(Cursor::new(bytes), boundary);
+            let mut entries = Vec::new();
+            form.foreach_entry(|mut entry| {
+                let mut data = Vec::new();
+                entry.data.read_to_end(&mut data).unwrap();
+                let content_type = entry.headers.content_type.as_ref().map(|ct| ct.to_string());
+                entries.push((entry.headers.name.to_string(), content_type, data));
+            })
+            .unwrap();
+            send.send(entries).unwrap();
+            "OK"
+        });
+    let (addr, fut) =
+        rt.block_on(async { warp::serve(filter).bind_ephemeral("0.0.0.0:0".parse::<SocketAddr>().unwrap()) });
+    let port = addr.port();
+    thread::spawn(move || {
+        rt.block_on(fut);
+    });
+    (port, recv)
+}
+
+#[test]
+fn test_multipart_typed_text() -> attohttpc::Result<()> {
+    let form = attohttpc::MultipartBuilder::new()
+        .with_text_typed("doc", "{\"name\":\"Zoë\"}", "application/json")?
+        .with_text("plain", "café")
+        .build()?;
+
+    let (port, recv) = start_collecting_server();
+
+    attohttpc::post(format!("http://localhost:{port}/multipart"))
+        .body(form)
+        .send()?
+        .text()?;
+
+    let entries = recv.recv().unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            (
+                "doc".to_string(),
+                Some("application/json".to_string()),
+                "{\"name\":\"Zoë\"}".as_bytes().to_vec()
+            ),
+            ("plain".to_string(), None, "café".as_bytes().to_vec()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "charsets")]
+#[test]
+fn test_multipart_default_text_charset() -> attohttpc::Result<()> {
+    let form = attohttpc::MultipartBuilder::new()
+        .with_text("utf8", "Grüße, 世界")
+        .default_text_charset(attohttpc::charsets::UTF_8)
+        .build()?;
+
+    let (port, recv) = start_collecting_server();
+
+    attohttpc::post(format!("http://localhost:{port}/multipart"))
+        .body(form)
+        .send()?
+        .text()?;
+
+    let entries = recv.recv().unwrap();
+    assert_eq!(
+        entries,
+        vec![(
+            "utf8".to_string(),
+            Some("text/plain; charset=utf-8".to_string()),
+            "Grüße, 世界".as_bytes().to_vec()
+        )]
+    );
+
+    Ok(())
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("attohttpc-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Sends back the received body with the same content type.
+fn start_echo_server() -> u16 {
+    let rt = Builder::new_multi_thread().enable_io().enable_time().build().unwrap();
+    let filter = warp::path("echo")
+        .and(warp::header::<String>("content-type"))
+        .and(warp::body::bytes())
+        .map(|ct: String, bytes: warp::hyper::body::Bytes| {
+            warp::reply::with_header(bytes.to_vec(), "content-type", ct)
+        });
+    let (addr, fut) =
+        rt.block_on(async { warp::serve(filter).bind_ephemeral("0.0.0.0:0".parse::<SocketAddr>().unwrap()) });
+    let port = addr.port();
+    thread::spawn(move || {
+        rt.block_on(fut);
+    });
+    port
+}
+
+#[test]
+fn test_multipart_dir_round_trip() -> attohttpc::Result<()> {
+    use std::fs;
+
+    let src = temp_dir("round-trip-src");
+    let dest = temp_dir("round-trip-dest");
+    fs::create_dir_all(src.join("nested").join("deeper"))?;
+    fs::write(src.join("top.txt"), "top level")?;
+    fs::write(src.join("nested").join("data.bin"), (0..=255).collect::<Vec<u8>>())?;
+    // Large enough to be split across several reads, and containing something like a delimiter.
+    let large = "line\r\n--not-the-boundary\r\n".repeat(10_000);
+    fs::write(src.join("nested").join("deeper").join("large.txt"), &large)?;
+
+    let form = attohttpc::MultipartBuilder::new().with_dir("", &src, true)?.build()?;
+
+    let port = start_echo_server();
+    let paths = attohttpc::post(format!("http://localhost:{port}/echo"))
+        .body(form)
+        .send()?
+        .unpack_multipart_to_dir(&dest)?;
+
+    assert_eq!(
+        paths,
+        vec![
+            dest.join("nested").join("data.bin"),
+            dest.join("nested").join("deeper").join("large.txt"),
+            dest.join("top.txt"),
+        ]
+    );
+    for path in &paths {
+        let relative = path.strip_prefix(&dest).unwrap();
+        assert_eq!(fs::read(path)?, fs::read(src.join(relative))?);
+    }
+
+    fs::remove_dir_all(&src)?;
+    fs::remove_dir_all(&dest)?;
+    Ok(())
+}
+
+#[test]
+fn test_multipart_from_file() -> attohttpc::Result<()> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(b"Hello, world!")?;
+    let file = attohttpc::MultipartFile::from_file("file", file)?
+        .with_type("text/plain")?
+        .with_filename("hello.txt");
+    let form = attohttpc::MultipartBuilder::new()
+        .with_text("Hello", "world!")
+        .with_file(file)
+        .build()?;
+
+    let (port, recv) = start_server();
+
+    attohttpc::post(format!("http://localhost:{port}/multipart"))
+        .body(form)
+        .send()?
+        .text()?;
+
+    if let Some(err) = recv.recv().unwrap() {
+        panic!("{}", err);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// Answers with the value of the Authorization header, `/redirect` redirects to `127.0.0.1` and
+/// `/local-redirect` to the same host.
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let port = incoming.local_addr()?.port();
+
+    let echo = warp::path("echo")
+        .and(warp::header::optional::<String>("authorization"))
+        .map(|auth: Option<String>| auth.unwrap_or_else(|| "none".to_string()));
+    let redirect = warp::path("redirect").map(move || {
+        http::Response::builder()
+            .header("Location", format!("http://127.0.0.1:{port}/echo"))
+            .status(http::StatusCode::FOUND)
+            .body(String::new())
+    });
+
+    let local_redirect = warp::path("local-redirect").map(|| warp::redirect::found(http::Uri::from_static("/echo")));
+
+    let server = warp::serve(echo.or(redirect).or(local_redirect)).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(port)
+}
+
+fn write_netrc(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("attohttpc-{}-{name}.netrc", std::process::id()));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_netrc_machine() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let path = write_netrc("machine", "machine localhost login alice password secret\n");
+
+    let auth = attohttpc::get(format!("http://localhost:{port}/echo"))
+        .netrc_file(&path)
+        .send()?
+        .text()?;
+    assert_eq!(auth, "Basic YWxpY2U6c2VjcmV0");
+
+    // An explicit Authorization header is not replaced.
+    let auth = attohttpc::get(format!("http://localhost:{port}/echo"))
+        .netrc_file(&path)
+        .bearer_auth("token")
+        .send()?
+        .text()?;
+    assert_eq!(auth, "Bearer token");
+
+    // Disabled by default.
+    let auth = attohttpc::get(format!("http://localhost:{port}/echo")).send()?.text()?;
+    assert_eq!(auth, "none");
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_netrc_redirect_to_other_host() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let path = write_netrc("redirect", "machine localhost login alice password secret\n");
+    let auth = attohttpc::get(format!("http://localhost:{port}/redirect"))
+        .netrc_file(&path)
+        .send()?
+        .text()?;
+    assert_eq!(auth, "none");
+    fs::remove_file(path)?;
+
+    let path = write_netrc(
+        "redirect-default",
+        "machine localhost login alice password secret\ndefault login bob password hunter2\n",
+    );
+    let auth = attohttpc::get(format!("http://localhost:{port}/redirect"))
+        .netrc_file(&path)
+        .send()?
+        .text()?;
+    assert_eq!(auth, "none");
+    fs::remove_file(path)?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_netrc_redirect_same_origin() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let path = write_netrc(
+        "same-origin",
+        "machine localhost login alice password secret
+",
+    );
+    let auth = attohttpc::get(format!("http://localhost:{port}/local-redirect"))
+        .netrc_file(&path)
+        .send()?
+        .text()?;
+    assert_eq!(auth, "Basic YWxpY2U6c2VjcmV0");
+    fs::remove_file(path)?;
+
+    let path = write_netrc("same-origin-default", "default login bob password hunter2\n");
+    let auth = attohttpc::get(format!("http://localhost:{port}/local-redirect"))
+        .netrc_file(&path)
+        .send()?
+        .text()?;
+    assert_eq!(auth, "none");
+    fs::remove_file(path)?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_netrc_missing_file() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let auth = attohttpc::get(format!("http://localhost:{port}/echo"))
+        .netrc_file("/this/file/does/not/exist/.netrc")
+        .send()?
+        .text()?;
+    assert_eq!(auth, "none");
+
+    Ok(())
+}
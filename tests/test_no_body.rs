@@ -0,0 +1,58 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use attohttpc::StatusCode;
+
+/// Answers one request with `head` and keeps the connection open, like a server ignoring
+/// `Connection: close` would.
+fn start_server(head: &str) -> (u16, JoinHandle<Vec<Vec<String>>>) {
+    raw::start_lingering_raw_server([head], Duration::from_millis(1500))
+}
+
+fn assert_empty_body(req: attohttpc::RequestBuilder, status: StatusCode) {
+    let start = Instant::now();
+    let resp = req.read_timeout(Duration::from_secs(5)).send().unwrap();
+    assert_eq!(resp.status(), status);
+    assert_eq!(resp.bytes().unwrap(), b"");
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_head_with_content_length() {
+    let (port, thread) = start_server("HTTP/1.1 200 OK\r\nContent-Length: 1000\r\nContent-Encoding: gzip\r\n\r\n");
+    assert_empty_body(attohttpc::head(format!("http://localhost:{port}")), StatusCode::OK);
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_head_into_head() {
+    let (port, thread) = start_server("HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n");
+    let resp = attohttpc::head(format!("http://localhost:{port}")).send().unwrap();
+    let (status, headers) = resp.into_head();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-length"], "1000");
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_no_content() {
+    let (port, thread) = start_server("HTTP/1.1 204 No Content\r\n\r\n");
+    assert_empty_body(
+        attohttpc::delete(format!("http://localhost:{port}")),
+        StatusCode::NO_CONTENT,
+    );
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_not_modified_with_content_length() {
+    let (port, thread) = start_server("HTTP/1.1 304 Not Modified\r\nContent-Length: 1000\r\n\r\n");
+    assert_empty_body(
+        attohttpc::get(format!("http://localhost:{port}")).header("If-None-Match", "\"abc\""),
+        StatusCode::NOT_MODIFIED,
+    );
+    thread.join().unwrap();
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// Serves three pages under `/items`, the last page links back to the first one on `/loop`.
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let pages = warp::path::param::<String>()
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::header::optional::<String>("x-token"))
+        .map(|kind: String, query: HashMap<String, String>, token: Option<String>| {
+            let page: u32 = query.get("page").and_then(|p| p.parse().ok()).unwrap_or(1);
+            let mut resp = http::Response::builder();
+            if page < 3 {
+                resp = resp.header(
+                    "Link",
+                    format!(
+                        r#"</{}?page={}>; rel="next", </{}?page=3>; rel="last""#,
+                        kind,
+                        page + 1,
+                        kind
+                    ),
+                );
+            } else if kind == "loop" {
+                resp = resp.header("Link", format!(r#"</{kind}?page=1>; rel="next""#));
+            }
+            resp.body(format!("page {} {}", page, token.unwrap_or_default()))
+        });
+
+    let server = warp::serve(pages).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paginate_follows_next_links() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let session = attohttpc::Session::new();
+
+    let pages = attohttpc::paginate(&session, format!("http://localhost:{port}/items"), |req| {
+        req.header("x-token", "secret")
+    })
+    .map(|page| page?.text())
+    .collect::<attohttpc::Result<Vec<_>>>()?;
+
+    assert_eq!(pages, ["page 1 secret", "page 2 secret", "page 3 secret"]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paginate_max_pages() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let session = attohttpc::Session::new();
+
+    let count = attohttpc::paginate(&session, format!("http://localhost:{port}/items"), |req| req)
+        .max_pages(2)
+        .count();
+
+    assert_eq!(count, 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paginate_stops_on_loop() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+    let session = attohttpc::Session::new();
+
+    let count = attohttpc::paginate(&session, format!("http://localhost:{port}/loop?page=1"), |req| req).count();
+
+    assert_eq!(count, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_paginate_stops_on_error() {
+    let session = attohttpc::Session::new();
+
+    let mut pages = attohttpc::paginate(&session, "not a url", |req| req);
+
+    assert!(pages.next().unwrap().is_err());
+    assert!(pages.next().is_none());
+}
@@ -0,0 +1,54 @@
+use std::net::SocketAddr;
+use std::thread;
+
+use attohttpc::header::HOST;
+use http02 as http;
+use tokio_stream::wrappers::TcpListenerStream;
+use warp::Filter;
+
+/// Answers with the value of the Host header, or redirects to `redirect_port` if given.
+async fn make_server(redirect_port: Option<u16>) -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let route = warp::header::<String>("host").map(move |host: String| match redirect_port {
+        Some(port) => http::Response::builder()
+            .header("Location", format!("http://localhost:{port}/"))
+            .status(http::StatusCode::FOUND)
+            .body(String::new()),
+        None => http::Response::builder().body(host),
+    });
+
+    let server = warp::serve(route).serve_incoming(TcpListenerStream::new(incoming));
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_clone_prepared_request() -> Result<(), anyhow::Error> {
+    let target_port = make_server(None).await?;
+    let redirect_port = make_server(Some(target_port)).await?;
+
+    let mut first = attohttpc::get(format!("http://localhost:{redirect_port}/")).prepare();
+    let mut second = first.clone();
+
+    let other = thread::spawn(move || -> attohttpc::Result<String> {
+        let text = second.send()?.text()?;
+        assert_eq!(second.headers()[HOST], format!("localhost:{redirect_port}"));
+        Ok(text)
+    });
+    assert_eq!(first.send()?.text()?, format!("localhost:{target_port}"));
+    assert_eq!(other.join().unwrap()?, format!("localhost:{target_port}"));
+
+    // The Host header of the last hop is not kept once the request is sent.
+    assert_eq!(first.headers()[HOST], format!("localhost:{redirect_port}"));
+    assert_eq!(first.send()?.text()?, format!("localhost:{target_port}"));
+
+    let mut direct = attohttpc::get(format!("http://localhost:{target_port}/")).prepare();
+    assert_eq!(direct.send()?.text()?, format!("localhost:{target_port}"));
+    assert_eq!(first.send()?.text()?, format!("localhost:{target_port}"));
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+#[test]
+fn test_preresolved_skips_resolution() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let thread = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut head = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            head.push(line.trim_end().to_owned());
+        }
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+            .unwrap();
+        head
+    });
+
+    // The .invalid domain never resolves, the request can only reach the server through the
+    // pre-resolved address.
+    let resp = attohttpc::get(format!("http://api.example.invalid:{}/x", addr.port()))
+        .preresolved(vec!["[::1]:1".parse().unwrap(), addr])
+        .send()
+        .unwrap();
+    assert_eq!(resp.text().unwrap(), "ok");
+
+    let head = thread.join().unwrap();
+    assert_eq!(head[0], "GET /x HTTP/1.1");
+    assert!(head.contains(&format!("host: api.example.invalid:{}", addr.port())));
+}
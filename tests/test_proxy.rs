@@ -25,6 +25,55 @@ async fn test_http_url_with_http_proxy() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connection_info() -> Result<(), anyhow::Error> {
+    let remote_port = tools::start_hello_world_server(false).await?;
+    let remote_url = format!("http://localhost:{remote_port}");
+
+    let resp = attohttpc::get(&remote_url).send()?;
+    let info = *resp.connection_info().unwrap();
+    assert_eq!(info.peer.port(), remote_port);
+    assert!(info.peer.ip().is_loopback());
+    assert!(info.local.ip().is_loopback());
+    assert!(!info.proxied);
+    assert_eq!(resp.text()?, "hello");
+
+    let proxy_port = tools::start_proxy_server(false).await?;
+    let proxy_url = Url::parse(&format!("http://localhost:{proxy_port}")).unwrap();
+
+    let mut sess = attohttpc::Session::new();
+    sess.proxy_settings(attohttpc::ProxySettingsBuilder::new().http_proxy(proxy_url).build());
+
+    let resp = sess.get(&remote_url).send()?;
+    let info = *resp.connection_info().unwrap();
+    assert_eq!(info.peer.port(), proxy_port);
+    assert!(info.proxied);
+    assert_eq!(resp.text()?, "hello");
+
+    Ok(())
+}
+
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connection_info_tunnel() -> Result<(), anyhow::Error> {
+    let remote_port = tools::start_hello_world_server(true).await?;
+    let remote_url = format!("https://localhost:{remote_port}");
+
+    let proxy_port = tools::start_proxy_server(false).await?;
+    let proxy_url = Url::parse(&format!("http://localhost:{proxy_port}")).unwrap();
+
+    let mut sess = attohttpc::Session::new();
+    sess.proxy_settings(attohttpc::ProxySettingsBuilder::new().https_proxy(proxy_url).build());
+
+    let resp = sess.get(remote_url).danger_accept_invalid_certs(true).send()?;
+    let info = *resp.connection_info().unwrap();
+    assert_eq!(info.peer.port(), proxy_port);
+    assert!(info.proxied);
+    assert_eq!(resp.text()?, "hello");
+
+    Ok(())
+}
+
 #[cfg(any(feature = "tls-native", feature = "__rustls"))]
 #[tokio::test(flavor = "multi_thread")]
 async fn test_http_url_with_https_proxy() -> Result<(), anyhow::Error> {
@@ -98,6 +147,55 @@ async fn test_https_url_with_https_proxy() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_https_url_via_absolute_form_with_http_proxy() -> Result<(), anyhow::Error> {
+    let proxy_port = tools::start_absolute_form_proxy_server(false).await?;
+    let proxy_url = Url::parse(&format!("http://localhost:{proxy_port}")).unwrap();
+
+    let settings = attohttpc::ProxySettingsBuilder::new()
+        .https_proxy(proxy_url.clone())
+        .build();
+    // Without the setting, the proxy refuses the CONNECT request.
+    assert!(attohttpc::get("https://example.com/a?b=c")
+        .proxy_settings(settings)
+        .send()
+        .is_err());
+
+    let settings = attohttpc::ProxySettingsBuilder::new()
+        .https_proxy(proxy_url)
+        .https_via_absolute_form(true)
+        .build();
+    let resp = attohttpc::get("https://example.com/a?b=c")
+        .proxy_settings(settings)
+        .send()?;
+    assert_eq!(
+        resp.text()?,
+        format!("https://example.com/a?b=c localhost:{proxy_port}")
+    );
+
+    Ok(())
+}
+
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_https_url_via_absolute_form_with_https_proxy() -> Result<(), anyhow::Error> {
+    let proxy_port = tools::start_absolute_form_proxy_server(true).await?;
+    let proxy_url = Url::parse(&format!("https://localhost:{proxy_port}")).unwrap();
+
+    let settings = attohttpc::ProxySettingsBuilder::new()
+        .https_proxy(proxy_url)
+        .https_via_absolute_form(true)
+        .build();
+    let resp = attohttpc::get("https://example.com/a")
+        .proxy_settings(settings)
+        .danger_accept_invalid_certs(true)
+        .send()?;
+    assert_eq!(resp.text()?, format!("https://example.com/a localhost:{proxy_port}"));
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_http_url_with_http_proxy_refusal() -> Result<(), anyhow::Error> {
     let proxy_port = tools::start_refusing_proxy_server(false).await?;
@@ -123,6 +221,7 @@ async fn test_http_url_with_http_proxy_refusal() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
 #[tokio::test(flavor = "multi_thread")]
 async fn test_https_url_with_http_proxy_refusal() -> Result<(), anyhow::Error> {
     let proxy_port = tools::start_refusing_proxy_server(false).await?;
@@ -140,7 +239,7 @@ async fn test_https_url_with_http_proxy_refusal() -> Result<(), anyhow::Error> {
 
     let err = res.err().unwrap();
     match err.kind() {
-        attohttpc::ErrorKind::ConnectError { status_code, body } => {
+        attohttpc::ErrorKind::ConnectError { status_code, body, .. } => {
             assert_eq!(status_code.as_u16(), 400);
             assert_eq!(body, b"bad request");
         }
@@ -196,7 +295,7 @@ async fn test_https_url_with_https_proxy_refusal() -> Result<(), anyhow::Error>
 
     let err = res.err().unwrap();
     match err.kind() {
-        attohttpc::ErrorKind::ConnectError { status_code, body } => {
+        attohttpc::ErrorKind::ConnectError { status_code, body, .. } => {
             assert_eq!(status_code.as_u16(), 400);
             assert_eq!(body, b"bad request");
         }
@@ -205,3 +304,101 @@ async fn test_https_url_with_https_proxy_refusal() -> Result<(), anyhow::Error>
 
     Ok(())
 }
+
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_https_url_with_http_proxy_auth_required() -> Result<(), anyhow::Error> {
+    let proxy_port = tools::start_auth_required_proxy_server().await?;
+    let proxy_url = Url::parse(&format!("http://localhost:{proxy_port}")).unwrap();
+
+    let settings = attohttpc::ProxySettingsBuilder::new().https_proxy(proxy_url).build();
+
+    let mut sess = attohttpc::Session::new();
+    sess.proxy_settings(settings);
+
+    let err = sess.get("https://localhost").send().unwrap_err();
+    assert_eq!(err.status().map(|status| status.as_u16()), Some(407));
+
+    let challenges: Vec<_> = err
+        .response_headers()
+        .unwrap()
+        .get_all("proxy-authenticate")
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect();
+    assert_eq!(challenges, ["Basic realm=\"proxy\"", "Negotiate"]);
+
+    Ok(())
+}
+
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_https_url_with_http_proxy_connect_headers() -> Result<(), anyhow::Error> {
+    let (proxy_port, received) = tools::start_recording_proxy_server().await?;
+    let proxy_url = Url::parse(&format!("http://localhost:{proxy_port}")).unwrap();
+
+    let settings = attohttpc::ProxySettingsBuilder::new()
+        .https_proxy(proxy_url.clone())
+        .build();
+    let mut sess = attohttpc::Session::new();
+    sess.proxy_settings(settings);
+    sess.header("User-Agent", "my-agent");
+    assert!(sess.get("https://localhost").send().is_err());
+
+    let settings = attohttpc::ProxySettingsBuilder::new()
+        .https_proxy(proxy_url)
+        .connect_header("Connection", "keep-alive")
+        .connect_header("Proxy-Connection", "keep-alive")
+        .build();
+    let mut sess = attohttpc::Session::new();
+    sess.proxy_settings(settings);
+    assert!(sess.get("https://localhost").send().is_err());
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+
+    assert_eq!(received[0]["host"], "localhost:443");
+    assert_eq!(received[0]["user-agent"], "my-agent");
+    assert_eq!(received[0]["connection"], "close");
+    assert!(!received[0].contains_key("proxy-connection"));
+
+    assert_eq!(received[1]["host"], "localhost:443");
+    assert!(received[1]["user-agent"].to_str()?.starts_with("attohttpc/"));
+    assert_eq!(received[1]["connection"], "keep-alive");
+    assert_eq!(received[1]["proxy-connection"], "keep-alive");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_proxy_fn_by_port() -> Result<(), anyhow::Error> {
+    let proxied_port = tools::start_hello_world_server(false).await?;
+    let direct_port = tools::start_hello_world_server(false).await?;
+
+    let proxy_port = tools::start_proxy_server(false).await?;
+    let proxy_url = Url::parse(&format!("http://localhost:{proxy_port}")).unwrap();
+
+    let settings = attohttpc::ProxySettingsBuilder::new()
+        .proxy_fn(move |url| match url.port() {
+            Some(port) if port == proxied_port => Some(proxy_url.clone()),
+            _ => None,
+        })
+        .build();
+
+    let mut sess = attohttpc::Session::new();
+    sess.proxy_settings(settings);
+
+    let resp = sess.get(format!("http://localhost:{proxied_port}")).send()?;
+    let info = *resp.connection_info().unwrap();
+    assert_eq!(info.peer.port(), proxy_port);
+    assert!(info.proxied);
+    assert_eq!(resp.text()?, "hello");
+
+    let resp = sess.get(format!("http://localhost:{direct_port}")).send()?;
+    let info = *resp.connection_info().unwrap();
+    assert_eq!(info.peer.port(), direct_port);
+    assert!(!info.proxied);
+    assert_eq!(resp.text()?, "hello");
+
+    Ok(())
+}
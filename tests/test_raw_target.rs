@@ -0,0 +1,55 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use attohttpc::{Method, RequestBuilder, Uri};
+
+#[test]
+fn test_raw_target_on_the_wire() {
+    let (port, thread) = raw::start_raw_server(["HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"]);
+
+    let uri: Uri = format!("http://localhost:{port}/bucket//key%2Fpart/./x/../y?X-Signature=a%2Fb%3D")
+        .parse()
+        .unwrap();
+    let resp = RequestBuilder::try_new_uri(Method::GET, uri).unwrap().send().unwrap();
+    assert_eq!(resp.text().unwrap(), "ok");
+
+    let heads = thread.join().unwrap();
+    assert_eq!(
+        heads[0][0],
+        "GET /bucket//key%2Fpart/./x/../y?X-Signature=a%2Fb%3D HTTP/1.1"
+    );
+}
+
+#[test]
+fn test_raw_target_redirection_uses_url() {
+    let (port, thread) = raw::start_raw_server([
+        "HTTP/1.1 302 Found\r\nLocation: next/./z\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+    ]);
+
+    let uri: Uri = format!("http://localhost:{port}/a//./b").parse().unwrap();
+    let resp = RequestBuilder::try_new_uri(Method::GET, uri).unwrap().send().unwrap();
+    assert_eq!(resp.text().unwrap(), "ok");
+
+    let heads = thread.join().unwrap();
+    assert_eq!(heads[0][0], "GET /a//./b HTTP/1.1");
+    assert_eq!(heads[1][0], "GET /a//next/z HTTP/1.1");
+}
+
+#[test]
+fn test_absolute_form_on_the_wire() {
+    let (port, thread) = raw::start_raw_server(["HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"]);
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/gateway/path?q=1"))
+        .absolute_form(true)
+        .send()
+        .unwrap();
+    assert_eq!(resp.text().unwrap(), "ok");
+
+    let head = &thread.join().unwrap()[0];
+    assert_eq!(
+        head[0],
+        format!("GET http://localhost:{port}/gateway/path?q=1 HTTP/1.1")
+    );
+    assert!(head.contains(&format!("host: localhost:{port}")));
+}
@@ -1,16 +1,33 @@
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use attohttpc::ErrorKind;
 use http02 as http;
 use tokio_stream::wrappers::TcpListenerStream;
 use warp::Filter;
 
+static HOPS: AtomicUsize = AtomicUsize::new(0);
+
 async fn make_server() -> Result<u16, anyhow::Error> {
     let addr = SocketAddr::from(([127, 0, 0, 1], 0));
     let incoming = tokio::net::TcpListener::bind(&addr).await?;
     let local_addr = incoming.local_addr()?;
 
     let a = warp::path("301").map(|| warp::redirect::redirect(http::Uri::from_static("/301")));
+    let chain_1 = warp::path("chain1").map(|| warp::redirect::found(http::Uri::from_static("/chain2")));
+    let chain_2 = warp::path("chain2").map(|| warp::redirect::see_other(http::Uri::from_static("/done")));
+    let done = warp::path("done").map(|| "done");
+    let to_signed = warp::path("to-signed").map(|| warp::redirect::found(http::Uri::from_static("/signed")));
+    let signed = warp::path("signed").and(warp::header::<String>("x-signature"));
+    let loop_a = warp::path("a").map(|| warp::redirect::found(http::Uri::from_static("/b")));
+    let loop_b = warp::path("b").map(|| warp::redirect::found(http::Uri::from_static("/a")));
+    let count = warp::path("count").map(|| {
+        let hop = HOPS.fetch_add(1, Ordering::SeqCst);
+        http::Response::builder()
+            .header("Location", format!("/count?hop={}", hop + 1))
+            .status(http::StatusCode::FOUND)
+            .body("")
+    });
     let b = warp::path("304").map(|| {
         http::Response::builder()
             .header("Location", "/304")
@@ -18,7 +35,18 @@ async fn make_server() -> Result<u16, anyhow::Error> {
             .body("")
     });
 
-    let server = warp::serve(a.or(b)).serve_incoming(TcpListenerStream::new(incoming));
+    let server = warp::serve(
+        a.or(b)
+            .or(loop_a)
+            .or(loop_b)
+            .or(count)
+            .or(chain_1)
+            .or(chain_2)
+            .or(done)
+            .or(to_signed)
+            .or(signed),
+    )
+    .serve_incoming(TcpListenerStream::new(incoming));
     tokio::spawn(server);
 
     Ok(local_addr.port())
@@ -30,7 +58,7 @@ async fn test_redirection_default() -> Result<(), anyhow::Error> {
 
     match attohttpc::get(format!("http://localhost:{port}/301")).send() {
         Err(err) => match err.kind() {
-            ErrorKind::TooManyRedirections => (),
+            ErrorKind::RedirectionLoop { url } => assert_eq!(url, &format!("http://localhost:{port}/301")),
             _ => panic!(),
         },
         _ => panic!(),
@@ -82,3 +110,82 @@ async fn test_redirection_not_redirect() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_redirection_loop() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let err = attohttpc::get(format!("http://localhost:{port}/a"))
+        .max_redirections(20)
+        .send()
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::RedirectionLoop { url } => assert_eq!(url, &format!("http://localhost:{port}/a")),
+        _ => panic!("wrong error {:?}", err),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_redirection_distinct_urls() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let err = attohttpc::get(format!("http://localhost:{port}/count"))
+        .max_redirections(3)
+        .send()
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TooManyRedirections));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_redirection_history() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/chain1")).send()?;
+    assert_eq!(resp.url().path(), "/done");
+
+    let history = resp.redirect_history();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].status, http::StatusCode::FOUND.as_u16());
+    assert_eq!(history[0].url.path(), "/chain1");
+    assert_eq!(history[0].location, "/chain2");
+    assert_eq!(history[1].status, http::StatusCode::SEE_OTHER.as_u16());
+    assert_eq!(history[1].url.path(), "/chain2");
+    assert_eq!(history[1].location, "/done");
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/done")).send()?;
+    assert!(resp.redirect_history().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_redirection_signed_again() -> Result<(), anyhow::Error> {
+    use std::io::{self, Write};
+
+    use attohttpc::header::HeaderMap;
+    use attohttpc::Method;
+    use url::Url;
+
+    let port = make_server().await?;
+
+    let signer = |_: &Method,
+                  url: &Url,
+                  headers: &mut HeaderMap,
+                  _: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>|
+     -> attohttpc::Result<()> {
+        let signature = format!("{} {}", headers["host"].to_str().unwrap(), url.path());
+        headers.insert("x-signature", signature.parse().unwrap());
+        Ok(())
+    };
+
+    let resp = attohttpc::get(format!("http://localhost:{port}/to-signed"))
+        .sign_with(signer)
+        .send()?;
+    assert_eq!(resp.text()?, format!("localhost:{port} /signed"));
+
+    Ok(())
+}
@@ -1,16 +1,12 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
 use std::io;
-use std::net::TcpListener;
-use std::thread;
 use std::time::Duration;
 
 #[test]
 fn request_fails_due_to_read_timeout() {
-    let listener = TcpListener::bind("localhost:0").unwrap();
-    let port = listener.local_addr().unwrap().port();
-    let thread = thread::spawn(move || {
-        let _stream = listener.accept().unwrap();
-        thread::sleep(Duration::from_millis(500));
-    });
+    let (port, thread) = raw::start_lingering_raw_server([b""], Duration::from_millis(500));
 
     let result = attohttpc::get(format!("http://localhost:{port}"))
         .read_timeout(Duration::from_millis(100))
@@ -30,14 +26,34 @@ fn request_fails_due_to_read_timeout() {
     thread.join().unwrap();
 }
 
+#[test]
+fn body_read_fails_due_to_timeout() {
+    use std::io::Read;
+
+    let (port, thread) = raw::start_lingering_raw_server(
+        [b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello"],
+        Duration::from_millis(1500),
+    );
+
+    // The read timeout is longer than the overall timeout, so only the timeout thread, which
+    // shuts the socket down while this thread is blocked reading it, can end the read early.
+    let resp = attohttpc::get(format!("http://localhost:{port}"))
+        .timeout(Duration::from_millis(300))
+        .read_timeout(Duration::from_secs(5))
+        .send()
+        .unwrap();
+
+    let mut body = Vec::new();
+    let err = resp.split().2.read_to_end(&mut body).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert_eq!(body, b"hello");
+
+    thread.join().unwrap();
+}
+
 #[test]
 fn request_fails_due_to_timeout() {
-    let listener = TcpListener::bind("localhost:0").unwrap();
-    let port = listener.local_addr().unwrap().port();
-    let thread = thread::spawn(move || {
-        let _stream = listener.accept().unwrap();
-        thread::sleep(Duration::from_millis(500));
-    });
+    let (port, thread) = raw::start_lingering_raw_server([b""], Duration::from_millis(500));
 
     let result = attohttpc::get(format!("http://localhost:{port}"))
         .timeout(Duration::from_millis(100))
@@ -56,3 +72,103 @@ fn request_fails_due_to_timeout() {
 
     thread.join().unwrap();
 }
+
+#[test]
+fn close_delimited_body_ends_after_grace() {
+    use std::time::Instant;
+
+    // Keep the connection open without sending anything else.
+    let (port, thread) = raw::start_lingering_raw_server(
+        [b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello"],
+        Duration::from_millis(1500),
+    );
+
+    let start = Instant::now();
+    let resp = attohttpc::get(format!("http://localhost:{port}"))
+        .read_timeout(Duration::from_secs(5))
+        .close_read_grace(Some(Duration::from_millis(200)))
+        .send()
+        .unwrap();
+
+    assert_eq!(resp.text().unwrap(), "hello");
+    assert!(start.elapsed() < Duration::from_millis(1000));
+
+    thread.join().unwrap();
+}
+
+#[test]
+fn close_delimited_body_without_grace_times_out() {
+    use std::io::Read;
+
+    let (port, thread) = raw::start_lingering_raw_server(
+        [b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello"],
+        Duration::from_millis(1000),
+    );
+
+    let resp = attohttpc::get(format!("http://localhost:{port}"))
+        .read_timeout(Duration::from_millis(200))
+        .send()
+        .unwrap();
+
+    let mut body = Vec::new();
+    let err = resp.split().2.read_to_end(&mut body).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ));
+    assert_eq!(body, b"hello");
+
+    thread.join().unwrap();
+}
+
+#[test]
+fn session_max_timeouts_clamp_requests() {
+    let mut sess = attohttpc::Session::new();
+    sess.max_timeout(Duration::from_secs(5));
+    sess.max_connect_timeout(Duration::from_secs(2));
+    sess.max_read_timeout(Duration::from_secs(3));
+    sess.read_timeout(Duration::from_secs(60));
+
+    let req = sess
+        .get("http://localhost")
+        .timeout(Duration::from_secs(600))
+        .connect_timeout(Duration::from_secs(600))
+        .prepare();
+    assert_eq!(req.timeout(), Some(Duration::from_secs(5)));
+    assert_eq!(req.connect_timeout(), Duration::from_secs(2));
+    assert_eq!(req.read_timeout(), Duration::from_secs(3));
+
+    // The maximum timeout is the default one, and shorter timeouts are kept.
+    let req = sess
+        .get("http://localhost")
+        .connect_timeout(Duration::from_secs(1))
+        .prepare();
+    assert_eq!(req.timeout(), Some(Duration::from_secs(5)));
+    assert_eq!(req.connect_timeout(), Duration::from_secs(1));
+}
+
+#[test]
+fn session_max_timeout_bounds_slow_request() {
+    use std::time::Instant;
+
+    let (port, thread) = raw::start_lingering_raw_server([b""], Duration::from_millis(1500));
+
+    let mut sess = attohttpc::Session::new();
+    sess.max_timeout(Duration::from_millis(200));
+
+    let start = Instant::now();
+    let err = sess
+        .get(format!("http://localhost:{port}"))
+        .timeout(Duration::from_secs(600))
+        .read_timeout(Duration::from_secs(600))
+        .send()
+        .unwrap_err();
+
+    match err.kind() {
+        attohttpc::ErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+        err => panic!("Unexpected error: {:?}", err),
+    }
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    thread.join().unwrap();
+}
@@ -0,0 +1,86 @@
+#![cfg(any(feature = "tls-native", feature = "__rustls"))]
+
+use std::io::prelude::*;
+use std::net::TcpListener;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use tokio_rustls::rustls::{ServerConfig, ServerConnection, StreamOwned};
+
+/// Serves a single HTTPS request and reports the server name sent by the client, and the request.
+fn start_server() -> (u16, mpsc::Receiver<(Option<String>, String)>) {
+    let certs = rustls_pemfile::certs(&mut &include_bytes!("tools/cert.pem")[..])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = rustls_pemfile::private_key(&mut &include_bytes!("tools/key.pem")[..])
+        .unwrap()
+        .unwrap();
+    let config = Arc::new(
+        ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap(),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (sock, _) = listener.accept().unwrap();
+        let conn = ServerConnection::new(config).unwrap();
+        let mut stream = StreamOwned::new(conn, sock);
+
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => request.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        let server_name = stream.conn.server_name().map(str::to_owned);
+        let _ = tx.send((server_name, String::from_utf8_lossy(&request).into_owned()));
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello");
+        stream.conn.send_close_notify();
+        let _ = stream.conn.complete_io(&mut stream.sock);
+    });
+
+    (port, rx)
+}
+
+// The test certificate has expired, so only the name sent by the client can be checked.
+
+#[test]
+fn test_tls_server_name_override() -> attohttpc::Result {
+    let (port, rx) = start_server();
+
+    let body = attohttpc::get(format!("https://127.0.0.1:{port}/"))
+        .danger_accept_invalid_certs(true)
+        .tls_server_name("localhost")
+        .send()?
+        .text()?;
+    assert_eq!(body, "hello");
+
+    let (server_name, request) = rx.recv().unwrap();
+    assert_eq!(server_name.as_deref(), Some("localhost"));
+    assert!(request.contains(&format!("host: 127.0.0.1:{port}\r\n")));
+
+    Ok(())
+}
+
+#[test]
+fn test_tls_server_name_from_url() -> attohttpc::Result {
+    let (port, rx) = start_server();
+
+    let body = attohttpc::get(format!("https://localhost:{port}/"))
+        .danger_accept_invalid_certs(true)
+        .send()?
+        .text()?;
+    assert_eq!(body, "hello");
+
+    let (server_name, _) = rx.recv().unwrap();
+    assert_eq!(server_name.as_deref(), Some("localhost"));
+
+    Ok(())
+}
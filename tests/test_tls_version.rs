@@ -0,0 +1,101 @@
+#![cfg(any(feature = "tls-native", feature = "__rustls"))]
+
+use std::io::prelude::*;
+use std::net::TcpListener;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use attohttpc::{ErrorKind, TlsVersion};
+use tokio_rustls::rustls::version::{TLS12, TLS13};
+use tokio_rustls::rustls::{ServerConfig, ServerConnection, StreamOwned, SupportedProtocolVersion};
+
+/// Serves a single HTTPS request and reports the ALPN protocol negotiated by the client.
+fn start_server(
+    versions: &[&'static SupportedProtocolVersion],
+    alpn_protocols: &[&[u8]],
+) -> (u16, mpsc::Receiver<Option<Vec<u8>>>) {
+    let certs = rustls_pemfile::certs(&mut &include_bytes!("tools/cert.pem")[..])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = rustls_pemfile::private_key(&mut &include_bytes!("tools/key.pem")[..])
+        .unwrap()
+        .unwrap();
+    let mut config = ServerConfig::builder_with_protocol_versions(versions)
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    config.alpn_protocols = alpn_protocols.iter().map(|protocol| protocol.to_vec()).collect();
+    let config = Arc::new(config);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (sock, _) = listener.accept().unwrap();
+        let conn = ServerConnection::new(config).unwrap();
+        let mut stream = StreamOwned::new(conn, sock);
+
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => request.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        let _ = tx.send(stream.conn.alpn_protocol().map(|protocol| protocol.to_vec()));
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello");
+        stream.conn.send_close_notify();
+        let _ = stream.conn.complete_io(&mut stream.sock);
+    });
+
+    (port, rx)
+}
+
+#[test]
+fn test_max_tls_version_below_server_fails() {
+    let (port, _) = start_server(&[&TLS13], &[]);
+
+    let err = attohttpc::get(format!("https://localhost:{port}"))
+        .danger_accept_invalid_certs(true)
+        .max_tls_version(TlsVersion::Tls1_2)
+        .send()
+        .unwrap_err();
+
+    match err.kind() {
+        ErrorKind::Tls(_) => (),
+        kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_min_tls_version_matching_server() -> attohttpc::Result {
+    let (port, _) = start_server(&[&TLS13], &[]);
+
+    let body = attohttpc::get(format!("https://localhost:{port}"))
+        .danger_accept_invalid_certs(true)
+        .min_tls_version(TlsVersion::Tls1_3)
+        .send()?
+        .text()?;
+
+    assert_eq!(body, "hello");
+
+    Ok(())
+}
+
+#[test]
+fn test_alpn_protocols_offered() -> attohttpc::Result {
+    let (port, alpn) = start_server(&[&TLS12, &TLS13], &[b"h2", b"http/1.1"]);
+
+    let body = attohttpc::get(format!("https://localhost:{port}"))
+        .danger_accept_invalid_certs(true)
+        .alpn_protocols(&["http/1.1"])
+        .send()?
+        .text()?;
+
+    assert_eq!(body, "hello");
+    assert_eq!(alpn.recv().unwrap().as_deref(), Some(&b"http/1.1"[..]));
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+use attohttpc::body::{Bytes, ChunkedWithTrailers};
+use attohttpc::header::HeaderName;
+use sha2::{Digest, Sha256};
+
+/// The parts of a chunked request received by the server.
+#[derive(Debug, Default)]
+struct Received {
+    headers: Vec<String>,
+    body: Vec<u8>,
+    trailers: Vec<String>,
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    line.trim_end_matches("\r\n").to_string()
+}
+
+/// Answers one request, decoding its chunked body and trailers by hand since hyper does not
+/// expose the trailers of HTTP/1.1 requests.
+fn start_server() -> (u16, JoinHandle<Received>) {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let thread = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut received = Received::default();
+
+        read_line(&mut reader);
+        loop {
+            let line = read_line(&mut reader);
+            if line.is_empty() {
+                break;
+            }
+            received.headers.push(line.to_lowercase());
+        }
+        loop {
+            let size = usize::from_str_radix(&read_line(&mut reader), 16).unwrap();
+            if size == 0 {
+                break;
+            }
+            let start = received.body.len();
+            received.body.resize(start + size, 0);
+            reader.read_exact(&mut received.body[start..]).unwrap();
+            assert_eq!(read_line(&mut reader), "");
+        }
+        loop {
+            let line = read_line(&mut reader);
+            if line.is_empty() {
+                break;
+            }
+            received.trailers.push(line);
+        }
+
+        (&stream)
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+            .unwrap();
+        received
+    });
+    (port, thread)
+}
+
+#[test]
+fn test_chunked_body_with_sha256_trailer() {
+    let (port, thread) = start_server();
+    let payload: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+
+    let resp = attohttpc::post(format!("http://localhost:{port}"))
+        .body(ChunkedWithTrailers::new(
+            Bytes(&payload),
+            HeaderName::from_static("x-content-sha256"),
+        ))
+        .send()
+        .unwrap();
+    assert_eq!(resp.text().unwrap(), "ok");
+
+    let received = thread.join().unwrap();
+    assert!(received.headers.contains(&"transfer-encoding: chunked".to_string()));
+    assert!(received.headers.contains(&"trailer: x-content-sha256".to_string()));
+    assert_eq!(received.body, payload);
+
+    let digest: String = Sha256::digest(&received.body)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    assert_eq!(received.trailers, [format!("x-content-sha256: {digest}")]);
+}
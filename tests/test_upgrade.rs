@@ -0,0 +1,73 @@
+#[path = "tools/raw.rs"]
+mod raw;
+
+use std::net::SocketAddr;
+
+use attohttpc::header::{SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE};
+use attohttpc::{ErrorKind, StatusCode};
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::{Message, WebSocket};
+
+/// Accepts a WebSocket connection, sends a greeting right away, then echoes one message.
+async fn start_websocket_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let port = listener.local_addr()?.port();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        ws.send(Message::Text("hello".into())).await.unwrap();
+        if let Some(Ok(message)) = ws.next().await {
+            ws.send(message).await.unwrap();
+        }
+        let _ = ws.close(None).await;
+    });
+
+    Ok(port)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_send_upgrade_websocket() -> Result<(), anyhow::Error> {
+    let port = start_websocket_server().await?;
+
+    let (resp, stream) = attohttpc::get(format!("http://localhost:{port}/"))
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+        .header(SEC_WEBSOCKET_VERSION, "13")
+        .prepare()
+        .send_upgrade()?;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(resp.headers()[SEC_WEBSOCKET_ACCEPT], "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+
+    let mut ws = WebSocket::from_raw_socket(stream, Role::Client, None);
+    assert_eq!(ws.read()?, Message::Text("hello".into()));
+    ws.send(Message::Text("ping".into()))?;
+    assert_eq!(ws.read()?, Message::Text("ping".into()));
+
+    Ok(())
+}
+
+#[test]
+fn test_send_upgrade_refused() {
+    let (port, thread) =
+        raw::start_raw_server([b"HTTP/1.1 200 OK\r\nX-Reason: no websocket\r\nContent-Length: 2\r\n\r\nno"]);
+
+    let err = attohttpc::get(format!("http://localhost:{port}/"))
+        .header(UPGRADE, "websocket")
+        .prepare()
+        .send_upgrade()
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::UpgradeRefused { status, headers } => {
+            assert_eq!(*status, StatusCode::OK);
+            assert_eq!(headers["x-reason"], "no websocket");
+        }
+        kind => panic!("unexpected error {:?}", kind),
+    }
+
+    let head = &thread.join().unwrap()[0];
+    assert!(head.contains(&"connection: upgrade".to_owned()));
+    assert!(head.contains(&"upgrade: websocket".to_owned()));
+}
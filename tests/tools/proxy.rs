@@ -3,6 +3,8 @@
 
 use std::convert::Infallible;
 use std::net::SocketAddr;
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+use std::sync::{Arc, Mutex};
 
 use futures_util::future::try_join;
 use http02 as http;
@@ -158,3 +160,104 @@ pub async fn start_refusing_proxy_server(tls: bool) -> Result<u16, hyper::Error>
 
     Ok(addr.port())
 }
+
+/// Starts a proxy which requires authentication and answers every request with a 407.
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+pub async fn start_auth_required_proxy_server() -> Result<u16, hyper::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+    let bound = AddrIncoming::bind(&addr)?;
+    let addr = bound.local_addr();
+
+    async fn handler(_req: Request<Body>) -> http::Result<Response<Body>> {
+        Response::builder()
+            .status(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+            .header("Proxy-Authenticate", "Basic realm=\"proxy\"")
+            .header("Proxy-Authenticate", "Negotiate")
+            .body(Body::from("authentication required"))
+    }
+
+    let make_service = make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(handler)) });
+    let server = Server::builder(bound);
+    tokio::spawn(server.serve(make_service));
+
+    println!("Listening on http://{addr}");
+
+    Ok(addr.port())
+}
+
+/// Starts a proxy which records the headers of the requests it receives and answers them with a 400.
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+pub async fn start_recording_proxy_server() -> Result<(u16, Arc<Mutex<Vec<http::HeaderMap>>>), hyper::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+    let bound = AddrIncoming::bind(&addr)?;
+    let addr = bound.local_addr();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let make_service = {
+        let received = received.clone();
+        make_service_fn(move |_| {
+            let received = received.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    received.lock().unwrap().push(req.headers().clone());
+                    async move {
+                        Response::builder()
+                            .status(StatusCode::BAD_REQUEST)
+                            .body(Body::from("bad request"))
+                    }
+                }))
+            }
+        })
+    };
+    let server = Server::builder(bound);
+    tokio::spawn(server.serve(make_service));
+
+    println!("Listening on http://{addr}");
+
+    Ok((addr.port(), received))
+}
+
+/// Starts a proxy which refuses CONNECT requests and answers the other ones itself, with the target
+/// of the request and its Host header in the body. It serves https URLs like http ones, the way a
+/// proxy terminating TLS would.
+#[cfg(any(feature = "tls-native", feature = "__rustls"))]
+pub async fn start_absolute_form_proxy_server(tls: bool) -> Result<u16, hyper::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+    let bound = AddrIncoming::bind(&addr)?;
+    let addr = bound.local_addr();
+
+    async fn handler(req: Request<Body>) -> http::Result<Response<Body>> {
+        if req.method() == Method::CONNECT {
+            return Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::from("CONNECT is not allowed"));
+        }
+        let host = req.headers().get("host").and_then(|h| h.to_str().ok()).unwrap_or("");
+        Response::builder().body(Body::from(format!("{} {}", req.uri(), host)))
+    }
+
+    if tls {
+        let make_service = make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(handler)) });
+
+        let conf = TlsConfigBuilder::new()
+            .cert(include_bytes!("cert.pem"))
+            .key(include_bytes!("key.pem"))
+            .build()
+            .unwrap();
+        let acceptor = TlsAcceptor::new(conf, bound);
+        let server = Server::builder(acceptor);
+        tokio::spawn(server.serve(make_service));
+    } else {
+        let make_service = make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(handler)) });
+
+        let server = Server::builder(bound);
+        tokio::spawn(server.serve(make_service));
+    };
+
+    println!("Listening on http://{addr}");
+
+    Ok(addr.port())
+}
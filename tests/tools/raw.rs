@@ -0,0 +1,56 @@
+//! Raw-socket server answering with canned responses, for the tests which need control over the
+//! bytes on the wire.
+//!
+//! It is included on its own with `#[path = "tools/raw.rs"] mod raw;`, so that the tests using it
+//! do not build the other tools.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Answers one connection per response, in order, and returns the head of each request.
+///
+/// The server only reads the request head, never its body, and closes the connection right after
+/// writing the response. An empty response closes the connection without answering.
+#[allow(dead_code)]
+pub fn start_raw_server<I, R>(responses: I) -> (u16, JoinHandle<Vec<Vec<String>>>)
+where
+    I: IntoIterator<Item = R>,
+    R: AsRef<[u8]>,
+{
+    start_lingering_raw_server(responses, Duration::ZERO)
+}
+
+/// Like `start_raw_server`, but keeps each connection open for `linger` after writing the
+/// response, like a server ignoring `Connection: close` or stalling would.
+pub fn start_lingering_raw_server<I, R>(responses: I, linger: Duration) -> (u16, JoinHandle<Vec<Vec<String>>>)
+where
+    I: IntoIterator<Item = R>,
+    R: AsRef<[u8]>,
+{
+    let responses: Vec<Vec<u8>> = responses.into_iter().map(|r| r.as_ref().to_vec()).collect();
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let thread = thread::spawn(move || {
+        let mut heads = Vec::new();
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                head.push(line.trim_end().to_owned());
+            }
+            reader.get_mut().write_all(&response).unwrap();
+            thread::sleep(linger);
+            heads.push(head);
+        }
+        heads
+    });
+    (port, thread)
+}
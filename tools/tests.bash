@@ -15,6 +15,7 @@ cargo test --all-features
 cargo test --no-default-features
 cargo test --no-default-features --features basic-auth
 cargo test --no-default-features --features charsets
+cargo test --no-default-features --features checksum
 cargo test --no-default-features --features compress
 cargo test --no-default-features --features compress-zlib
 cargo test --no-default-features --features compress-zlib-ng